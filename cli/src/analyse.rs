@@ -0,0 +1,50 @@
+use ansi_term::Color::*;
+use tract_core::analyser::Analyser;
+use tract_core::TractErrorKind;
+
+use crate::display_graph::*;
+use crate::errors::*;
+use crate::format::Row;
+use crate::{Parameters, SomeModel};
+
+/// Runs the analyser in diagnostic mode, and highlights the node it failed
+/// on, if any.
+pub fn handle(params: Parameters, options: DisplayOptions) -> CliResult<()> {
+    let mut model = match params.tract_model {
+        SomeModel::Inference(model) => model,
+        _ => bail!("analyse needs a model that has not been analysed yet"),
+    };
+    let result = Analyser::new(&mut model)?.with_diagnostic().analyse();
+    let mut display_graph =
+        DisplayGraph::from_model_and_options(&model, options)?.with_graph_def(&params.graph)?;
+    let e = match result {
+        Ok(()) => return display_graph.render(),
+        Err(e) => e,
+    };
+    if let TractErrorKind::AnalyseFailure(failure) = e.kind() {
+        display_graph
+            .add_node_label(failure.node, Red.bold().paint("ANALYSE FAILED").to_string())?;
+        let mut section = vec![Row::Simple(Red.paint(&*failure.message).to_string())];
+        if let Some(rule) = &failure.rule {
+            section.push(Row::Double(format!("{} rule", rule.kind), rule.rule.clone()));
+            for (path, value) in &rule.paths {
+                section.push(Row::Double(path.clone(), value.clone()));
+            }
+            for (tensor, fact) in &rule.facts {
+                section.push(Row::Double(tensor.clone(), format!("{:?}", fact)));
+            }
+            display_graph.add_node_section(failure.node, section)?;
+            let applied = rule
+                .applied
+                .iter()
+                .enumerate()
+                .map(|(ix, r)| Row::Double(format!("Applied #{}", ix), r.clone()))
+                .collect();
+            display_graph.add_node_section(failure.node, applied)?;
+        } else {
+            display_graph.add_node_section(failure.node, section)?;
+        }
+    }
+    display_graph.render()?;
+    Err(e.into())
+}
@@ -0,0 +1,449 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use ansi_term::Color::*;
+use ndarray::{ArrayD, ArrayViewD, Axis};
+use serde_json::{json, Value};
+use tract_core::model::{InferenceModel, Model, TensorInfo, TypedModel, TypedTensorInfo};
+use tract_core::ops::prelude::*;
+use tract_core::plan::{SimplePlan, SimpleState};
+use tract_core::pulse::{PulsedModel, PulsedOutputInfo};
+
+use crate::errors::*;
+use crate::rusage::{get_memory_usage, Duration};
+use crate::{Parameters, SomeModel};
+
+/// Options of the `bench-matrix` subcommand.
+pub struct BenchMatrixOptions {
+    pub max_iters: u64,
+    pub max_time: u64,
+    /// Pulse sizes to try, or None to pick some among the valid ones.
+    pub pulses: Option<Vec<usize>>,
+    /// Length of the streaming axis, if the input fact does not fix it.
+    pub stream_len: usize,
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl BenchMatrixOptions {
+    pub fn from_clap(matches: &clap::ArgMatches) -> CliResult<BenchMatrixOptions> {
+        let pulses = matches
+            .value_of("pulses")
+            .map(|s| s.split(',').map(|p| p.parse()).collect::<Result<Vec<usize>, _>>())
+            .transpose()?;
+        Ok(BenchMatrixOptions {
+            max_iters: matches
+                .value_of("max_iters")
+                .map(|s| s.parse())
+                .transpose()?
+                .unwrap_or(crate::DEFAULT_MAX_ITERS),
+            max_time: matches
+                .value_of("max-time")
+                .map(|s| s.parse())
+                .transpose()?
+                .unwrap_or(crate::DEFAULT_MAX_TIME),
+            pulses,
+            stream_len: matches
+                .value_of("stream-len")
+                .map(|s| s.parse())
+                .transpose()?
+                .unwrap_or(100),
+            atol: matches.value_of("atol").map(|s| s.parse()).transpose()?.unwrap_or(1e-4),
+            rtol: matches.value_of("rtol").map(|s| s.parse()).transpose()?.unwrap_or(1e-3),
+        })
+    }
+}
+
+/// One variant of the model, as built and measured.
+struct Variant {
+    name: String,
+    result: Result<Measure, String>,
+}
+
+struct Measure {
+    nodes: usize,
+    /// Time to process the whole input.
+    time: Duration,
+    /// Plan runs needed for the whole input: one, or one per pulse.
+    calls: usize,
+    /// Growth of the process resident set size, from before the variant is
+    /// built to the peak while it runs.
+    rss_growth: u64,
+    /// Largest absolute difference with the inference model outputs.
+    max_abs: f64,
+    agrees: bool,
+}
+
+/// Handles the `bench-matrix` subcommand: builds the model at each stage of
+/// the pipeline, and pulsed with a few pulse sizes, then benches them on the
+/// same input and checks they agree with the inference model.
+pub fn handle(params: Parameters, options: BenchMatrixOptions) -> CliResult<()> {
+    let raw = match params.tract_model {
+        SomeModel::Inference(ref m) => m,
+        _ => bail!("bench-matrix needs the model as loaded"),
+    };
+
+    let mut inputs = tvec!();
+    for (ix, &input) in raw.inputs()?.iter().enumerate() {
+        let given = params.inputs.as_ref().and_then(|v| v.get(ix)).and_then(|t| t.as_ref());
+        inputs.push(match given {
+            Some(value) => value.as_tensor().clone(),
+            None => {
+                let fact = raw.fact(input)?;
+                crate::tensor::tensor_for_fact(fact, Some(options.stream_len))?
+            }
+        });
+    }
+    let mut fixed = raw.clone();
+    for (&input, tensor) in raw.inputs()?.iter().zip(inputs.iter()) {
+        fixed.set_fact(input, TensorFact::dt_shape(tensor.datum_type(), tensor.shape()))?;
+    }
+
+    info!("Benching inference model");
+    let ((mut measure, reference), rss_growth) =
+        with_rss_growth(|| bench_fixed(&fixed, &inputs, None, &options))?;
+    measure.rss_growth = rss_growth;
+    let reference_time = measure.time;
+    let mut variants = vec![Variant { name: "inference".to_string(), result: Ok(measure) }];
+
+    // each stage is built from scratch so that only one variant is alive at
+    // a time, and the RSS growth is its own
+    let stages: &[(&str, fn(InferenceModel) -> TractResult<TypedModel>)] = &[
+        ("typed", |m| m.into_typed()),
+        ("decluttered", |m| m.into_typed()?.declutter()),
+        ("optimized", |m| m.into_typed()?.declutter()?.codegen()),
+    ];
+    for (name, build) in stages {
+        info!("Benching {} model", name);
+        let result = with_rss_growth(|| {
+            let model = build(fixed.clone())?;
+            bench_fixed(&model, &inputs, Some(&reference), &options).map(|(measure, _)| measure)
+        });
+        let result = result
+            .map(|(measure, rss_growth)| Measure { rss_growth, ..measure })
+            .map_err(|e| e.to_string());
+        variants.push(Variant { name: name.to_string(), result });
+    }
+
+    match stream_axis(raw, &fixed)? {
+        Some(axis) if inputs[0].datum_type() == DatumType::F32 => {
+            let mut streaming = fixed.clone();
+            let input = streaming.inputs()?[0];
+            let fact = tract_core::pulse::streaming_fact(streaming.fact(input)?, axis)?;
+            streaming.set_fact(input, fact)?;
+            let prepared = streaming.into_normalized().map_err(|e| e.to_string()).and_then(|n| {
+                let pulses = match &options.pulses {
+                    Some(pulses) => pulses.clone(),
+                    None => default_pulses(&n).map_err(|e| e.to_string())?,
+                };
+                Ok((n, pulses))
+            });
+            match prepared {
+                Ok((normalized, pulses)) => {
+                    for pulse in pulses {
+                        info!("Benching pulsed model, pulse {}", pulse);
+                        let result = with_rss_growth(|| {
+                            bench_pulsed(&normalized, pulse, &inputs[0], &reference, &options)
+                        });
+                        let result = result
+                            .map(|(measure, rss_growth)| Measure { rss_growth, ..measure })
+                            .map_err(|e| e.to_string());
+                        variants.push(Variant { name: format!("pulse {}", pulse), result });
+                    }
+                }
+                Err(e) => variants.push(Variant { name: "pulsed".to_string(), result: Err(e) }),
+            }
+        }
+        _ => info!("No streaming axis on a single f32 input, skipping pulsed variants"),
+    }
+
+    if params.json {
+        let json: Vec<Value> = variants.iter().map(|v| v.to_json(reference_time)).collect();
+        let ok = variants.iter().all(|v| v.is_ok());
+        crate::json::print(&json!({ "variants": json, "ok": ok }))?;
+    } else {
+        print_matrix(&variants, reference_time);
+    }
+
+    let failed: Vec<&str> = variants.iter().filter(|v| !v.is_ok()).map(|v| &*v.name).collect();
+    if !failed.is_empty() {
+        bail!("Variant(s) failing or disagreeing with the inference model: {}", failed.join(", "))
+    }
+    Ok(())
+}
+
+fn print_matrix(variants: &[Variant], reference_time: Duration) {
+    let mut table = table!([
+        "variant",
+        "nodes",
+        "latency",
+        "throughput",
+        "speedup",
+        "RSS growth",
+        "max abs error",
+        "status"
+    ]);
+    for variant in variants {
+        table.add_row(match &variant.result {
+            Ok(m) => {
+                let status = if m.agrees {
+                    Green.paint("OK").to_string()
+                } else {
+                    Red.paint("DISAGREE").to_string()
+                };
+                row![
+                    variant.name,
+                    m.nodes,
+                    format!("{:.3} ms/call x{}", m.latency() * 1e3, m.calls),
+                    format!("{:.1} inputs/s", 1.0 / m.time.avg_real()),
+                    format!("{:.2}x", reference_time.avg_real() / m.time.avg_real()),
+                    format!("{:.1} MB", m.rss_growth as f64 / 1024.0 / 1024.0),
+                    format!("{:e}", m.max_abs),
+                    status
+                ]
+            }
+            Err(e) => row![variant.name, "", "", "", "", "", "", Red.paint(&**e)],
+        });
+    }
+    table.printstd();
+}
+
+impl Variant {
+    fn is_ok(&self) -> bool {
+        self.result.as_ref().map(|m| m.agrees).unwrap_or(false)
+    }
+
+    fn to_json(&self, reference_time: Duration) -> Value {
+        match &self.result {
+            Ok(m) => json!({
+                "name": self.name,
+                "nodes": m.nodes,
+                "time": crate::json::duration(m.time),
+                "calls": m.calls,
+                "latency": m.latency(),
+                "throughput": 1.0 / m.time.avg_real(),
+                "speedup": reference_time.avg_real() / m.time.avg_real(),
+                "rss_growth": m.rss_growth,
+                "max_abs": m.max_abs,
+                "ok": m.agrees,
+            }),
+            Err(e) => json!({ "name": self.name, "ok": false, "error": e }),
+        }
+    }
+}
+
+impl Measure {
+    /// Time of one plan run.
+    fn latency(&self) -> f64 {
+        self.time.avg_real() / self.calls as f64
+    }
+}
+
+/// The streaming axis of the single input: the one of its fact as loaded, or
+/// the one the stream axis detection finds on the fixed model if there is no
+/// ambiguity.
+fn stream_axis(raw: &InferenceModel, fixed: &InferenceModel) -> CliResult<Option<usize>> {
+    let inputs = raw.inputs()?;
+    if inputs.len() != 1 {
+        return Ok(None);
+    }
+    if let Some(info) = raw.fact(inputs[0])?.stream_info().ok().and_then(|s| s) {
+        return Ok(Some(info.axis));
+    }
+    let candidates = tract_core::pulse::stream_axis_candidates(fixed)?;
+    Ok(if candidates[0].len() == 1 { Some(candidates[0][0]) } else { None })
+}
+
+/// The smallest valid pulse, and 4 and 16 times it when they are valid too.
+fn default_pulses(normalized: &NormalizedModel) -> CliResult<Vec<usize>> {
+    let candidates = PulsedModel::pulse_candidates(normalized, crate::DEFAULT_MAX_PULSE)?;
+    let smallest = candidates.first().ok_or("No valid pulse size found")?.pulse;
+    Ok([1, 4, 16]
+        .iter()
+        .map(|f| f * smallest)
+        .filter(|p| candidates.iter().any(|c| c.pulse == *p))
+        .collect())
+}
+
+/// Runs `f` while sampling the resident set size in the background, and
+/// returns its result with how much the process grew from the start to the
+/// peak size seen.
+///
+/// The memory freed by the previous variants may be reused rather than
+/// claimed from the system, so this is a lower bound of what `f` needs.
+fn with_rss_growth<T>(f: impl FnOnce() -> CliResult<T>) -> CliResult<(T, u64)> {
+    let start = get_memory_usage()?.resident_size;
+    let done = Arc::new(AtomicBool::new(false));
+    let sampler = {
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut peak = start;
+            while !done.load(Ordering::Relaxed) {
+                peak = peak.max(get_memory_usage().map(|u| u.resident_size).unwrap_or(0));
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+            peak
+        })
+    };
+    let result = f();
+    done.store(true, Ordering::Relaxed);
+    let peak = sampler.join().map_err(|_| "RSS sampler panicked")?;
+    Ok((result?, peak - start))
+}
+
+/// Compares the outputs of a variant to the reference ones, returning the
+/// largest absolute error and whether they are all within tolerance.
+fn check(
+    outputs: &[Tensor],
+    reference: &[SharedTensor],
+    options: &BenchMatrixOptions,
+) -> CliResult<(f64, bool)> {
+    if outputs.len() != reference.len() {
+        bail!("Got {} outputs, expected {}", outputs.len(), reference.len())
+    }
+    let mut max_abs: f64 = 0.0;
+    let mut agrees = true;
+    for (ix, (got, expected)) in outputs.iter().zip(reference.iter()).enumerate() {
+        let discrepancy = got
+            .compare(expected, options.atol, options.rtol)
+            .map_err(|e| format!("Output #{}: {}", ix, e))?;
+        max_abs = max_abs.max(discrepancy.max_abs);
+        agrees &= discrepancy.is_ok();
+    }
+    Ok((max_abs, agrees))
+}
+
+fn bench_fixed<TI: TensorInfo>(
+    model: &Model<TI>,
+    inputs: &TVec<Tensor>,
+    reference: Option<&[SharedTensor]>,
+    options: &BenchMatrixOptions,
+) -> CliResult<(Measure, TVec<SharedTensor>)> {
+    let plan = SimplePlan::new(model)?;
+    let mut state = SimpleState::new(&plan)?;
+    let outputs = state.run(inputs.clone())?;
+    let time = crate::profile::bench(options.max_iters, options.max_time, || {
+        state.run(inputs.clone())?;
+        Ok(())
+    })?;
+    let (max_abs, agrees) = match reference {
+        Some(reference) => {
+            let outputs: Vec<Tensor> = outputs.iter().map(|t| t.as_tensor().clone()).collect();
+            check(&outputs, reference, options)?
+        }
+        None => (0.0, true),
+    };
+    let nodes = model.nodes().len();
+    let measure = Measure { nodes, time, calls: 1, rss_growth: 0, max_abs, agrees };
+    Ok((measure, outputs))
+}
+
+type StreamState<'a> =
+    SimpleState<TypedTensorInfo, &'a TypedModel, &'a SimplePlan<TypedTensorInfo, &'a TypedModel>>;
+
+/// How a pulsed model consumes and produces its streams.
+struct Stream {
+    axis: usize,
+    pulse: usize,
+    pulse_shape: Vec<usize>,
+    outputs: TVec<PulsedOutputInfo>,
+}
+
+fn bench_pulsed(
+    normalized: &NormalizedModel,
+    pulse: usize,
+    input: &Tensor,
+    reference: &[SharedTensor],
+    options: &BenchMatrixOptions,
+) -> CliResult<Measure> {
+    let pulsed = PulsedModel::new(normalized, pulse)?;
+    let input_fact = pulsed.input_fact()?;
+    let stream = Stream {
+        axis: input_fact.axis,
+        pulse: input_fact.pulse(),
+        pulse_shape: input_fact.shape.to_vec(),
+        outputs: pulsed.output_info()?,
+    };
+    if stream.outputs.len() != reference.len() {
+        bail!("Pulsed model has {} outputs, expected {}", stream.outputs.len(), reference.len())
+    }
+    let output_lens: Vec<usize> =
+        stream.outputs.iter().zip(reference).map(|(o, r)| r.shape()[o.axis]).collect();
+    let model = pulsed.into_typed()?.codegen()?;
+
+    let plan = SimplePlan::new(&model)?;
+    let mut state = SimpleState::new(&plan)?;
+    let input = input.to_array_view::<f32>()?;
+    let (outputs, calls) = run_stream(&mut state, &stream, &input, &output_lens)?;
+    let time = crate::profile::bench(options.max_iters, options.max_time, || {
+        run_stream(&mut state, &stream, &input, &output_lens)?;
+        Ok(())
+    })?;
+    let (max_abs, agrees) = check(&outputs, reference, options)?;
+    Ok(Measure { nodes: model.nodes().len(), time, calls, rss_growth: 0, max_abs, agrees })
+}
+
+/// Feeds the whole input to the pulsed model, pulse after pulse, and
+/// reassembles the valid part of the outputs, dropping the delay. Returns
+/// the outputs and the number of pulses.
+fn run_stream(
+    state: &mut StreamState,
+    stream: &Stream,
+    input: &ArrayViewD<f32>,
+    output_lens: &[usize],
+) -> CliResult<(Vec<Tensor>, usize)> {
+    state.reset_op_states()?;
+    state.session_state.known_stream_len = None;
+    let stream_len = input.shape()[stream.axis];
+    let max_delay = stream.outputs.iter().map(|o| o.input_delay).max().unwrap_or(0);
+    let max_calls = (stream_len + max_delay) / stream.pulse + 2;
+    let mut chunks: Vec<Vec<ArrayD<f32>>> = vec![vec![]; stream.outputs.len()];
+    let mut produced = vec![0; stream.outputs.len()];
+    let mut calls = 0;
+    while stream
+        .outputs
+        .iter()
+        .zip(output_lens)
+        .zip(&produced)
+        .any(|((o, len), p)| *p < o.delay + len)
+    {
+        if calls > max_calls {
+            bail!("Pulsed model did not produce the whole output after {} pulses", calls)
+        }
+        let offset = calls * stream.pulse;
+        let mut chunk = ArrayD::<f32>::zeros(&*stream.pulse_shape);
+        if offset < stream_len {
+            let count = stream.pulse.min(stream_len - offset);
+            chunk
+                .slice_axis_mut(Axis(stream.axis), (0..count).into())
+                .assign(&input.slice_axis(Axis(stream.axis), (offset..offset + count).into()));
+        }
+        if offset + stream.pulse >= stream_len {
+            state.session_state.known_stream_len = Some(stream_len);
+        }
+        let outputs = state.run(tvec!(chunk.into()))?;
+        calls += 1;
+        for (ix, output) in outputs.iter().enumerate() {
+            let info = &stream.outputs[ix];
+            let output = output.to_array_view::<f32>()?;
+            let start = produced[ix];
+            let valid_start = start.max(info.delay);
+            let valid_end = (start + info.pulse).min(info.delay + output_lens[ix]);
+            if valid_start < valid_end {
+                let range = (valid_start - start..valid_end - start).into();
+                chunks[ix].push(output.slice_axis(Axis(info.axis), range).to_owned());
+            }
+            produced[ix] = start + info.pulse;
+        }
+    }
+    let outputs = chunks
+        .iter()
+        .zip(stream.outputs.iter())
+        .map(|(chunks, info)| {
+            let views: Vec<ArrayViewD<f32>> = chunks.iter().map(|c| c.view()).collect();
+            Ok(ndarray::stack(Axis(info.axis), &views)?.into())
+        })
+        .collect::<CliResult<Vec<Tensor>>>()?;
+    Ok((outputs, calls))
+}
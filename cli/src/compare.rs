@@ -1,26 +1,293 @@
 #![allow(unused_imports)]
 
+use std::collections::HashMap;
+use std::path;
+
 use ansi_term::Color::*;
 
 use log::Level::Info;
-use tract_core::model::OutletId;
+use ndarray::Axis;
+use tract_core::model::{InferenceModel, Model, OutletId, TensorInfo};
+use tract_core::ops::prelude::*;
 use tract_core::plan::{SimplePlan, SimpleState};
+use tract_core::pulse::PulsedModel;
 use tract_core::{SharedTensor, Tensor, TensorFact};
 
 use crate::display_graph::DisplayOptions;
 use crate::errors::*;
 use crate::format::*;
+use crate::regression::{self, Check, Tolerances};
 use crate::utils::*;
-use crate::Parameters;
+use crate::{Parameters, SomeModel};
+
+/// Length of the streaming axis of generated inputs.
+const STREAM_LEN: usize = 100;
 
 /// Handles the `compare` subcommand.
+///
+/// The reference is either the model as loaded, evaluated without any
+/// optimisation ("inference"), tensorflow ("tf"), or stored values: a .npz
+/// file or an ONNX test data set directory.
+pub fn handle(
+    params: Parameters,
+    reference: Option<&str>,
+    tolerances: Tolerances,
+    output_params: DisplayOptions,
+) -> CliResult<()> {
+    match reference {
+        Some("tf") => handle_tensorflow(params, output_params),
+        Some("inference") => handle_stages(params, tolerances),
+        Some(path) if path.ends_with(".npz") => regression::handle(params, path, tolerances),
+        Some(path) => handle_dataset(params, path::Path::new(path), tolerances),
+        None if has_tf_model(&params) => handle_tensorflow(params, output_params),
+        None => handle_stages(params, tolerances),
+    }
+}
+
+#[cfg(feature = "conform")]
+fn has_tf_model(params: &Parameters) -> bool {
+    params.tf_model.is_some()
+}
+
 #[cfg(not(feature = "conform"))]
-pub fn handle(_params: Parameters, _: DisplayOptions) -> CliResult<()> {
-    bail!("Comparison requires the `tensorflow` feature.")
+fn has_tf_model(_params: &Parameters) -> bool {
+    false
+}
+
+#[cfg(not(feature = "conform"))]
+fn handle_tensorflow(_params: Parameters, _: DisplayOptions) -> CliResult<()> {
+    bail!("Comparison with tensorflow requires the `conform` feature.")
+}
+
+/// Checks the model against an ONNX test data set directory: `input_N.pb`
+/// feed the inputs, `output_N.pb` are the expected outputs.
+#[cfg(feature = "onnx")]
+fn handle_dataset(params: Parameters, dir: &path::Path, tolerances: Tolerances) -> CliResult<()> {
+    if !dir.is_dir() {
+        bail!("Reference {} is neither a .npz file nor a directory", dir.display())
+    }
+    let model = params.reference_model.as_ref().ok_or("The model as loaded is not available")?;
+    let mut expected = vec![];
+    let inputs = crate::conform::load_tensors(dir, "input")?;
+    if model.inputs()?.len() != inputs.len() {
+        bail!(
+            "The model has {} inputs, {} has {} input files",
+            model.inputs()?.len(),
+            dir.display(),
+            inputs.len()
+        )
+    }
+    for (input, tensor) in model.inputs()?.iter().zip(inputs) {
+        expected.push((model.node(input.node).name.clone(), tensor));
+    }
+    let outputs = crate::conform::load_tensors(dir, "output")?;
+    if outputs.is_empty() {
+        bail!("No output_0.pb in {}", dir.display())
+    }
+    let output_names = crate::run::output_names(model)?;
+    if output_names.len() != outputs.len() {
+        bail!(
+            "The model has {} outputs, {} has {} output files",
+            output_names.len(),
+            dir.display(),
+            outputs.len()
+        )
+    }
+    for (name, tensor) in output_names.into_iter().zip(outputs) {
+        expected.push((name, tensor));
+    }
+    regression::handle_named(params, &expected, tolerances)
+}
+
+#[cfg(not(feature = "onnx"))]
+fn handle_dataset(_params: Parameters, dir: &path::Path, _: Tolerances) -> CliResult<()> {
+    bail!("Reference {} is not a .npz file (data sets require the `onnx` feature)", dir.display())
+}
+
+/// Compares the values of the nodes of the model, analysed, optimised or
+/// pulsed, to the ones of the nodes of the same name in the model as loaded,
+/// evaluated op by op without any optimisation.
+fn handle_stages(params: Parameters, tolerances: Tolerances) -> CliResult<()> {
+    let reference =
+        params.reference_model.as_ref().ok_or("The model as loaded is not available")?;
+
+    let mut inputs = tvec!();
+    for (ix, &input) in reference.inputs()?.iter().enumerate() {
+        let given = params.inputs.as_ref().and_then(|v| v.get(ix)).and_then(|t| t.as_ref());
+        inputs.push(match given {
+            Some(value) => value.as_tensor().clone(),
+            None => crate::tensor::tensor_for_fact(reference.fact(input)?, Some(STREAM_LEN))?,
+        });
+    }
+
+    info!("Running the reference model");
+    let expected: HashMap<String, TVec<Tensor>> = evaluate(reference, &inputs)?
+        .into_iter()
+        .map(|(n, values)| (reference.node(n).name.clone(), values))
+        .collect();
+
+    info!("Running the compared model");
+    let checks = match &params.tract_model {
+        SomeModel::Inference(m) => checks(m, &evaluate(m, &inputs)?, &expected, &tolerances)?,
+        SomeModel::Typed(m) => checks(m, &evaluate(m, &inputs)?, &expected, &tolerances)?,
+        SomeModel::Normalized(m) => checks(m, &evaluate(m, &inputs)?, &expected, &tolerances)?,
+        SomeModel::Pulsed(_, m) => {
+            checks(m, &evaluate_pulsed(m, &inputs)?, &expected, &tolerances)?
+        }
+    };
+    regression::report(&checks, params.json)
+}
+
+/// Runs the model node by node, keeping the values of every node.
+fn evaluate<TI: TensorInfo>(
+    model: &Model<TI>,
+    inputs: &[Tensor],
+) -> CliResult<HashMap<usize, TVec<Tensor>>> {
+    let plan = SimplePlan::new(model)?;
+    let mut state = SimpleState::new(&plan)?;
+    state.set_inputs(inputs.iter().cloned().collect())?;
+    let mut values = HashMap::new();
+    for &n in &plan.order {
+        if !model.node(n).op_is::<tract_core::ops::source::Source>() {
+            state.compute_one(n)?;
+        }
+        let computed = state.values[n].as_ref().unwrap();
+        values.insert(n, computed.iter().map(|t| t.as_tensor().clone()).collect());
+    }
+    Ok(values)
+}
+
+/// Feeds the whole input to the pulsed model, pulse after pulse, and
+/// reassembles the values of every node along its streaming axis, dropping
+/// the delay.
+fn evaluate_pulsed(
+    model: &PulsedModel,
+    inputs: &[Tensor],
+) -> CliResult<HashMap<usize, TVec<Tensor>>> {
+    if inputs.len() != 1 {
+        bail!("Pulsed comparison needs a single input")
+    }
+    let input_fact = model.input_fact()?;
+    let (axis, pulse) = (input_fact.axis, input_fact.pulse());
+    let input = inputs[0].to_array_view::<f32>()?;
+    let stream_len = input.shape()[axis];
+
+    // the length of each streaming output, and the pulses needed to get it
+    let mut lens = HashMap::new();
+    let mut pulses = 0;
+    for node in model.nodes() {
+        for (slot, output) in node.outputs.iter().enumerate() {
+            let fact = &output.fact;
+            if let Some(len) = fact.dim.eval(stream_len as i32) {
+                let needed = fact.delay + len as usize;
+                pulses = pulses.max((needed + fact.pulse() - 1) / fact.pulse());
+                lens.insert(OutletId::new(node.id, slot), len as usize);
+            }
+        }
+    }
+
+    let plan = SimplePlan::new(model)?;
+    let mut state = SimpleState::new(&plan)?;
+    let mut chunks: HashMap<OutletId, Vec<SharedTensor>> = HashMap::new();
+    for i in 0..pulses {
+        let offset = i * pulse;
+        let mut chunk = ndarray::ArrayD::<f32>::zeros(&*input_fact.shape);
+        if offset < stream_len {
+            let count = pulse.min(stream_len - offset);
+            chunk
+                .slice_axis_mut(Axis(axis), (0..count).into())
+                .assign(&input.slice_axis(Axis(axis), (offset..offset + count).into()));
+        }
+        if offset + pulse >= stream_len {
+            state.session_state.known_stream_len = Some(stream_len);
+        }
+        state.set_input(0, chunk.into())?;
+        for &n in &plan.order {
+            if !model.node(n).op_is::<tract_core::ops::source::Source>() {
+                state.compute_one(n)?;
+            }
+            for (slot, value) in state.values[n].as_ref().unwrap().iter().enumerate() {
+                chunks.entry(OutletId::new(n, slot)).or_default().push(value.clone());
+            }
+        }
+        state.reset_wires()?;
+    }
+
+    let mut values: HashMap<usize, TVec<Tensor>> = HashMap::new();
+    for node in model.nodes() {
+        let mut joined = tvec!();
+        for (slot, output) in node.outputs.iter().enumerate() {
+            let outlet = OutletId::new(node.id, slot);
+            let (len, chunks) = match (lens.get(&outlet), chunks.get(&outlet)) {
+                (Some(len), Some(chunks)) => (len, chunks),
+                _ => break,
+            };
+            let fact = &output.fact;
+            let range = fact.delay..fact.delay + len;
+            let dt = chunks[0].datum_type();
+            joined.push(dispatch_copy!(self::join_t(dt)(chunks, fact.axis, range))?);
+        }
+        if joined.len() == node.outputs.len() {
+            values.insert(node.id, joined);
+        }
+    }
+    Ok(values)
+}
+
+/// Concatenates pulses along the streaming axis, and keeps `range` of it.
+fn join_t<T: Datum + Copy>(
+    chunks: &[SharedTensor],
+    axis: usize,
+    range: std::ops::Range<usize>,
+) -> CliResult<Tensor> {
+    let views = chunks.iter().map(|c| c.to_array_view::<T>()).collect::<TractResult<Vec<_>>>()?;
+    let joined = ndarray::stack(Axis(axis), &views)?;
+    Ok(joined.slice_axis(Axis(axis), range.into()).to_owned().into())
+}
+
+/// Compares the values of the nodes of the model to the ones of the same
+/// name, in evaluation order, so the first failure is the first diverging
+/// node. Intermediate values of a different type or shape are not
+/// comparable and skipped; outputs always are compared.
+fn checks<TI: TensorInfo>(
+    model: &Model<TI>,
+    values: &HashMap<usize, TVec<Tensor>>,
+    expected: &HashMap<String, TVec<Tensor>>,
+    tolerances: &Tolerances,
+) -> CliResult<Vec<Check>> {
+    let outputs = model.outputs()?;
+    let mut checks = vec![];
+    for n in model.eval_order()? {
+        let node = model.node(n);
+        if node.op_is::<tract_core::ops::source::Source>() {
+            continue;
+        }
+        let (got, exp) = match (values.get(&n), expected.get(&node.name)) {
+            (Some(got), Some(exp)) => (got, exp),
+            _ => continue,
+        };
+        for (slot, (got, exp)) in got.iter().zip(exp.iter()).enumerate() {
+            let name = if node.outputs.len() == 1 {
+                node.name.clone()
+            } else {
+                format!("{}:{}", node.name, slot)
+            };
+            if !outputs.contains(&OutletId::new(n, slot))
+                && (got.datum_type() != exp.datum_type() || got.shape() != exp.shape())
+            {
+                debug!("Not comparing {}: {:?} to {:?}", name, got, exp);
+                continue;
+            }
+            let (atol, rtol) = tolerances.for_name(&name);
+            checks.push(Check::new(name, got, exp, atol, rtol));
+        }
+    }
+    Ok(checks)
 }
 
+/// Compares each node of the model to tensorflow, running the graph.
 #[cfg(feature = "conform")]
-pub fn handle(params: Parameters, output_params: DisplayOptions) -> CliResult<()> {
+fn handle_tensorflow(params: Parameters, output_params: DisplayOptions) -> CliResult<()> {
     use format::Row;
 
     let tract = params.tract_model;
@@ -172,11 +439,9 @@ pub fn handle(params: Parameters, output_params: DisplayOptions) -> CliResult<()
     }
 
     if failing.len() > 0 {
-        for f in &failing {
-            display_graph.render_node(&tract.nodes()[*f])?;
-        }
+        display_graph.render_nodes(&failing)?;
         bail!("{} error(s).", failing.len())
-    } else if log_enabled!(Info) {
+    } else if log_enabled!(Info) || display_graph.options.json {
         display_graph.render()?;
     } else {
         println!("{}", Green.paint("Each node passed the comparison."));
@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{fs, path};
+
+use ansi_term::Color::*;
+use prettytable::Table;
+use serde_json::{json, Value};
+use tract_core::model::{InferenceModel, TypedModel};
+use tract_core::ops::prelude::*;
+use tract_core::ops::unimpl::UnimplementedOp;
+use tract_core::{Framework, SimplePlan};
+
+use crate::errors::*;
+
+const STAGES: &[&str] = &["load", "analyse", "declutter", "optimize", "run"];
+
+/// How a test went through one stage of the pipeline.
+enum Status {
+    Pass,
+    Fail(String),
+    Skipped,
+}
+
+/// The outcome of one test of the suite.
+struct Outcome {
+    name: String,
+    stages: Vec<Status>,
+    max_error: Option<f64>,
+    missing_ops: Vec<String>,
+}
+
+/// Handles `conform onnx <dir>`: runs every `model.onnx` found under `dir`
+/// through analyse, declutter, optimize and run against its
+/// `test_data_set_*` folders, and prints the result matrix.
+pub fn handle(
+    dir: &str,
+    atol: f64,
+    rtol: f64,
+    csv: Option<&str>,
+    json: bool,
+) -> CliResult<()> {
+    let mut tests = vec![];
+    find_tests(path::Path::new(dir), &mut tests)?;
+    tests.sort();
+    if tests.len() == 0 {
+        bail!("No model.onnx found under {}", dir)
+    }
+
+    // ops panicking on unsupported cases are reported as failures, silently.
+    // The previous hook is restored even if a panic escapes the runs.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| ()));
+    let outcomes = catch_unwind(AssertUnwindSafe(|| {
+        tests
+            .iter()
+            .map(|test| {
+                let name = test.strip_prefix(dir).unwrap_or(test).to_string_lossy().to_string();
+                info!("Running {}", name);
+                run_one(name, test, atol, rtol)
+            })
+            .collect::<Vec<Outcome>>()
+    }));
+    std::panic::set_hook(hook);
+    let outcomes = outcomes.unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+    if json {
+        crate::json::print(&to_json(&outcomes))?;
+    } else {
+        print_matrix(&outcomes);
+    }
+
+    if let Some(csv) = csv {
+        let mut file = fs::File::create(csv)?;
+        for row in rows(&outcomes) {
+            let row: Vec<String> =
+                row.iter().map(|cell| format!("\"{}\"", cell.replace("\"", "\"\""))).collect();
+            writeln!(file, "{}", row.join(","))?;
+        }
+    }
+    Ok(())
+}
+
+fn to_json(outcomes: &[Outcome]) -> Value {
+    let tests: Vec<Value> = outcomes
+        .iter()
+        .map(|outcome| {
+            let stages: Vec<Value> = STAGES
+                .iter()
+                .zip(outcome.stages.iter())
+                .map(|(stage, status)| match status {
+                    Status::Pass => json!({ "stage": stage, "status": "pass" }),
+                    Status::Fail(e) => json!({ "stage": stage, "status": "fail", "error": e }),
+                    Status::Skipped => json!({ "stage": stage, "status": "skipped" }),
+                })
+                .collect();
+            json!({
+                "name": outcome.name,
+                "stages": stages,
+                "max_error": outcome.max_error,
+                "missing_ops": outcome.missing_ops,
+            })
+        })
+        .collect();
+    let passed: serde_json::Map<String, Value> = STAGES
+        .iter()
+        .enumerate()
+        .map(|(ix, stage)| {
+            (stage.to_string(), outcomes.iter().filter(|o| o.stages[ix].is_pass()).count().into())
+        })
+        .collect();
+    json!({
+        "tests": tests,
+        "passed": passed,
+        "missing_ops": missing_ops_count(outcomes),
+    })
+}
+
+fn missing_ops_count(outcomes: &[Outcome]) -> BTreeMap<&str, usize> {
+    let mut missing: BTreeMap<&str, usize> = BTreeMap::new();
+    for op in outcomes.iter().flat_map(|o| o.missing_ops.iter()) {
+        *missing.entry(op).or_insert(0) += 1;
+    }
+    missing
+}
+
+/// The header and one row of plain text cells per test.
+fn rows(outcomes: &[Outcome]) -> Vec<Vec<String>> {
+    let mut header = vec!["test"];
+    header.extend(STAGES);
+    header.extend(&["max error", "missing ops"]);
+    let mut rows = vec![header.iter().map(|s| s.to_string()).collect::<Vec<_>>()];
+    for outcome in outcomes {
+        let mut cells = vec![outcome.name.clone()];
+        for status in &outcome.stages {
+            cells.push(match status {
+                Status::Pass => "pass".to_string(),
+                Status::Fail(e) => format!("FAIL: {}", e),
+                Status::Skipped => "-".to_string(),
+            });
+        }
+        cells.push(outcome.max_error.map(|e| format!("{:e}", e)).unwrap_or(String::new()));
+        cells.push(outcome.missing_ops.join(" "));
+        rows.push(cells);
+    }
+    rows
+}
+
+fn print_matrix(outcomes: &[Outcome]) {
+    let mut rows = rows(outcomes).into_iter();
+    let mut table = Table::new();
+    table.set_titles(rows.next().unwrap().into());
+    for (outcome, mut cells) in outcomes.iter().zip(rows) {
+        for (cell, status) in cells[1..].iter_mut().zip(outcome.stages.iter()) {
+            match status {
+                Status::Pass => *cell = Green.paint("pass").to_string(),
+                Status::Fail(_) => *cell = Red.paint("FAIL").to_string(),
+                Status::Skipped => (),
+            }
+        }
+        table.add_row(cells.into());
+    }
+    table.printstd();
+
+    for outcome in outcomes {
+        if let Some((stage, Status::Fail(e))) =
+            STAGES.iter().zip(outcome.stages.iter()).find(|(_, s)| s.is_fail())
+        {
+            println!("{} {}: {}", Red.paint(&*outcome.name), stage, e);
+        }
+    }
+
+    println!();
+    for (ix, stage) in STAGES.iter().enumerate() {
+        let passed = outcomes.iter().filter(|o| o.stages[ix].is_pass()).count();
+        println!("{:>10}: {}/{} pass", stage, passed, outcomes.len());
+    }
+    let missing = missing_ops_count(outcomes);
+    if missing.len() > 0 {
+        let missing: Vec<String> =
+            missing.iter().map(|(op, count)| format!("{} ({})", op, count)).collect();
+        println!("Missing ops: {}", missing.join(", "));
+    }
+}
+
+impl Status {
+    fn is_pass(&self) -> bool {
+        match self {
+            Status::Pass => true,
+            _ => false,
+        }
+    }
+
+    fn is_fail(&self) -> bool {
+        match self {
+            Status::Fail(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Collects the directories containing a `model.onnx`, recursively.
+fn find_tests(dir: &path::Path, tests: &mut Vec<path::PathBuf>) -> CliResult<()> {
+    if dir.join("model.onnx").is_file() {
+        tests.push(dir.to_owned());
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_tests(&path, tests)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs a stage, turning errors and panics into a failure message.
+fn stage<T>(f: impl FnOnce() -> CliResult<T>) -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(it)) => Ok(it),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(panic) => Err(panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or("panic".to_string())),
+    }
+}
+
+fn run_one(name: String, dir: &path::Path, atol: f64, rtol: f64) -> Outcome {
+    let mut outcome = Outcome { name, stages: vec![], max_error: None, missing_ops: vec![] };
+    let model = stage(|| Ok(tract_onnx::onnx().model_for_path(dir.join("model.onnx"))?));
+    outcome.push(&model);
+    if let Ok(model) = &model {
+        outcome.missing_ops = missing_ops(model);
+    }
+    let analysed = outcome.record(model, |mut model| {
+        model.analyse()?;
+        let missing = model.missing_type_shape()?;
+        if missing.len() > 0 {
+            bail!("Incomplete inference for {:?}", missing)
+        }
+        Ok(model)
+    });
+    let decluttered = outcome.record(analysed, |model| Ok(model.into_typed()?.declutter()?));
+    let optimized = outcome.record(decluttered, |model| Ok(model.into_optimized()?));
+    let mut max_error = None;
+    // the failure, if any, is recorded in the stages
+    let _ = outcome.record(optimized, |model| {
+        run_datasets(&model, dir, atol, rtol, &mut max_error)
+    });
+    outcome.max_error = max_error;
+    outcome
+}
+
+impl Outcome {
+    /// Records the status of the stage `f`, running it on the result of the
+    /// previous one if that one passed.
+    fn record<T, U>(
+        &mut self,
+        previous: Result<T, String>,
+        f: impl FnOnce(T) -> CliResult<U>,
+    ) -> Result<U, String> {
+        let result = match previous {
+            Ok(it) => stage(|| f(it)),
+            Err(_) => {
+                self.stages.push(Status::Skipped);
+                return Err("skipped".to_string());
+            }
+        };
+        self.push(&result);
+        result
+    }
+
+    fn push<T>(&mut self, result: &Result<T, String>) {
+        self.stages.push(match result {
+            Ok(_) => Status::Pass,
+            Err(e) => Status::Fail(e.clone()),
+        });
+    }
+}
+
+fn missing_ops(model: &InferenceModel) -> Vec<String> {
+    let mut ops: Vec<String> = model
+        .nodes()
+        .iter()
+        .filter(|n| n.op_is::<UnimplementedOp>())
+        .map(|n| {
+            let name = n.op().name();
+            name.trim_start_matches("Unimplemented(").trim_end_matches(")").to_string()
+        })
+        .collect();
+    ops.sort();
+    ops.dedup();
+    ops
+}
+
+/// Loads `prefix_0.pb`, `prefix_1.pb`, and so on, up to the first missing one.
+pub fn load_tensors(dir: &path::Path, prefix: &str) -> CliResult<TVec<Tensor>> {
+    let mut tensors = tvec!();
+    loop {
+        let file = dir.join(format!("{}_{}.pb", prefix, tensors.len()));
+        if !file.exists() {
+            return Ok(tensors);
+        }
+        tensors.push(tract_onnx::tensor::from_reader(fs::File::open(file)?)?);
+    }
+}
+
+/// Runs the model on every `test_data_set_*` of the test directory, failing
+/// on results out of tolerance, and tracking the maximum absolute error.
+fn run_datasets(
+    model: &TypedModel,
+    dir: &path::Path,
+    atol: f64,
+    rtol: f64,
+    max_error: &mut Option<f64>,
+) -> CliResult<()> {
+    let plan = SimplePlan::new(model)?;
+    let mut datasets: Vec<path::PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_dir()
+                && p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with("test_data_set_"))
+                    .unwrap_or(false)
+        })
+        .collect();
+    datasets.sort();
+    if datasets.len() == 0 {
+        bail!("No test_data_set_* folder")
+    }
+    for dataset in datasets {
+        let inputs = load_tensors(&dataset, "input")?;
+        let expected = load_tensors(&dataset, "output")?;
+        let computed = plan.run(inputs)?;
+        if computed.len() != expected.len() {
+            bail!("Got {} outputs, expected {}", computed.len(), expected.len())
+        }
+        for (ix, (got, expected)) in computed.iter().zip(expected.iter()).enumerate() {
+            let discrepancy = got.compare(expected, atol, rtol)?;
+            *max_error = Some(max_error.unwrap_or(0.0).max(discrepancy.max_abs));
+            if !discrepancy.is_ok() {
+                bail!(
+                    "Output #{} has {} value(s) out of tolerance in {:?}",
+                    ix,
+                    discrepancy.failures,
+                    dataset.file_name().unwrap()
+                )
+            }
+        }
+    }
+    Ok(())
+}
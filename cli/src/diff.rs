@@ -0,0 +1,76 @@
+use ansi_term::Color::*;
+use serde_json::json;
+use tract_core::model::InferenceModel;
+use tract_core::TensorFact;
+
+use crate::errors::*;
+use crate::{load_model, model_format};
+
+fn load(name: &str, inputs: &[TensorFact]) -> CliResult<InferenceModel> {
+    let (_, mut model) = load_model(name, model_format(name, None))?;
+    for (ix, fact) in inputs.iter().enumerate() {
+        model.set_input_fact(ix, fact.clone())?;
+    }
+    if let Err(e) = model.analyse() {
+        warn!("{}: analyse failed, comparing partial facts ({})", name, e);
+    }
+    Ok(model)
+}
+
+/// Handles the `diff` subcommand. Input facts apply to both models.
+pub fn handle(first: &str, second: &str, inputs: &[TensorFact], json: bool) -> CliResult<()> {
+    let a = load(first, inputs)?;
+    let b = load(second, inputs)?;
+    let diff = a.diff(&b)?;
+    if json {
+        let changed: Vec<_> = diff
+            .changed
+            .iter()
+            .map(|node| {
+                let changes: Vec<String> = node.changes.iter().map(|c| c.to_string()).collect();
+                json!({ "before": node.before, "after": node.after, "changes": changes })
+            })
+            .collect();
+        let hashes: Vec<_> = [(first, &a), (second, &b)]
+            .iter()
+            .map(|(name, model)| {
+                match (*model).clone().into_typed().and_then(|m| m.content_hash()) {
+                    Ok(hash) => json!({ "model": name, "hash": format!("{:016x}", hash) }),
+                    Err(e) => json!({ "model": name, "hash": null, "error": e.to_string() }),
+                }
+            })
+            .collect();
+        return crate::json::print(&json!({
+            "added": diff.added,
+            "removed": diff.removed,
+            "changed": changed,
+            "hashes": hashes,
+        }));
+    }
+    for name in &diff.removed {
+        println!("{} {}", Red.paint("-"), name);
+    }
+    for name in &diff.added {
+        println!("{} {}", Green.paint("+"), name);
+    }
+    for node in &diff.changed {
+        if node.before == node.after {
+            println!("{} {}", Yellow.paint("~"), node.before);
+        } else {
+            println!("{} {} -> {}", Yellow.paint("~"), node.before, node.after);
+        }
+        for change in &node.changes {
+            println!("    {}", change);
+        }
+    }
+    if diff.is_empty() {
+        println!("No structural difference");
+    }
+    for (name, model) in &[(first, a), (second, b)] {
+        match model.clone().into_typed().and_then(|m| m.content_hash()) {
+            Ok(hash) => println!("{:016x} {}", hash, name),
+            Err(e) => println!("{:16} {} ({})", "no hash", name, e),
+        }
+    }
+    Ok(())
+}
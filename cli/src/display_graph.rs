@@ -3,6 +3,7 @@ use crate::CliResult;
 use crate::SomeGraphDef;
 use ansi_term::Color::*;
 use ansi_term::Style;
+use serde_json::json;
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use tract_core::model::{Model, Node, TensorInfo};
@@ -21,6 +22,7 @@ pub struct DisplayOptions {
     pub op_name: Option<String>,
     pub node_name: Option<String>,
     pub successors: Option<usize>,
+    pub json: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -37,23 +39,21 @@ impl<TI: TensorInfo, M: Borrow<Model<TI>>> DisplayGraph<TI, M> {
         if self.options.quiet {
             return Ok(());
         }
+        let nodes = self.selected_nodes()?;
+        self.render_nodes(&nodes)
+    }
+
+    /// The nodes to display, according to the options.
+    fn selected_nodes(&self) -> CliResult<Vec<usize>> {
         let model = self.model.borrow();
         if let Some(nodes) = &self.options.node_ids {
-            for &node in nodes {
-                let node = &model.nodes()[node];
-                self.render_node(node)?;
-            }
-            return Ok(());
+            return Ok(nodes.clone());
         }
         if let Some(node_name) = &self.options.node_name {
-            if let Ok(node) = model.node_by_name(node_name) {
-                return self.render_node(node);
-            } else {
-                return Ok(());
-            }
+            return Ok(model.node_by_name(node_name).map(|n| vec![n.id]).unwrap_or(vec![]));
         }
-        let node_ids = ::tract_core::model::eval_order(&model)?;
-        for node in node_ids {
+        let mut selected = vec![];
+        for node in ::tract_core::model::eval_order(&model)? {
             let node = &model.nodes()[node];
             if node.op().name() == "Const" && !self.options.konst {
                 continue;
@@ -70,12 +70,51 @@ impl<TI: TensorInfo, M: Borrow<Model<TI>>> DisplayGraph<TI, M> {
             {
                 continue;
             }
-            self.render_node(&node)?
+            selected.push(node.id)
+        }
+        Ok(selected)
+    }
+
+    /// Renders some nodes, as boxes or as a single JSON document.
+    pub fn render_nodes(&self, nodes: &[usize]) -> CliResult<()> {
+        if self.options.json {
+            return crate::json::print(&self.to_json(nodes)?);
+        }
+        for &node in nodes {
+            self.render_node(&self.model.borrow().nodes()[node])?;
         }
         Ok(())
     }
 
+    /// The model inputs and outputs, and the given nodes with their labels
+    /// and sections.
+    pub fn to_json(&self, nodes: &[usize]) -> CliResult<serde_json::Value> {
+        let model = self.model.borrow();
+        let nodes = nodes
+            .iter()
+            .map(|&id| {
+                let mut json = crate::json::node(model, &model.nodes()[id])?;
+                let labels = self.node_labels.get(&id).map(|v| v.as_slice()).unwrap_or(&[]);
+                json["labels"] = labels.iter().map(|l| crate::json::strip_ansi(l)).collect();
+                let sections = self.node_sections.get(&id).map(|v| v.as_slice()).unwrap_or(&[]);
+                json["sections"] = sections
+                    .iter()
+                    .map(|section| section.iter().map(Row::to_json).collect::<Vec<_>>())
+                    .collect();
+                Ok(json)
+            })
+            .collect::<CliResult<Vec<_>>>()?;
+        Ok(json!({
+            "inputs": model.inputs()?.iter().map(|&o| crate::json::outlet(o)).collect::<Vec<_>>(),
+            "outputs": model.outputs()?.iter().map(|&o| crate::json::outlet(o)).collect::<Vec<_>>(),
+            "nodes": nodes,
+        }))
+    }
+
     pub fn render_node(&self, node: &Node<TI>) -> CliResult<()> {
+        if self.options.json {
+            return self.render_nodes(&[node.id]);
+        }
         let bold = Style::new().bold();
         let mut sections: Vec<Vec<Row>> = vec![];
         if let Some(id) = self.model.borrow().inputs()?.iter().position(|n| n.node == node.id) {
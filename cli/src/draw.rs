@@ -1,18 +1,295 @@
-use crate::{ CliResult, SomeModel };
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::errors::*;
+use crate::profile::ProfileData;
+use crate::{ Parameters, ProfilingMode, SomeModel };
 use ansi_term::{Color, Style};
 use box_drawing::light::*;
+use itertools::Itertools;
 use tract_core::model::{ Model, OutletId, TensorInfo };
+use tract_core::ops::konst::Const;
+use tract_core::ops::prelude::Fact;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawFormat {
+    Text,
+    Dot,
+    Svg,
+}
+
+pub struct DrawOptions {
+    pub format: DrawFormat,
+    /// Colour the nodes by the time spent in them.
+    pub profiling: Option<ProfilingMode>,
+    pub output: Option<String>,
+    pub json: bool,
+}
 
-pub fn render(model: &SomeModel) -> CliResult<()> {
-    match model {
-        SomeModel::Inference(m) => render_t(m),
-        SomeModel::Typed(m) => render_t(m),
-        SomeModel::Normalized(m) => render_t(m),
-        SomeModel::Pulsed(_, m) => render_t(m),
+impl DrawOptions {
+    pub fn from_clap(matches: &clap::ArgMatches, json: bool) -> CliResult<DrawOptions> {
+        let format = match matches.value_of("format").unwrap_or("text") {
+            "dot" => DrawFormat::Dot,
+            "svg" => DrawFormat::Svg,
+            _ => DrawFormat::Text,
+        };
+        let profiling = if matches.is_present("profile") {
+            Some(ProfilingMode::from_clap(matches)?)
+        } else {
+            None
+        };
+        let output = matches.value_of("output").map(String::from);
+        Ok(DrawOptions { format, profiling, output, json })
+    }
+}
+
+pub fn render(params: &Parameters, options: DrawOptions) -> CliResult<()> {
+    match &params.tract_model {
+        SomeModel::Inference(m) => render_t(m, params, options),
+        SomeModel::Typed(m) => render_t(m, params, options),
+        SomeModel::Normalized(m) => render_t(m, params, options),
+        SomeModel::Pulsed(_, m) => render_t(m, params, options),
+    }
+}
+
+fn render_t<TI: TensorInfo>(
+    model: &Model<TI>,
+    params: &Parameters,
+    options: DrawOptions,
+) -> CliResult<()> {
+    if options.json {
+        return crate::json::print(&crate::json::model(model)?);
+    }
+    if options.format == DrawFormat::Text {
+        return render_text(model);
     }
+    let profile = match options.profiling {
+        Some(ProfilingMode::Regular { max_iters, max_time })
+        | Some(ProfilingMode::RegularBenching { max_iters, max_time }) => {
+            Some(crate::profile::measure_nodes(model, params, max_iters, max_time)?)
+        }
+        None => None,
+    };
+    let graph = Graph::new(model, profile.as_ref())?;
+    let rendered = match options.format {
+        DrawFormat::Dot => graph.to_dot(),
+        _ => graph.to_svg(),
+    };
+    match &options.output {
+        Some(path) => std::fs::File::create(path)?.write_all(rendered.as_bytes())?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// A node to draw: constant inputs are folded in their consumers.
+struct DrawNode {
+    id: usize,
+    title: String,
+    lines: Vec<String>,
+    /// Share of the time spent in the slowest node.
+    heat: Option<f64>,
+}
+
+/// The model as nodes and edges, ready to be laid out.
+struct Graph {
+    nodes: Vec<DrawNode>,
+    edges: Vec<(usize, usize, Option<usize>)>,
+}
+
+impl Graph {
+    fn new<TI: TensorInfo>(model: &Model<TI>, profile: Option<&ProfileData>) -> CliResult<Graph> {
+        let slowest = profile
+            .map(|p| p.nodes.values().map(|d| d.avg_real()).fold(0.0, f64::max))
+            .unwrap_or(0.0);
+        let sum = profile.map(|p| p.summed().avg_real()).unwrap_or(0.0);
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for n in model.eval_order()? {
+            let node = model.node(n);
+            if node.op_is::<Const>() {
+                continue;
+            }
+            let mut lines = vec![node.name.clone()];
+            for (ix, output) in node.outputs.iter().enumerate() {
+                lines.push(format!("out #{}: {:?}", ix, output.fact));
+            }
+            for (ix, input) in node.inputs.iter().enumerate() {
+                if model.node(input.node).op_is::<Const>() {
+                    let fact = model.fact(*input)?.to_tensor_fact();
+                    let shape = fact.shape.dims().map(|d| format!("{:?}x", d)).join("");
+                    let dt = fact.datum_type.concretize().map(|dt| format!("{:?}", dt));
+                    lines.push(format!("const #{}: {}{}", ix, shape, dt.unwrap_or("?".into())));
+                } else {
+                    let slot = if model.node(input.node).outputs.len() > 1 {
+                        Some(input.slot)
+                    } else {
+                        None
+                    };
+                    edges.push((input.node, n, slot));
+                }
+            }
+            let mut heat = None;
+            if let Some(measure) = profile.and_then(|p| p.nodes.get(&n)) {
+                lines.push(format!(
+                    "{:.3} ms/i ({:.0}%)",
+                    measure.avg_real() * 1e3,
+                    measure.avg_real() / sum * 100.0
+                ));
+                heat = Some(if slowest > 0.0 { measure.avg_real() / slowest } else { 0.0 });
+            }
+            nodes.push(DrawNode { id: n, title: node.op().name().to_string(), lines, heat });
+        }
+        Ok(Graph { nodes, edges })
+    }
+
+    fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph tract {\n  node [shape=box, fontname=monospace];\n");
+        for b in &self.nodes {
+            let mut label = format!("#{} {}", b.id, escape(&b.title));
+            for line in &b.lines {
+                label.push_str("\\n");
+                label.push_str(&escape(line));
+            }
+            let fill = b.heat.map(|h| format!(", style=filled, fillcolor=\"{}\"", heat_color(h)));
+            dot.push_str(&format!(
+                "  n{} [label=\"{}\"{}];\n",
+                b.id,
+                label,
+                fill.unwrap_or(String::new())
+            ));
+        }
+        for (from, to, slot) in &self.edges {
+            match slot {
+                Some(slot) => {
+                    dot.push_str(&format!("  n{} -> n{} [label=\"{}\"];\n", from, to, slot))
+                }
+                None => dot.push_str(&format!("  n{} -> n{};\n", from, to)),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Lays the nodes out in layers, by longest path from the inputs, and
+    /// renders them as SVG.
+    fn to_svg(&self) -> String {
+        const CHAR_WIDTH: usize = 7;
+        const LINE_HEIGHT: usize = 16;
+        const GAP: usize = 24;
+        let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+
+        let mut layers: HashMap<usize, usize> = HashMap::new();
+        for b in &self.nodes {
+            let layer = self
+                .edges
+                .iter()
+                .filter(|e| e.1 == b.id)
+                .map(|e| layers.get(&e.0).map(|l| l + 1).unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+            layers.insert(b.id, layer);
+        }
+        let layer_count = layers.values().max().map(|l| l + 1).unwrap_or(0);
+        let size = |b: &DrawNode| {
+            let title = format!("#{} {}", b.id, b.title);
+            let chars = b.lines.iter().map(|l| l.chars().count()).chain(Some(title.len())).max();
+            (chars.unwrap_or(0) * CHAR_WIDTH + 16, (b.lines.len() + 1) * LINE_HEIGHT + 12)
+        };
+        let mut widths = vec![0; layer_count];
+        let mut heights = vec![0; layer_count];
+        for b in &self.nodes {
+            let (w, h) = size(b);
+            let layer = layers[&b.id];
+            widths[layer] += w + GAP;
+            heights[layer] = heights[layer].max(h);
+        }
+        let widths: Vec<usize> = widths.iter().map(|w| w - GAP).collect();
+        let width = widths.iter().cloned().max().unwrap_or(0) + 2 * GAP;
+        let mut tops = vec![GAP; layer_count];
+        for layer in 1..layer_count {
+            tops[layer] = tops[layer - 1] + heights[layer - 1] + 2 * GAP;
+        }
+        let height = tops.last().cloned().unwrap_or(0) + heights.last().cloned().unwrap_or(0) + GAP;
+
+        let mut lefts: Vec<usize> = widths.iter().map(|w| (width - w) / 2).collect();
+        let mut positions = HashMap::new();
+        for b in &self.nodes {
+            let (w, h) = size(b);
+            let layer = layers[&b.id];
+            positions.insert(b.id, (lefts[layer], tops[layer], w, h));
+            lefts[layer] += w + GAP;
+        }
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             font-family=\"monospace\" font-size=\"12\">\n",
+            width, height
+        );
+        svg.push_str(
+            "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+             <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n",
+        );
+        for (from, to, slot) in &self.edges {
+            let (x1, y1, w1, h1) = positions[from];
+            let (x2, y2, w2, _) = positions[to];
+            let (x1, y1, x2, y2) = (x1 + w1 / 2, y1 + h1, x2 + w2 / 2, y2);
+            let dy = (y2 - y1) / 2;
+            svg.push_str(&format!(
+                "<path d=\"M {} {} C {} {} {} {} {} {}\" fill=\"none\" stroke=\"black\" \
+                 marker-end=\"url(#arrow)\"/>\n",
+                x1,
+                y1,
+                x1,
+                y1 + dy,
+                x2,
+                y2 - dy,
+                x2,
+                y2
+            ));
+            if let Some(slot) = slot {
+                let (x, y) = (x1 + 4, y1 + 12);
+                svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", x, y, slot));
+            }
+        }
+        for b in &self.nodes {
+            let (x, y, w, h) = positions[&b.id];
+            let fill = b.heat.map(heat_color).unwrap_or("white".to_string());
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{}\" \
+                 stroke=\"black\"/>\n",
+                x, y, w, h, fill
+            ));
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-weight=\"bold\">#{} {}</text>\n",
+                x + 8,
+                y + LINE_HEIGHT + 2,
+                b.id,
+                escape(&b.title)
+            ));
+            for (ix, line) in b.lines.iter().enumerate() {
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                    x + 8,
+                    y + (ix + 2) * LINE_HEIGHT + 2,
+                    escape(line)
+                ));
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// From white, for nodes taking no time, to red, for the slowest one.
+fn heat_color(heat: f64) -> String {
+    let cold = (255.0 * (1.0 - heat.max(0.0).min(1.0))) as u8;
+    format!("#ff{:02x}{:02x}", cold, cold)
 }
 
-fn render_t<TI:TensorInfo>(model: &Model<TI>) -> CliResult<()> {
+fn render_text<TI: TensorInfo>(model: &Model<TI>) -> CliResult<()> {
     let colors: &[Style] = &[
         Color::Red.normal(),
         Color::Green.normal(),
@@ -16,6 +16,8 @@ error_chain! {
     foreign_links {
         Io(::std::io::Error);
         NumParseInt(::std::num::ParseIntError);
+        NumParseFloat(::std::num::ParseFloatError);
         NdarrayShape(ndarray::ShapeError);
+        Json(serde_json::Error);
     }
 }
@@ -0,0 +1,499 @@
+//! The `explore` subcommand: an interactive session keeping a model and its
+//! execution state in memory.
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+use ansi_term::Color::*;
+use ansi_term::Style;
+use serde_json::{json, Value};
+use tract_core::model::{InferenceModel, Model, TensorInfo, TypedModel};
+use tract_core::ops::prelude::*;
+use tract_core::plan::{SimplePlan, SimpleState};
+
+use crate::display_graph::*;
+use crate::errors::*;
+use crate::format::Row;
+use crate::{Parameters, SomeGraphDef, SomeModel};
+
+/// The pipeline stages a session can switch between.
+pub const STAGES: &[&str] = &["inference", "typed", "decluttered", "optimized"];
+
+const HELP: &str = "\
+nodes [pattern]       list the nodes, or the ones with pattern in their name or op
+node [node]           select a node, and show its facts, op info and values
+inputs                list the model inputs, and whether they are set
+outputs               list the model outputs
+pred [node]           list the nodes a node takes its inputs from
+succ [node]           list the nodes using the outputs of a node
+set <input> <value>   set an input (index or name) to @file, or to random 3x4xf32
+compute [node]        compute a node, and the nodes it depends on
+print [node]          print the computed values of a node
+save <file> [node]    save the values of a node (.npy for a single one, or .npz)
+stage [stage]         show the stage, or rebuild the model at another one
+                      (inference, typed, decluttered or optimized)
+reset                 forget the computed values and the op states
+help                  show this help
+quit                  leave
+
+<node> is a node id or name, and defaults to the selected node.";
+
+/// The commands of `HELP`, as usage and description pairs.
+fn help_commands() -> Vec<Value> {
+    let mut commands: Vec<(String, String)> = vec![];
+    for line in HELP.lines().take_while(|l| !l.is_empty()) {
+        let (usage, help) = line.split_at(22);
+        match commands.last_mut() {
+            Some(last) if usage.trim().is_empty() => last.1 = format!("{} {}", last.1, help),
+            _ => commands.push((usage.trim().to_string(), help.to_string())),
+        }
+    }
+    commands.into_iter().map(|(usage, help)| json!({ "usage": usage, "help": help })).collect()
+}
+
+type State<TI> = SimpleState<TI, Model<TI>, SimplePlan<TI, Model<TI>>>;
+
+/// The model, built at some stage.
+enum Stage {
+    Inference(InferenceModel),
+    Typed(TypedModel),
+}
+
+/// What the command loop does once a command has run.
+enum Next {
+    Continue,
+    Switch(String, Stage),
+    Quit,
+}
+
+/// What outlives the stage switches: the model as loaded, the inputs and the
+/// node selection.
+struct Session {
+    raw: InferenceModel,
+    graph: SomeGraphDef,
+    json: bool,
+    interactive: bool,
+    stage: String,
+    inputs: Vec<Option<Tensor>>,
+    selected: Option<String>,
+}
+
+/// Handles the `explore` subcommand, starting at the given stage.
+pub fn handle(params: Parameters, stage: &str) -> CliResult<()> {
+    let Parameters { tract_model, graph, inputs: given, json, .. } = params;
+    let raw = match tract_model {
+        SomeModel::Inference(m) => m,
+        _ => bail!("explore needs the model as loaded"),
+    };
+    let mut inputs = vec![];
+    for (ix, &input) in raw.inputs()?.iter().enumerate() {
+        let given = given.as_ref().and_then(|v| v.get(ix)).and_then(|t| t.as_ref());
+        inputs.push(match given {
+            Some(value) => Some(value.as_tensor().clone()),
+            None => crate::tensor::tensor_for_fact(raw.fact(input)?, None).ok(),
+        });
+    }
+    let interactive = atty::is(atty::Stream::Stdin);
+    let mut model = match build(&raw, stage) {
+        Ok(model) => model,
+        Err(e) => {
+            warn!("Could not build the {} model, exploring it as loaded: {}", stage, e);
+            build(&raw, "inference")?
+        }
+    };
+    let stage = match model {
+        Stage::Inference(_) => "inference",
+        _ => stage,
+    };
+    let mut session =
+        Session { raw, graph, json, interactive, stage: stage.to_string(), inputs, selected: None };
+    if session.interactive && !session.json {
+        println!("Type {} for the list of commands.", Style::new().bold().paint("help"));
+    }
+    loop {
+        let next = match model {
+            Stage::Inference(m) => session.explore(m)?,
+            Stage::Typed(m) => session.explore(m)?,
+        };
+        match next {
+            Some((name, next)) => {
+                session.stage = name;
+                model = next;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Builds the model at a stage of the pipeline, from the model as loaded.
+fn build(raw: &InferenceModel, stage: &str) -> CliResult<Stage> {
+    Ok(match stage {
+        "inference" => Stage::Inference(raw.clone()),
+        "typed" => Stage::Typed(raw.clone().into_typed()?),
+        "decluttered" => Stage::Typed(raw.clone().into_typed()?.declutter()?),
+        "optimized" => Stage::Typed(raw.clone().into_typed()?.declutter()?.codegen()?),
+        _ => bail!("Unknown stage {}, stages are {}", stage, STAGES.join(", ")),
+    })
+}
+
+/// Resolves a node id or name, or the selected node if there is none.
+fn node_id<TI: TensorInfo>(
+    model: &Model<TI>,
+    spec: Option<&str>,
+    selected: Option<&String>,
+) -> CliResult<usize> {
+    let spec = match spec.or(selected.map(|s| &**s)) {
+        Some(spec) => spec,
+        None => bail!("No node selected, give a node id or name"),
+    };
+    if let Ok(id) = spec.parse::<usize>() {
+        if id < model.nodes().len() {
+            return Ok(id);
+        }
+    }
+    Ok(model.node_by_name(spec)?.id)
+}
+
+impl Session {
+    /// Runs commands on the model until a stage switch or the end of the
+    /// input, returning the next stage if any.
+    fn explore<TI: TensorInfo>(&mut self, model: Model<TI>) -> CliResult<Option<(String, Stage)>> {
+        if let Some(name) = self.selected.take() {
+            if model.node_by_name(&name).is_ok() {
+                self.selected = Some(name);
+            }
+        }
+        let mut state = SimpleState::new(SimplePlan::new(model)?)?;
+        self.set_inputs(&mut state)?;
+        let stdin = std::io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            if self.interactive && !self.json {
+                print!("{}> ", self.stage);
+                std::io::stdout().flush()?;
+            }
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(None),
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            match self.command(&mut state, &words) {
+                Ok(Next::Continue) => (),
+                Ok(Next::Switch(name, stage)) => return Ok(Some((name, stage))),
+                Ok(Next::Quit) => return Ok(None),
+                Err(e) => self.report_error(&e),
+            }
+        }
+    }
+
+    fn report_error(&self, e: &CliError) {
+        if self.json {
+            let _ = crate::json::print(&json!({ "error": e.to_string() }));
+        } else {
+            println!("{}", Red.paint(e.to_string()));
+        }
+    }
+
+    /// Feeds the inputs known so far to the state, after forgetting all the
+    /// computed values.
+    fn set_inputs<TI: TensorInfo>(&self, state: &mut State<TI>) -> CliResult<()> {
+        state.reset_wires()?;
+        for (ix, input) in self.inputs.iter().enumerate() {
+            if let Some(input) = input {
+                state.set_input(ix, input.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn command<TI: TensorInfo>(
+        &mut self,
+        state: &mut State<TI>,
+        words: &[&str],
+    ) -> CliResult<Next> {
+        let arg = words.get(1).cloned();
+        match words[0] {
+            "nodes" => self.nodes(state, arg)?,
+            "node" => {
+                let id = node_id(state.model(), arg, self.selected.as_ref())?;
+                self.selected = Some(state.model().node(id).name.clone());
+                self.show(state, id)?
+            }
+            "inputs" => self.inputs(state)?,
+            "outputs" => self.outputs(state)?,
+            "pred" => {
+                let node = state.model().node(node_id(state.model(), arg, self.selected.as_ref())?);
+                let ids: Vec<usize> = node.inputs.iter().map(|i| i.node).collect();
+                self.list(state, &ids)?
+            }
+            "succ" => {
+                let node = state.model().node(node_id(state.model(), arg, self.selected.as_ref())?);
+                let ids: Vec<usize> =
+                    node.outputs.iter().flat_map(|o| o.successors.iter().map(|i| i.node)).collect();
+                self.list(state, &ids)?
+            }
+            "set" => {
+                if words.len() != 3 {
+                    bail!("Usage: set <input> <value>")
+                }
+                self.set(state, words[1], words[2])?
+            }
+            "compute" => {
+                let id = node_id(state.model(), arg, self.selected.as_ref())?;
+                self.compute(state, id)?
+            }
+            "print" => {
+                let id = node_id(state.model(), arg, self.selected.as_ref())?;
+                self.print(state, id)?
+            }
+            "save" => {
+                let path = arg.ok_or("Usage: save <file> [node]")?;
+                let id = node_id(state.model(), words.get(2).cloned(), self.selected.as_ref())?;
+                self.save(state, path, id)?
+            }
+            "stage" => match arg {
+                Some(stage) => {
+                    info!("Building the {} model", stage);
+                    return Ok(Next::Switch(stage.to_string(), build(&self.raw, stage)?));
+                }
+                None if self.json => {
+                    crate::json::print(&json!({ "stage": self.stage, "stages": STAGES }))?
+                }
+                None => println!("{} (stages are {})", self.stage, STAGES.join(", ")),
+            },
+            "reset" => {
+                state.reset_op_states()?;
+                self.set_inputs(state)?
+            }
+            "help" if self.json => crate::json::print(&json!({ "commands": help_commands() }))?,
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(Next::Quit),
+            command => bail!("Unknown command {}, try help", command),
+        }
+        Ok(Next::Continue)
+    }
+
+    fn nodes<TI: TensorInfo>(&self, state: &State<TI>, pattern: Option<&str>) -> CliResult<()> {
+        let model = state.model();
+        let ids: Vec<usize> = model
+            .nodes()
+            .iter()
+            .filter(|n| {
+                pattern.map(|p| n.name.contains(p) || n.op.name().contains(p)).unwrap_or(true)
+            })
+            .map(|n| n.id)
+            .collect();
+        self.list(state, &ids)
+    }
+
+    /// Prints one line per node, starred when its values are computed.
+    fn list<TI: TensorInfo>(&self, state: &State<TI>, ids: &[usize]) -> CliResult<()> {
+        let model = state.model();
+        if self.json {
+            let nodes: Vec<Value> = ids
+                .iter()
+                .map(|&id| {
+                    let node = model.node(id);
+                    json!({
+                        "id": id,
+                        "name": node.name,
+                        "op": node.op.name(),
+                        "computed": state.values[id].is_some(),
+                    })
+                })
+                .collect();
+            return crate::json::print(&nodes.into());
+        }
+        for &id in ids {
+            let node = model.node(id);
+            let computed = if state.values[id].is_some() { "*" } else { " " };
+            println!("{}{:>5} {:<20} {}", computed, id, node.op.name(), node.name);
+        }
+        Ok(())
+    }
+
+    fn show<TI: TensorInfo>(&self, state: &State<TI>, id: usize) -> CliResult<()> {
+        let options = DisplayOptions { json: self.json, ..DisplayOptions::default() };
+        let mut display_graph = DisplayGraph::from_model_and_options(state.model(), options)?
+            .with_graph_def(&self.graph)?;
+        if let Some(values) = &state.values[id] {
+            let rows = values
+                .iter()
+                .enumerate()
+                .map(|(ix, v)| {
+                    Row::Double(
+                        format!("Value {}:", ix),
+                        format!("{:?} {:?}", v.datum_type(), v.shape()),
+                    )
+                })
+                .collect();
+            display_graph.add_node_section(id, rows)?;
+        }
+        display_graph.render_node(state.model().node(id))
+    }
+
+    fn inputs<TI: TensorInfo>(&self, state: &State<TI>) -> CliResult<()> {
+        let model = state.model();
+        let mut json = vec![];
+        for (ix, &outlet) in model.inputs()?.iter().enumerate() {
+            let name = &model.node(outlet.node).name;
+            let value = self.inputs.get(ix).and_then(|v| v.as_ref());
+            if self.json {
+                json.push(json!({
+                    "input": ix,
+                    "node": outlet.node,
+                    "name": name,
+                    "fact": crate::json::fact(model.fact(outlet)?),
+                    "set": value.map(|v| json!({
+                        "datum_type": crate::json::datum_type(v.datum_type()),
+                        "shape": v.shape(),
+                    })),
+                }));
+            } else {
+                let value = match value {
+                    Some(v) => format!("set to {:?} {:?}", v.datum_type(), v.shape()),
+                    None => Yellow.paint("not set").to_string(),
+                };
+                println!("{}: #{} {} {:?} {}", ix, outlet.node, name, model.fact(outlet)?, value);
+            }
+        }
+        if self.json {
+            crate::json::print(&json.into())?;
+        }
+        Ok(())
+    }
+
+    fn outputs<TI: TensorInfo>(&self, state: &State<TI>) -> CliResult<()> {
+        let model = state.model();
+        let mut json = vec![];
+        for (ix, &outlet) in model.outputs()?.iter().enumerate() {
+            let name = &model.node(outlet.node).name;
+            if self.json {
+                json.push(json!({
+                    "output": ix,
+                    "node": outlet.node,
+                    "slot": outlet.slot,
+                    "name": name,
+                    "fact": crate::json::fact(model.fact(outlet)?),
+                }));
+            } else {
+                println!(
+                    "{}: #{}/{} {} {:?}",
+                    ix,
+                    outlet.node,
+                    outlet.slot,
+                    name,
+                    model.fact(outlet)?
+                );
+            }
+        }
+        if self.json {
+            crate::json::print(&json.into())?;
+        }
+        Ok(())
+    }
+
+    fn set<TI: TensorInfo>(
+        &mut self,
+        state: &mut State<TI>,
+        input: &str,
+        value: &str,
+    ) -> CliResult<()> {
+        let inputs = state.model().inputs()?;
+        let ix = match input.parse::<usize>() {
+            Ok(ix) if ix < inputs.len() => ix,
+            _ => {
+                let id = state.model().node_by_name(input)?.id;
+                inputs
+                    .iter()
+                    .position(|o| o.node == id)
+                    .ok_or_else(|| format!("Node {} is not an input of the model", input))?
+            }
+        };
+        let fact = crate::tensor::for_string(value)?;
+        let tensor = crate::tensor::tensor_for_fact(&fact, None)?;
+        if !self.json {
+            println!("Input {} set to {:?} {:?}", ix, tensor.datum_type(), tensor.shape());
+        }
+        self.inputs[ix] = Some(tensor);
+        self.set_inputs(state)
+    }
+
+    fn compute<TI: TensorInfo>(&self, state: &mut State<TI>, id: usize) -> CliResult<()> {
+        // only the inputs the node depends on need a value
+        let mut ancestors = HashSet::new();
+        let mut todo = vec![id];
+        while let Some(n) = todo.pop() {
+            if ancestors.insert(n) {
+                todo.extend(state.model().node(n).inputs.iter().map(|i| i.node));
+            }
+        }
+        for (ix, input) in state.model().inputs()?.iter().enumerate() {
+            if ancestors.contains(&input.node) && self.inputs[ix].is_none() {
+                bail!("Input {} is not set, use set", ix)
+            }
+        }
+        if state.values[id].is_none() {
+            state.compute_recursively(id)?;
+        }
+        let values = state.values[id].as_ref().unwrap();
+        if self.json {
+            let values: Vec<Value> = values
+                .iter()
+                .map(|v| {
+                    json!({
+                        "datum_type": crate::json::datum_type(v.datum_type()),
+                        "shape": v.shape(),
+                    })
+                })
+                .collect();
+            return crate::json::print(&json!({ "node": id, "values": values }));
+        }
+        let name = &state.model().node(id).name;
+        for (ix, v) in values.iter().enumerate() {
+            println!("#{}/{} {}: {:?} {:?}", id, ix, name, v.datum_type(), v.shape());
+        }
+        Ok(())
+    }
+
+    /// The values of a node, if they have been computed.
+    fn values<'s, TI: TensorInfo>(
+        &self,
+        state: &'s State<TI>,
+        id: usize,
+    ) -> CliResult<&'s TVec<SharedTensor>> {
+        Ok(state.values[id]
+            .as_ref()
+            .ok_or_else(|| format!("Node #{} is not computed, use compute", id))?)
+    }
+
+    fn print<TI: TensorInfo>(&self, state: &State<TI>, id: usize) -> CliResult<()> {
+        let values = self.values(state, id)?;
+        if self.json {
+            let values =
+                values.iter().map(|v| crate::json::tensor(v)).collect::<CliResult<Vec<_>>>()?;
+            return crate::json::print(&json!({ "node": id, "values": values }));
+        }
+        for (ix, v) in values.iter().enumerate() {
+            println!("value #{}\n{}\n", ix, v.dump(true)?);
+        }
+        Ok(())
+    }
+
+    fn save<TI: TensorInfo>(&self, state: &State<TI>, path: &str, id: usize) -> CliResult<()> {
+        let values = self.values(state, id)?;
+        let name = &state.model().node(id).name;
+        let names: Vec<String> = if values.len() == 1 {
+            vec![name.clone()]
+        } else {
+            (0..values.len()).map(|slot| format!("{}:{}", name, slot)).collect()
+        };
+        crate::run::save(path, &names, values)?;
+        if !self.json {
+            println!("Saved {} value(s) to {}", values.len(), path);
+        }
+        Ok(())
+    }
+}
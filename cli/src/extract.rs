@@ -0,0 +1,79 @@
+use tract_core::model::{Model, OutletId, TensorInfo};
+use tract_core::TensorFact;
+
+use crate::display_graph::*;
+use crate::errors::*;
+use crate::{Parameters, SomeGraphDef, SomeModel};
+
+/// Handles the `extract` subcommand.
+pub fn handle(
+    params: Parameters,
+    from: Vec<&str>,
+    to: Vec<&str>,
+    output: Option<&str>,
+    options: DisplayOptions,
+) -> CliResult<()> {
+    match &params.tract_model {
+        SomeModel::Inference(m) => handle_t(m, &params.graph, from, to, output, options),
+        SomeModel::Typed(m) => handle_t(m, &params.graph, from, to, output, options),
+        SomeModel::Normalized(m) => handle_t(m, &params.graph, from, to, output, options),
+        SomeModel::Pulsed(_, m) => handle_t(m, &params.graph, from, to, output, options),
+    }
+}
+
+/// Resolves a `node` or `node:slot` specification to an outlet.
+fn outlet<TI: TensorInfo>(model: &Model<TI>, spec: &str) -> CliResult<OutletId> {
+    if let Ok(node) = model.node_by_name(spec) {
+        return Ok(OutletId::new(node.id, 0));
+    }
+    if let Some(colon) = spec.rfind(':') {
+        if let Ok(slot) = spec[colon + 1..].parse() {
+            let node = model.node_by_name(&spec[..colon])?;
+            if slot < node.outputs.len() {
+                return Ok(OutletId::new(node.id, slot));
+            }
+            bail!("Node {} has no output {}", node.name, slot)
+        }
+    }
+    bail!("Node named {} not found", spec)
+}
+
+fn handle_t<TI: TensorInfo>(
+    model: &Model<TI>,
+    graph: &SomeGraphDef,
+    from: Vec<&str>,
+    to: Vec<&str>,
+    output: Option<&str>,
+    options: DisplayOptions,
+) -> CliResult<()> {
+    let inputs = from.iter().map(|s| outlet(model, s)).collect::<CliResult<Vec<_>>>()?;
+    let outputs = to.iter().map(|s| outlet(model, s)).collect::<CliResult<Vec<_>>>()?;
+    let extracted = model.extract(&inputs, &outputs)?;
+    info!("Extracted {} nodes out of {}", extracted.nodes().len(), model.nodes().len());
+
+    if let Some(path) = output {
+        let facts = |outlets: &[OutletId]| -> CliResult<Vec<TensorFact>> {
+            outlets.iter().map(|&o| Ok(model.fact(o)?.to_tensor_fact())).collect()
+        };
+        match graph {
+            #[cfg(feature = "onnx")]
+            SomeGraphDef::Onnx(proto) => {
+                let onnx = tract_onnx::onnx();
+                let names = |outlets: &[OutletId]| -> CliResult<Vec<String>> {
+                    outlets
+                        .iter()
+                        .map(|o| Ok(onnx.tensor_name(proto, &model.node(o.node).name, o.slot)?))
+                        .collect()
+                };
+                let ins = names(&inputs)?.into_iter().zip(facts(&inputs)?).collect::<Vec<_>>();
+                let outs = names(&outputs)?.into_iter().zip(facts(&outputs)?).collect::<Vec<_>>();
+                let extracted_proto = onnx.extract_proto_model(proto, &ins, &outs)?;
+                let mut file = std::fs::File::create(path)?;
+                onnx.write_proto_model(&extracted_proto, &mut file)?;
+            }
+            _ => bail!("Only ONNX models can be written out by extract"),
+        }
+    }
+
+    DisplayGraph::from_model_and_options(&extracted, options)?.render()
+}
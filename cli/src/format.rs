@@ -29,6 +29,19 @@ pub enum Row {
     Double(String, String),
 }
 
+impl Row {
+    /// A string for simple rows, a `[header, content]` pair for double ones.
+    pub fn to_json(&self) -> serde_json::Value {
+        use crate::json::strip_ansi;
+        match self {
+            Row::Simple(content) => strip_ansi(content).into(),
+            Row::Double(header, content) => {
+                serde_json::json!([strip_ansi(header), strip_ansi(content)])
+            }
+        }
+    }
+}
+
 /// Returns a table format with no borders or padding.
 fn format_none() -> TableFormat {
     FormatBuilder::new().build()
@@ -0,0 +1,147 @@
+//! JSON output for `--json`.
+//!
+//! Nodes are rendered as `{ id, name, op, info, inputs, outputs }`, where
+//! inputs are `{ node, slot, fact }` and outputs `{ slot, fact, model_output }`.
+//! Facts are `{ datum_type, shape, constant, repr }`, with unknown parts as
+//! null and symbolic dimensions as strings. Durations are in seconds per
+//! iteration.
+
+use serde_json::{json, Value};
+use tract_core::model::{Model, Node, OutletId, TensorInfo};
+use tract_core::ops::prelude::*;
+
+use crate::errors::*;
+use crate::rusage::Duration;
+
+/// Prints a JSON document on the standard output.
+pub fn print(value: &Value) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+pub fn fact<TI: TensorInfo>(fact: &TI) -> Value {
+    let tensor_fact = fact.to_tensor_fact();
+    let shape = if tensor_fact.shape.is_open() {
+        Value::Null
+    } else {
+        tensor_fact
+            .shape
+            .dims()
+            .map(|d| match d.concretize() {
+                Some(d) => dim(&d),
+                None => Value::Null,
+            })
+            .collect()
+    };
+    json!({
+        "datum_type": tensor_fact.datum_type.concretize().map(datum_type),
+        "shape": shape,
+        "constant": tensor_fact.value.concretize().is_some(),
+        "repr": format!("{:?}", fact),
+    })
+}
+
+/// A dimension, as a number, or as a string if it is symbolic.
+pub fn dim(dim: &TDim) -> Value {
+    dim.as_const().map(|d| json!(d)).unwrap_or_else(|| json!(dim.to_string()))
+}
+
+pub fn datum_type(dt: DatumType) -> String {
+    format!("{:?}", dt).to_lowercase()
+}
+
+pub fn outlet(outlet: OutletId) -> Value {
+    json!({ "node": outlet.node, "slot": outlet.slot })
+}
+
+pub fn node<TI: TensorInfo>(model: &Model<TI>, node: &Node<TI>) -> CliResult<Value> {
+    let model_outputs = model.outputs()?;
+    let inputs = node
+        .inputs
+        .iter()
+        .map(|i| Ok(json!({ "node": i.node, "slot": i.slot, "fact": fact(model.fact(*i)?) })))
+        .collect::<CliResult<Vec<_>>>()?;
+    let outputs: Vec<Value> = node
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(slot, o)| {
+            let model_output =
+                model_outputs.iter().position(|&m| m == OutletId::new(node.id, slot));
+            json!({ "slot": slot, "fact": fact(&o.fact), "model_output": model_output })
+        })
+        .collect();
+    Ok(json!({
+        "id": node.id,
+        "name": node.name,
+        "op": node.op().name(),
+        "info": node.op().info()?,
+        "inputs": inputs,
+        "outputs": outputs,
+    }))
+}
+
+/// The model inputs and outputs, and all its nodes in evaluation order.
+pub fn model<TI: TensorInfo>(model: &Model<TI>) -> CliResult<Value> {
+    let nodes = model
+        .eval_order()?
+        .iter()
+        .map(|&n| node(model, model.node(n)))
+        .collect::<CliResult<Vec<_>>>()?;
+    Ok(json!({
+        "inputs": model.inputs()?.iter().map(|&o| outlet(o)).collect::<Vec<_>>(),
+        "outputs": model.outputs()?.iter().map(|&o| outlet(o)).collect::<Vec<_>>(),
+        "nodes": nodes,
+    }))
+}
+
+pub fn tensor(tensor: &Tensor) -> CliResult<Value> {
+    fn values<T: Datum + Clone + Into<Value>>(tensor: &Tensor) -> CliResult<Vec<Value>> {
+        Ok(tensor.to_array_view::<T>()?.iter().map(|v| v.clone().into()).collect())
+    }
+    let values = match tensor.datum_type() {
+        DatumType::Bool => values::<bool>(tensor)?,
+        DatumType::U8 => values::<u8>(tensor)?,
+        DatumType::U16 => values::<u16>(tensor)?,
+        DatumType::I8 => values::<i8>(tensor)?,
+        DatumType::I16 => values::<i16>(tensor)?,
+        DatumType::I32 => values::<i32>(tensor)?,
+        DatumType::I64 => values::<i64>(tensor)?,
+        DatumType::F16 | DatumType::F32 | DatumType::F64 => {
+            values::<f64>(&*tensor.cast_to::<f64>()?)?
+        }
+        DatumType::TDim => values::<i64>(&*tensor.cast_to::<i64>()?)?,
+        DatumType::String => values::<String>(tensor)?,
+    };
+    Ok(json!({
+        "datum_type": datum_type(tensor.datum_type()),
+        "shape": tensor.shape(),
+        "values": values,
+    }))
+}
+
+pub fn duration(duration: Duration) -> Value {
+    json!({
+        "real": duration.avg_real(),
+        "user": duration.avg_user(),
+        "sys": duration.avg_sys(),
+    })
+}
+
+/// Removes the terminal colour escapes from labels meant for display.
+pub fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            while let Some(c) = chars.next() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
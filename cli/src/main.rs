@@ -15,6 +15,7 @@ extern crate env_logger;
 extern crate libc;
 extern crate pbr;
 extern crate rand;
+extern crate serde_json;
 extern crate terminal_size;
 extern crate textwrap;
 #[macro_use]
@@ -33,20 +34,31 @@ use crate::tfpb::graph::GraphDef;
 use insideout::InsideOut;
 use tract_core::model::{InferenceModel, NormalizedModel, TypedModel};
 use tract_core::ops::prelude::*;
+use tract_core::optim::{Optimizer, OptimizerReport};
 #[cfg(feature = "tf")]
 use tract_tensorflow::tfpb;
 
 use crate::display_graph::DisplayOptions;
 use crate::errors::*;
 
+mod analyse;
+mod bench_matrix;
 mod compare;
+#[cfg(feature = "onnx")]
+mod conform;
+mod diff;
 mod display_graph;
 mod draw;
 mod dump;
 mod errors;
+mod explore;
+mod extract;
 mod format;
+mod json;
 // mod optimize_check;
 mod profile;
+mod pulse_info;
+mod regression;
 mod run;
 mod rusage;
 mod stream_check;
@@ -56,6 +68,8 @@ mod utils;
 /// The default maximum for iterations and time.
 const DEFAULT_MAX_ITERS: u64 = 100_000;
 const DEFAULT_MAX_TIME: u64 = 5000;
+/// The largest pulse listed by `--pulse auto`, which always tries the smallest valid one.
+const DEFAULT_MAX_PULSE: usize = 64;
 
 /// Entrypoint for the command-line interface.
 fn main() {
@@ -74,8 +88,10 @@ fn main() {
         (@arg format: +takes_value
             "Hint the model format ('onnx' or 'tf') instead of guess from extension.")
 
-        (@arg input: -i --input +takes_value
-            "Set input value (@file or 3x4xi32)")
+        (@arg input: -i --input +takes_value +multiple number_of_values(1)
+            "Set input value (@file, @image.png:1x224x224x3xf32, @raw.bin:3x4xi64, 3x4xi32, \
+            or 3x4xf32:normal(0,1), uniform(-1,1), int(0,10), const(1)), or input=value \
+            for a named input (can be repeated)")
 
         (@arg stream_axis: -s --("stream-axis") +takes_value
             "Set Axis number to stream upon (first is 0)")
@@ -89,17 +105,58 @@ fn main() {
         (@arg skip_analyse: --("skip-analyse") "Skip analyse after model build")
         (@arg declutter: --declutter "Declutter model after load")
         (@arg optimize: -O --optimize "Optimize after model load")
-        (@arg pulse: --pulse +takes_value "Translate to pulse network")
+        (@arg pulse: --pulse +takes_value
+            "Translate to pulse network (pulse size, or 'auto' to pick the smallest valid one)")
+        (@arg pass: --pass +takes_value +multiple number_of_values(1)
+            "Only run the given optimisation pass (can be repeated). Declutter and codegen \
+            passes not listed are skipped: listing only declutter passes skips codegen")
+        (@arg no_pass: --("no-pass") +takes_value +multiple number_of_values(1)
+            "Do not run the given optimisation pass (can be repeated)")
+        (@arg optim_iterations: --("optim-iterations") +takes_value
+            "Cap the number of declutter and codegen iterations")
+        (@arg optim_log: --("optim-log") "Print the rewrites done by the optimisation passes")
 
         (@arg verbosity: -v ... "Sets the level of verbosity.")
 
         (@arg machine_friendly: --("machine-friendly") "Machine friendly output")
 
+        (@arg json: --json +global "Output a JSON document instead of text")
+
         (@arg list_ops: --("list-ops") "List all known operators")
+        (@arg list_passes: --("list-passes") "List the optimisation passes")
     );
 
     let compare = clap::SubCommand::with_name("compare")
-        .help("Compares the output of tract and tensorflow on randomly generated input.");
+        .help("Compares the values of each node of the model to a reference")
+        .arg(
+            Arg::with_name("reference")
+                .takes_value(true)
+                .long("reference")
+                .help(
+                    "inference (the model as loaded, unoptimised), tf, a .npz file, or an \
+                     ONNX test data set directory [default: tf with conform, else inference]",
+                ),
+        )
+        .arg(
+            Arg::with_name("atol")
+                .takes_value(true)
+                .long("atol")
+                .help("Absolute tolerance [default: 1e-4]"),
+        )
+        .arg(
+            Arg::with_name("rtol")
+                .takes_value(true)
+                .long("rtol")
+                .help("Relative tolerance [default: 1e-3]"),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .takes_value(true)
+                .long("tolerance")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Tolerance for one node, as name=atol[,rtol]"),
+        );
     app = app.subcommand(output_options(compare));
 
     let dump = clap::SubCommand::with_name("dump")
@@ -118,7 +175,36 @@ fn main() {
         );
     app = app.subcommand(output_options(dump));
 
-    let draw = clap::SubCommand::with_name("draw");
+    let draw = clap::SubCommand::with_name("draw")
+        .help("Draws the graph in the terminal, or as Graphviz DOT or SVG")
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["text", "dot", "svg"])
+                .help("Output format [default: text]"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Write the drawing to this file (dot and svg)"),
+        )
+        .arg(Arg::with_name("profile").long("profile").help("Colour the nodes by time spent"))
+        .arg(
+            Arg::with_name("max_iters")
+                .takes_value(true)
+                .long("max-iters")
+                .short("n")
+                .help("Sets the maximum number of iterations for each node when profiling"),
+        )
+        .arg(
+            Arg::with_name("max-time")
+                .takes_value(true)
+                .long("max-time")
+                .help("Sets the maximum profiling time for each node (in ms) [default: 5000]."),
+        );
     app = app.subcommand(output_options(draw));
 
     let profile =
@@ -143,6 +229,58 @@ fn main() {
             );
     app = app.subcommand(output_options(profile));
 
+    let bench_matrix = clap::SubCommand::with_name("bench-matrix")
+        .help("Benches the model at each optimisation stage, and pulsed, on the same input")
+        .arg(
+            Arg::with_name("max_iters")
+                .takes_value(true)
+                .long("max-iters")
+                .short("n")
+                .help("Sets the maximum number of iterations for each variant [default: 100_000]."),
+        )
+        .arg(
+            Arg::with_name("max-time")
+                .takes_value(true)
+                .long("max-time")
+                .help("Sets the maximum execution time for each variant (in ms) [default: 5000]."),
+        )
+        .arg(
+            Arg::with_name("pulses")
+                .takes_value(true)
+                .long("pulses")
+                .help("Comma-separated pulse sizes [default: smallest valid one, x4 and x16]"),
+        )
+        .arg(
+            Arg::with_name("stream-len")
+                .takes_value(true)
+                .long("stream-len")
+                .help("Length of the streaming axis when the input does not fix it [default: 100]"),
+        )
+        .arg(
+            Arg::with_name("atol")
+                .takes_value(true)
+                .long("atol")
+                .help("Absolute tolerance on the outputs [default: 1e-4]"),
+        )
+        .arg(
+            Arg::with_name("rtol")
+                .takes_value(true)
+                .long("rtol")
+                .help("Relative tolerance on the outputs [default: 1e-3]"),
+        );
+    app = app.subcommand(bench_matrix);
+
+    let explore = clap::SubCommand::with_name("explore")
+        .help("Explores the model interactively: navigate nodes, set inputs, compute values")
+        .arg(
+            Arg::with_name("stage")
+                .long("stage")
+                .takes_value(true)
+                .possible_values(explore::STAGES)
+                .help("Stage of the pipeline to start at [default: typed]"),
+        );
+    app = app.subcommand(explore);
+
     let run = clap::SubCommand::with_name("run")
         .help("Run the graph")
         .arg(
@@ -156,6 +294,38 @@ fn main() {
                 .takes_value(true)
                 .long("assert-output-fact")
                 .help("Infered shape and datum type must match exactly this"),
+        )
+        .arg(
+            Arg::with_name("save-outputs")
+                .takes_value(true)
+                .long("save-outputs")
+                .help("Save the outputs, named after their nodes (.npz, or .npy for one output)"),
+        )
+        .arg(
+            Arg::with_name("assert-outputs-from")
+                .takes_value(true)
+                .long("assert-outputs-from")
+                .help("Check outputs and intermediate values against the named arrays of a .npz"),
+        )
+        .arg(
+            Arg::with_name("atol")
+                .takes_value(true)
+                .long("atol")
+                .help("Absolute tolerance for --assert-outputs-from [default: 1e-5]"),
+        )
+        .arg(
+            Arg::with_name("rtol")
+                .takes_value(true)
+                .long("rtol")
+                .help("Relative tolerance for --assert-outputs-from [default: 1e-5]"),
+        )
+        .arg(
+            Arg::with_name("tolerance")
+                .takes_value(true)
+                .long("tolerance")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Tolerance for one reference value, as name=atol[,rtol]"),
         );
     app = app.subcommand(output_options(run));
 
@@ -163,6 +333,73 @@ fn main() {
         .help("Analyses the graph to infer properties about tensors (experimental).");
     app = app.subcommand(output_options(analyse));
 
+    let extract = clap::SubCommand::with_name("extract")
+        .help("Extracts the subgraph between some nodes as a standalone model")
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Node (or node:slot) to cut the graph at, becoming an input"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Node (or node:slot) to use as an output"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("Write the extracted model to this file (ONNX only)"),
+        );
+    app = app.subcommand(output_options(extract));
+
+    let diff = clap::SubCommand::with_name("diff")
+        .help("Compares the structure of two models")
+        .arg(Arg::with_name("first").required(true).help("First model"))
+        .arg(Arg::with_name("second").required(true).help("Second model"));
+    app = app.subcommand(diff);
+
+    let conform = clap::SubCommand::with_name("conform")
+        .help("Runs a directory of conformance tests and reports a result matrix")
+        .arg(
+            Arg::with_name("framework")
+                .required(true)
+                .possible_values(&["onnx"])
+                .help("Framework of the test suite"),
+        )
+        .arg(
+            Arg::with_name("dir")
+                .required(true)
+                .help("Directory of model.onnx and test_data_set_* folders, searched recursively"),
+        )
+        .arg(
+            Arg::with_name("atol")
+                .long("atol")
+                .takes_value(true)
+                .help("Absolute tolerance on the outputs [default: 1e-4]"),
+        )
+        .arg(
+            Arg::with_name("rtol")
+                .long("rtol")
+                .takes_value(true)
+                .help("Relative tolerance on the outputs [default: 1e-3]"),
+        )
+        .arg(
+            Arg::with_name("csv")
+                .long("csv")
+                .takes_value(true)
+                .help("Also write the result matrix to this CSV file"),
+        );
+    app = app.subcommand(conform);
+
     let optimize = clap::SubCommand::with_name("optimize").help("Optimize the graph");
     app = app.subcommand(output_options(optimize));
 
@@ -174,6 +411,10 @@ fn main() {
         .help("Compare output of streamed and regular exec");
     app = app.subcommand(output_options(stream_check));
 
+    let pulse_info = clap::SubCommand::with_name("pulse-info")
+        .help("Reports delay and output length of a pulsed network");
+    app = app.subcommand(pulse_info);
+
     let matches = app.get_matches();
 
     if ::std::env::var("RUST_LOG").is_err() {
@@ -251,6 +492,9 @@ pub struct Parameters {
     graph: SomeGraphDef,
     tract_model: SomeModel,
 
+    /// The model as loaded, before analysis, kept by `compare` as a reference.
+    reference_model: Option<InferenceModel>,
+
     #[cfg(feature = "conform")]
     tf_model: Option<tract_tensorflow::conform::tf::Tensorflow>,
 
@@ -263,44 +507,78 @@ pub struct Parameters {
     assertions: Option<Assertions>,
 
     machine_friendly: bool,
+
+    json: bool,
+}
+
+/// The format of a model file, from the hint if any, else from its name.
+fn model_format<'a>(name: &str, hint: Option<&'a str>) -> &'a str {
+    hint.unwrap_or(if name.ends_with(".onnx") { "onnx" } else { "tf" })
+}
+
+/// The index of the model input fed by the array `name` of a .npz file:
+/// the input node of that name, or the n-th input for numpy "arr_n" names.
+fn input_index(model: &InferenceModel, name: &str) -> CliResult<usize> {
+    let inputs = model.inputs()?;
+    if let Some(ix) = inputs.iter().position(|i| model.node(i.node).name == name) {
+        return Ok(ix);
+    }
+    if name.starts_with("arr_") {
+        if let Ok(ix) = name[4..].parse::<usize>() {
+            if ix < inputs.len() {
+                return Ok(ix);
+            }
+        }
+    }
+    let names: Vec<&str> = inputs.iter().map(|i| &*model.node(i.node).name).collect();
+    bail!("No input named {} (inputs are {})", name, names.join(", "))
+}
+
+/// Splits an `input=value` specification, as opposed to an unnamed value.
+fn named_input(spec: &str) -> Option<(&str, &str)> {
+    if spec.starts_with("@") {
+        return None;
+    }
+    spec.find('=').map(|eq| (&spec[..eq], &spec[eq + 1..]))
+}
+
+/// Loads a model file in the given format.
+fn load_model(name: &str, format: &str) -> CliResult<(SomeGraphDef, InferenceModel)> {
+    let loaded = if format == "onnx" {
+        #[cfg(not(feature = "onnx"))]
+        {
+            panic!("Tract compiled without onnx feature");
+        }
+        #[cfg(feature = "onnx")]
+        {
+            let onnx = tract_onnx::onnx();
+            let graph = onnx.proto_model_for_path(&name)?;
+            let tract = onnx.model_for_proto_model(&graph)?;
+            (SomeGraphDef::Onnx(graph), tract)
+        }
+    } else {
+        #[cfg(not(feature = "tf"))]
+        {
+            panic!("Tract compiled without tensorflow feature");
+        }
+        #[cfg(feature = "tf")]
+        {
+            let tf = tract_tensorflow::tensorflow();
+            let graph = tf.proto_model_for_path(&name)?;
+            let tract = tf.model_for_proto_model(&graph)?;
+            (SomeGraphDef::Tf(graph), tract)
+        }
+    };
+    info!("Model {:?} loaded", name);
+    Ok(loaded)
 }
 
 impl Parameters {
     /// Parses the command-line arguments.
     pub fn from_clap(matches: &clap::ArgMatches) -> CliResult<Parameters> {
         let name = matches.value_of("model").unwrap();
-        let format = matches.value_of("format").unwrap_or(if name.ends_with(".onnx") {
-            "onnx"
-        } else {
-            "tf"
-        });
-        let (graph, mut raw_model) = if format == "onnx" {
-            #[cfg(not(feature = "onnx"))]
-            {
-                panic!("Tract compiled without onnx feature");
-            }
-            #[cfg(feature = "onnx")]
-            {
-                let onnx = tract_onnx::onnx();
-                let graph = onnx.proto_model_for_path(&name)?;
-                let tract = onnx.model_for_proto_model(&graph)?;
-                (SomeGraphDef::Onnx(graph), tract)
-            }
-        } else {
-            #[cfg(not(feature = "tf"))]
-            {
-                panic!("Tract compiled without tensorflow feature");
-            }
-            #[cfg(feature = "tf")]
-            {
-                let tf = tract_tensorflow::tensorflow();
-                let graph = tf.proto_model_for_path(&name)?;
-                let tract = tf.model_for_proto_model(&graph)?;
-                (SomeGraphDef::Tf(graph), tract)
-            }
-        };
-
-        info!("Model {:?} loaded", name);
+        let format = model_format(name, matches.value_of("format"));
+        let (graph, mut raw_model) = load_model(name, format)?;
 
         #[cfg(feature = "conform")]
         let tf_model = if format == "tf" {
@@ -323,31 +601,31 @@ impl Parameters {
         let machine_friendly = matches.is_present("machine_friendly");
 
         let inputs = if let Some(inputs) = matches.values_of("input") {
-            let mut vs = vec![];
+            let mut facts = vec![];
             for (ix, v) in inputs.enumerate() {
-                let t = tensor::for_string(v)?;
+                let (ix, v) = match named_input(v) {
+                    Some((name, v)) => (input_index(&raw_model, name)?, v),
+                    None => (ix, v),
+                };
+                if v.starts_with("@") && v.ends_with(".npz") {
+                    for (name, t) in tensor::for_npz(&v[1..])? {
+                        facts.push((input_index(&raw_model, &name)?, t));
+                    }
+                } else {
+                    facts.push((ix, tensor::for_string(v)?));
+                }
+            }
+            let mut vs = vec![None; facts.iter().map(|f| f.0 + 1).max().unwrap_or(0)];
+            for (ix, t) in facts {
                 // obliterate value in input (the analyser/optimizer would fold
                 // the graph)
                 let mut fact = TensorFact { value: Default::default(), ..t };
                 if let Some(axis) = matches.value_of("stream_axis") {
                     let axis = axis.parse::<usize>().unwrap();
-                    let shape = ShapeFact::closed(
-                        fact.shape
-                            .dims()
-                            .enumerate()
-                            .map(|(ix, d)| {
-                                if ix == axis {
-                                    GenericFact::Only(::tract_core::TDim::s())
-                                } else {
-                                    d
-                                }
-                            })
-                            .collect(),
-                    );
-                    fact.shape = shape;
+                    fact = ::tract_core::pulse::streaming_fact(&fact, axis)?;
                 }
-                vs.push(t.value.concretize());
-                let outlet = raw_model.inputs()?[ix];
+                vs[ix] = t.value.concretize();
+                let outlet = *raw_model.inputs()?.get(ix).ok_or("Too many inputs for model")?;
                 raw_model.set_fact(outlet, fact)?;
             }
             Some(vs)
@@ -355,9 +633,41 @@ impl Parameters {
             None
         };
 
-        let pulse: Option<usize> = matches.value_of("pulse").map(|s| s.parse()).inside_out()?;
+        let auto_pulse = matches.value_of("pulse") == Some("auto");
+        let pulse: Option<usize> =
+            matches.value_of("pulse").filter(|_| !auto_pulse).map(|s| s.parse()).inside_out()?;
+
+        if (pulse.is_some() || auto_pulse) && matches.value_of("stream_axis").is_none() {
+            let candidates = ::tract_core::pulse::stream_axis_candidates(&raw_model)?;
+            for (ix, axes) in candidates.iter().enumerate() {
+                let outlet = raw_model.inputs()?[ix];
+                let fact = raw_model.fact(outlet)?;
+                if fact.stream_info().ok().and_then(|s| s).is_some() {
+                    continue;
+                }
+                if axes.len() == 1 {
+                    info!("Input {}: guessed streaming axis {}", ix, axes[0]);
+                    let fact = ::tract_core::pulse::streaming_fact(fact, axes[0])?;
+                    raw_model.set_fact(outlet, fact)?;
+                } else if axes.len() > 1 {
+                    warn!("Input {}: can not choose streaming axis among {:?}", ix, axes);
+                }
+            }
+        }
+
+        let reference_model = if matches.subcommand_name() == Some("compare") {
+            Some(raw_model.clone())
+        } else {
+            None
+        };
 
-        let mut tract_model = if !matches.is_present("skip_analyse") {
+        // the analyse subcommand runs the analyser itself, to report failures,
+        // and bench-matrix and explore build every stage from the loaded model
+        let analyse = !matches.is_present("skip_analyse")
+            && matches.subcommand_name() != Some("analyse")
+            && matches.subcommand_name() != Some("bench-matrix")
+            && matches.subcommand_name() != Some("explore");
+        let mut tract_model = if analyse {
             info!("Running analyse");
             SomeModel::Typed(raw_model.into_typed()?)
         } else {
@@ -365,30 +675,56 @@ impl Parameters {
             SomeModel::Inference(raw_model)
         };
 
-        if matches.is_present("optimize") || matches.is_present("declutter") || pulse.is_some() {
+        let optimizer = optimizer_from_clap(matches)?;
+        let optim_log = matches.is_present("optim_log");
+
+        if matches.is_present("optimize")
+            || matches.is_present("declutter")
+            || pulse.is_some()
+            || auto_pulse
+        {
             if let SomeModel::Typed(typed) = tract_model {
                 info!("Declutter");
-                tract_model = SomeModel::Typed(typed.declutter()?);
+                let (typed, report) = optimizer.declutter(typed)?;
+                if optim_log {
+                    print_optimizer_report("Declutter", &report);
+                }
+                tract_model = SomeModel::Typed(typed);
             } else {
                 bail!("Can not run optimize without analyse")
             }
         }
 
-        if let (Some(pulse), &SomeModel::Typed(ref model)) = (pulse, &tract_model)
+        if let (true, &SomeModel::Typed(ref model)) = (pulse.is_some() || auto_pulse, &tract_model)
         {
             info!("Convert to normalized net");
             let normalized = model.clone().into_normalized()?;
+            let pulse = if let Some(pulse) = pulse {
+                pulse
+            } else {
+                let candidates =
+                    ::tract_core::pulse::PulsedModel::pulse_candidates(&normalized, DEFAULT_MAX_PULSE)?;
+                for c in &candidates {
+                    info!("Pulse candidate: {} (delay: {})", c.pulse, c.delay);
+                }
+                candidates.first().ok_or("No valid pulse size found")?.pulse
+            };
             info!("Pulsify {}", pulse);
             let pulsed = ::tract_core::pulse::PulsedModel::new(&normalized, pulse)?;
             tract_model = SomeModel::Pulsed(normalized, pulsed);
         };
 
         if matches.is_present("optimize") {
-            if let SomeModel::Typed(typed) = tract_model {
-                tract_model = SomeModel::Typed(typed.codegen()?);
-            } else if let SomeModel::Pulsed(_, pulsed) = tract_model {
-                tract_model = SomeModel::Typed(pulsed.into_typed()?.codegen()?);
+            let typed = match tract_model {
+                SomeModel::Typed(typed) => typed,
+                SomeModel::Pulsed(_, pulsed) => pulsed.into_typed()?,
+                _ => bail!("Can not run optimize without analyse"),
+            };
+            let (typed, report) = optimizer.codegen(typed)?;
+            if optim_log {
+                print_optimizer_report("Codegen", &report);
             }
+            tract_model = SomeModel::Typed(typed);
         }
 
         info!("Model ready");
@@ -397,14 +733,55 @@ impl Parameters {
             name: name.to_string(),
             graph,
             tract_model,
+            reference_model,
             tf_model,
             inputs,
             assertions: None,
             machine_friendly,
+            json: matches.is_present("json"),
         })
     }
 }
 
+/// Builds the optimiser from `--pass`, `--no-pass` and `--optim-iterations`.
+fn optimizer_from_clap(matches: &clap::ArgMatches) -> CliResult<Optimizer> {
+    let mut optimizer = Optimizer::default();
+    if let Some(passes) = matches.values_of("pass") {
+        optimizer = optimizer.only_passes(&passes.collect::<Vec<_>>())?;
+        let declutter = matches.is_present("declutter") || matches.is_present("optimize");
+        if declutter && optimizer.declutter_pass_names().is_empty() {
+            warn!("No declutter pass selected by --pass, declutter will not change the model");
+        }
+        if matches.is_present("optimize") && optimizer.codegen_pass_names().is_empty() {
+            warn!("No codegen pass selected by --pass, codegen will not change the model");
+        }
+    }
+    if let Some(passes) = matches.values_of("no_pass") {
+        for pass in passes {
+            optimizer = optimizer.without_pass(pass)?;
+        }
+    }
+    if let Some(max) = matches.value_of("optim_iterations") {
+        optimizer = optimizer.max_iterations(max.parse()?);
+    }
+    Ok(optimizer)
+}
+
+/// Prints the report on stderr, so it does not mix with the command output.
+fn print_optimizer_report(stage: &str, report: &OptimizerReport) {
+    eprintln!(
+        "{}: {} -> {} nodes in {} iteration(s){}",
+        stage,
+        report.nodes_before,
+        report.nodes_after,
+        report.iterations,
+        if report.converged { "" } else { " (not converged)" }
+    );
+    for rewrite in &report.rewrites {
+        eprintln!("  {}", rewrite);
+    }
+}
+
 pub enum ProfilingMode {
     Regular { max_iters: u64, max_time: u64 },
     RegularBenching { max_iters: u64, max_time: u64 },
@@ -440,6 +817,7 @@ pub fn display_options_from_clap(matches: &clap::ArgMatches) -> CliResult<Displa
         node_name: matches.value_of("node_name").map(String::from),
         op_name: matches.value_of("op_name").map(String::from),
         successors: matches.value_of("successors").map(|id| id.parse().unwrap()),
+        json: matches.is_present("json"),
     })
 }
 
@@ -462,34 +840,94 @@ impl Assertions {
 
 /// Handles the command-line input.
 fn handle(matches: clap::ArgMatches) -> CliResult<()> {
+    let json = matches.is_present("json");
+
     if matches.is_present("list_ops") {
+        let mut ops = serde_json::Map::new();
         #[cfg(feature = "onnx")]
         {
             let onnx = tract_onnx::onnx();
-            let names = onnx.op_register.names().sorted().into_iter().join(", ");
-            println!("Onnx:\n");
-            println!("{}", names);
-            println!("\n");
+            let names: Vec<String> =
+                onnx.op_register.names().sorted().into_iter().map(|s| s.to_string()).collect();
+            if json {
+                ops.insert("onnx".to_string(), names.into());
+            } else {
+                println!("Onnx:\n");
+                println!("{}", names.join(", "));
+                println!("\n");
+            }
         }
         #[cfg(feature = "tf")]
         {
             let tf = tract_tensorflow::tensorflow();
-            let names = tf.op_register.names().sorted().into_iter().join(", ");
-            println!("Tensorflow:\n");
-            println!("{}", names);
-            println!("\n");
+            let names: Vec<String> =
+                tf.op_register.names().sorted().into_iter().map(|s| s.to_string()).collect();
+            if json {
+                ops.insert("tensorflow".to_string(), names.into());
+            } else {
+                println!("Tensorflow:\n");
+                println!("{}", names.join(", "));
+                println!("\n");
+            }
         }
+        if json {
+            json::print(&ops.into())?;
+        }
+        return Ok(());
+    }
+
+    if matches.is_present("list_passes") {
+        let names = Optimizer::default().pass_names();
+        if json {
+            return json::print(&names.into());
+        }
+        println!("{}", names.join("\n"));
         return Ok(());
     }
 
+    if let ("diff", Some(m)) = matches.subcommand() {
+        let mut inputs = vec![];
+        for v in matches.values_of("input").into_iter().flat_map(|vs| vs) {
+            inputs.push(TensorFact { value: Default::default(), ..tensor::for_string(v)? });
+        }
+        let (first, second) = (m.value_of("first").unwrap(), m.value_of("second").unwrap());
+        return diff::handle(first, second, &inputs, json);
+    }
+
+    if let ("conform", Some(m)) = matches.subcommand() {
+        #[cfg(not(feature = "onnx"))]
+        {
+            let _ = m;
+            bail!("Tract compiled without onnx feature");
+        }
+        #[cfg(feature = "onnx")]
+        {
+            let atol = m.value_of("atol").map(|s| s.parse()).transpose()?.unwrap_or(1e-4);
+            let rtol = m.value_of("rtol").map(|s| s.parse()).transpose()?.unwrap_or(1e-3);
+            let csv = m.value_of("csv");
+            return conform::handle(m.value_of("dir").unwrap(), atol, rtol, csv, json);
+        }
+    }
+
     let mut params = Parameters::from_clap(&matches)?;
 
     match matches.subcommand() {
-        ("compare", Some(m)) => compare::handle(params, display_options_from_clap(m)?),
+        ("analyse", Some(m)) => analyse::handle(params, display_options_from_clap(m)?),
+
+        ("compare", Some(m)) => compare::handle(
+            params,
+            m.value_of("reference"),
+            regression::Tolerances::from_clap(m, 1e-4, 1e-3)?,
+            display_options_from_clap(m)?,
+        ),
 
         ("run", Some(m)) => {
+            if let Some(reference) = m.value_of("assert-outputs-from") {
+                let tolerances = regression::Tolerances::from_clap(m, 1e-5, 1e-5)?;
+                return regression::handle(params, reference, tolerances);
+            }
             params.assertions = Some(Assertions::from_clap(m)?);
-            run::handle(params)
+            run::handle(params, m.value_of("save-outputs"))
         }
 
         /*
@@ -498,9 +936,22 @@ fn handle(matches: clap::ArgMatches) -> CliResult<()> {
         }
         */
 
+        ("extract", Some(m)) => extract::handle(
+            params,
+            m.values_of("from").map(|v| v.collect()).unwrap_or(vec![]),
+            m.values_of("to").map(|v| v.collect()).unwrap_or(vec![]),
+            m.value_of("output"),
+            display_options_from_clap(m)?,
+        ),
+
         ("stream-check", Some(m)) => stream_check::handle(params, display_options_from_clap(m)?),
 
-        ("draw", _) => crate::draw::render(&params.tract_model),
+        ("pulse-info", _) => pulse_info::handle(params),
+
+        ("draw", Some(m)) => {
+            let options = draw::DrawOptions::from_clap(m, params.json)?;
+            draw::render(&params, options)
+        }
 
         ("dump", Some(m)) => {
             params.assertions = Some(Assertions::from_clap(m)?);
@@ -511,6 +962,12 @@ fn handle(matches: clap::ArgMatches) -> CliResult<()> {
             profile::handle(params, ProfilingMode::from_clap(&m)?, display_options_from_clap(m)?)
         }
 
+        ("bench-matrix", Some(m)) => {
+            bench_matrix::handle(params, bench_matrix::BenchMatrixOptions::from_clap(m)?)
+        }
+
+        ("explore", Some(m)) => explore::handle(params, m.value_of("stage").unwrap_or("typed")),
+
         (s, _) => bail!("Unknown subcommand {}.", s),
     }
 }
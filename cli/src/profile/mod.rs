@@ -1,27 +1,44 @@
-use crate::rusage::Duration;
+use crate::rusage::{Duration, Instant};
 use ansi_term::Color::*;
 use std::collections::HashMap;
 
 use crate::errors::*;
 use crate::format::*;
 use itertools::Itertools;
+use serde_json::json;
 use tract_core::model::{Model, Node, TensorInfo};
+use tract_core::ops::prelude::Cost;
 
 use crate::display_graph::DisplayOptions;
+use crate::tensor::make_inputs_for_model;
 use crate::{Parameters, ProfilingMode, SomeGraphDef};
 
 mod regular;
+mod roofline;
 //mod streaming;
 
+pub use self::roofline::Roofline;
+
 #[derive(Debug)]
 pub struct ProfileData {
     pub nodes: HashMap<usize, Duration>,
+    /// Estimated cost of the nodes, when their op and facts allow it.
+    pub costs: HashMap<usize, Cost>,
+    /// Machine capabilities, for efficiency figures.
+    pub roofline: Option<Roofline>,
 }
 
 impl ProfileData {
     pub fn new<TI:TensorInfo>(model: &Model<TI>) -> ProfileData {
+        let costs = model
+            .nodes()
+            .iter()
+            .filter_map(|n| Some((n.id, roofline::node_cost(model, n).ok()??)))
+            .collect();
         ProfileData {
             nodes: HashMap::with_capacity(model.nodes().len()),
+            costs,
+            roofline: None,
         }
     }
 
@@ -42,6 +59,9 @@ impl ProfileData {
                 .with_graph_def(&graph)?;
         for (ix, measure) in self.nodes.iter() {
             display_graph.add_node_label(*ix, dur_avg_oneline_ratio(*measure, sum))?;
+            if let Some(throughput) = self.throughput_label(*ix, *measure) {
+                display_graph.add_node_label(*ix, throughput)?;
+            }
         }
         let top5: Vec<usize> = self
             .nodes
@@ -62,9 +82,9 @@ impl ProfileData {
         Ok(())
     }
 
-    pub fn print_most_consuming_ops<TI:TensorInfo>(&self, model: &Model<TI>) -> CliResult<()> {
-        let sum = self.summed();
-        println!("Most time consuming operations:");
+    /// Time spent per operation, summed over the nodes, with the number of
+    /// nodes, most consuming first.
+    fn by_op<TI: TensorInfo>(&self, model: &Model<TI>) -> Vec<(String, usize, Duration)> {
         let mut operations = HashMap::new();
         let mut counters = HashMap::new();
         for (node, dur) in &self.nodes {
@@ -80,25 +100,165 @@ impl ProfileData {
             cell.counter = 1;
             *counters.entry(node.op.name().to_string()).or_insert(0) += 1;
         }
-        let mut operations: Vec<(&str, Duration)> =
-            operations.iter().map(|(s, d)| (&**s, *d)).collect();
-        operations.sort_by(|(_, a), (_, b)| {
+        let mut operations: Vec<(String, usize, Duration)> =
+            operations.into_iter().map(|(s, d)| (s.clone(), counters[&s], d)).collect();
+        operations.sort_by(|(_, _, a), (_, _, b)| {
             a.avg_real()
                 .partial_cmp(&b.avg_real())
                 .unwrap_or(::std::cmp::Ordering::Greater)
                 .reverse()
         });
-        for (operation, measure) in operations.iter().take(5) {
+        operations
+    }
+
+    pub fn print_most_consuming_ops<TI:TensorInfo>(&self, model: &Model<TI>) -> CliResult<()> {
+        let sum = self.summed();
+        let mut flops: HashMap<String, u64> = HashMap::new();
+        for (node, cost) in &self.costs {
+            *flops.entry(model.node(*node).op.name().to_string()).or_insert(0) += cost.flops;
+        }
+        println!("Most time consuming operations:");
+        for (operation, calls, measure) in self.by_op(model).iter().take(5) {
+            let throughput = match flops.get(operation) {
+                Some(&flops) if flops > 0 => format!(" {}", gflops(flops, measure.avg_real())),
+                _ => String::new(),
+            };
             println!(
-                "{:20} {:3} calls: {}",
-                Blue.bold().paint(*operation),
-                counters[&**operation],
-                dur_avg_oneline_ratio(*measure, sum)
+                "{:20} {:3} calls: {}{}",
+                Blue.bold().paint(&**operation),
+                calls,
+                dur_avg_oneline_ratio(*measure, sum),
+                throughput,
             );
         }
         Ok(())
     }
 
+    /// The efficiency of a node relative to the roofline, if its cost is
+    /// known and the machine was measured.
+    fn efficiency(&self, node: usize, measure: Duration) -> Option<f64> {
+        let cost = self.costs.get(&node).filter(|c| c.flops > 0)?;
+        Some(self.roofline?.efficiency(cost, measure.avg_real()))
+    }
+
+    fn throughput_label(&self, node: usize, measure: Duration) -> Option<String> {
+        let cost = self.costs.get(&node).filter(|c| c.flops > 0)?;
+        let mut label = format!(
+            "{} {:.2} flop/B",
+            gflops(cost.flops, measure.avg_real()),
+            cost.intensity()
+        );
+        if let Some(efficiency) = self.efficiency(node, measure) {
+            let text = format!("{:.0}% of roofline", efficiency * 100.0);
+            if efficiency_is_low(efficiency) {
+                label.push_str(&format!(" {}", Red.bold().paint(text)));
+            } else {
+                label.push_str(&format!(" {}", text));
+            }
+        }
+        Some(label)
+    }
+
+    /// Total operation count, overall throughput, and the nodes far below
+    /// what the machine can do, among the ones weighting in the total time.
+    pub fn print_efficiency<TI: TensorInfo>(
+        &self,
+        model: &Model<TI>,
+        entire: Duration,
+    ) -> CliResult<()> {
+        let flops: u64 = self.costs.values().map(|c| c.flops).sum();
+        if flops == 0 {
+            return Ok(());
+        }
+        let throughput = White.bold().paint(gflops(flops, entire.avg_real()));
+        let roofline = match self.roofline {
+            Some(roofline) => roofline,
+            None => {
+                println!("Estimated {:.3} GFLOP/i, {}", flops as f64 * 1e-9, throughput);
+                return Ok(());
+            }
+        };
+        println!(
+            "Estimated {:.3} GFLOP/i, {}, {:.0}% of the measured matmul peak",
+            flops as f64 * 1e-9,
+            throughput,
+            flops as f64 / entire.avg_real() / roofline.peak_flops * 100.0
+        );
+        println!(
+            "Machine: matmul peak {:.2} GFLOP/s, memory bandwidth {:.2} GB/s",
+            roofline.peak_flops * 1e-9,
+            roofline.bandwidth * 1e-9,
+        );
+        let sum = self.summed();
+        let mut low: Vec<(usize, f64)> = self
+            .nodes
+            .iter()
+            .filter(|(_, measure)| measure.avg_real() / sum.avg_real() >= 0.01)
+            .filter_map(|(&n, &measure)| Some((n, self.efficiency(n, measure)?)))
+            .filter(|&(_, efficiency)| efficiency_is_low(efficiency))
+            .collect();
+        low.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(::std::cmp::Ordering::Equal));
+        if low.len() > 0 {
+            println!("Nodes far below the roofline:");
+            for (n, efficiency) in low {
+                let node = model.node(n);
+                println!(
+                    "  {} {} ({}): {:.1}%",
+                    n,
+                    Red.bold().paint(&*node.name),
+                    node.op.name(),
+                    efficiency * 100.0
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Per-node and per-operation timings, with the time of the entire
+    /// network, as JSON.
+    pub fn to_json<TI: TensorInfo>(
+        &self,
+        model: &Model<TI>,
+        entire: Duration,
+    ) -> CliResult<serde_json::Value> {
+        let sum = self.summed();
+        let mut nodes = vec![];
+        for n in model.eval_order()? {
+            if let Some(measure) = self.nodes.get(&n) {
+                let mut node = crate::json::node(model, model.node(n))?;
+                node["time"] = crate::json::duration(*measure);
+                node["ratio"] = (measure.avg_real() / sum.avg_real()).into();
+                if let Some(cost) = self.costs.get(&n) {
+                    node["flops"] = cost.flops.into();
+                    node["bytes"] = cost.bytes.into();
+                    node["gflops"] = (cost.flops as f64 / measure.avg_real() * 1e-9).into();
+                    node["efficiency"] = self.efficiency(n, *measure).into();
+                }
+                nodes.push(node);
+            }
+        }
+        let ops: Vec<_> = self
+            .by_op(model)
+            .iter()
+            .map(|(op, calls, measure)| {
+                json!({ "op": op, "calls": calls, "time": crate::json::duration(*measure) })
+            })
+            .collect();
+        let flops: u64 = self.costs.values().map(|c| c.flops).sum();
+        let roofline = self.roofline.map(|r| {
+            json!({ "peak_gflops": r.peak_flops * 1e-9, "bandwidth_gbs": r.bandwidth * 1e-9 })
+        });
+        Ok(json!({
+            "entire": crate::json::duration(entire),
+            "accounted": crate::json::duration(sum),
+            "flops": flops,
+            "gflops": flops as f64 / entire.avg_real() * 1e-9,
+            "roofline": roofline,
+            "nodes": nodes,
+            "ops": ops,
+        }))
+    }
+
     pub fn summed(&self) -> Duration {
         let total_real = self.nodes.values().map(|n| n.avg_real()).sum();
         let total_sys = self.nodes.values().map(|n| n.avg_sys()).sum();
@@ -112,6 +272,14 @@ impl ProfileData {
     }
 }
 
+fn gflops(flops: u64, seconds: f64) -> String {
+    format!("{:.2} GFLOP/s", flops as f64 / seconds * 1e-9)
+}
+
+fn efficiency_is_low(efficiency: f64) -> bool {
+    efficiency < roofline::LOW_EFFICIENCY
+}
+
 /// Handles the `profile` subcommand.
 pub fn handle(
     params: Parameters,
@@ -123,3 +291,31 @@ pub fn handle(
         ProfilingMode::RegularBenching { .. } => regular::handle_benching(params, profiling),
     }
 }
+
+/// Calls `f` until `max_iters` iterations or `max_time` ms are reached, as
+/// `profile --bench` does, and returns the measure.
+pub fn bench<F: FnMut() -> CliResult<()>>(
+    max_iters: u64,
+    max_time: u64,
+    mut f: F,
+) -> CliResult<Duration> {
+    let mut iters = 0;
+    let start = Instant::now();
+    while iters < max_iters && start.elapsed_real() < (max_time as f64 * 1e-3) {
+        f()?;
+        iters += 1;
+    }
+    Ok(Duration::since(&start, iters))
+}
+
+/// Measures each node of the model on the `--input` values, or random ones,
+/// as the `profile` subcommand does, without reporting anything.
+pub fn measure_nodes<TI: TensorInfo>(
+    model: &Model<TI>,
+    params: &Parameters,
+    max_iters: u64,
+    max_time: u64,
+) -> CliResult<ProfileData> {
+    let inputs = make_inputs_for_model(model, params.inputs.as_ref())?;
+    regular::profile_nodes(model, params, inputs, max_iters, max_time, false)
+}
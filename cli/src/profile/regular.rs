@@ -3,18 +3,20 @@ use atty;
 use pbr::ProgressBar;
 
 use log::Level::Info;
+use serde_json::json;
 
 use crate::display_graph::DisplayOptions;
 use crate::errors::*;
 use crate::{Parameters, ProfilingMode, SomeModel};
 
 use crate::format::*;
-use crate::profile::ProfileData;
+use crate::profile::{ProfileData, Roofline};
 use crate::rusage::{Duration, Instant};
-use crate::tensor::make_inputs;
+use crate::tensor::make_inputs_for_model;
 
-use tract_core::plan::{SimplePlan, SimpleState};
 use tract_core::model::{Model, TensorInfo};
+use tract_core::plan::{SimplePlan, SimpleState};
+use tract_core::ops::prelude::*;
 
 pub fn handle_benching(params: Parameters, profiling: ProfilingMode) -> CliResult<()> {
     match &params.tract_model {
@@ -38,16 +40,17 @@ fn handle_benching_t<TI:TensorInfo>(model: &Model<TI>, params: &Parameters, prof
 
     let plan = SimplePlan::new(model)?;
     let mut state = SimpleState::new(plan)?;
+    let inputs = make_inputs_for_model(model, params.inputs.as_ref())?;
     info!("Starting bench itself");
-    let mut iters = 0;
-    let start = Instant::now();
-    while iters < max_iters && start.elapsed_real() < (max_time as f64 * 1e-3) {
-        state.run(make_inputs(&[model.input_fact()?.to_tensor_fact()])?)?;
-        iters += 1;
-    }
-    let dur = Duration::since(&start, iters);
-
-    if params.machine_friendly {
+    let dur = crate::profile::bench(max_iters, max_time, || {
+        state.run(inputs.clone())?;
+        Ok(())
+    })?;
+    let iters = dur.counter;
+
+    if params.json {
+        crate::json::print(&json!({ "iterations": iters, "time": crate::json::duration(dur) }))?;
+    } else if params.machine_friendly {
         println!("real: {}", dur.avg_real());
         println!("user: {}", dur.avg_user());
         println!("sys: {}", dur.avg_sys());
@@ -86,25 +89,76 @@ pub fn handle_t<TI:TensorInfo>(
 
     info!("Running entire network");
     let plan = SimplePlan::new(model)?;
+    let inputs = make_inputs_for_model(model, params.inputs.as_ref())?;
     let mut iters = 0;
     let start = Instant::now();
     while iters < max_iters && start.elapsed_real() < (max_time as f64 * 1e-3) {
-        let _ = plan.run(make_inputs(&[model.input_fact()?.to_tensor_fact()])?)?;
+        let _ = plan.run(inputs.clone())?;
         iters += 1;
     }
     let entire = Duration::since(&start, iters);
 
+    let mut profile = profile_nodes(model, params, inputs, max_iters, max_time, true)?;
+
+    info!("Measuring machine peak throughput and bandwidth");
+    profile.roofline = Some(Roofline::measure()?);
+
+    if params.json {
+        return crate::json::print(&profile.to_json(model, entire)?);
+    }
+
+    print_header(format!("Summary for {}:", params.name), &White.normal());
+
+    profile.print_most_consuming_nodes(model, &params.graph, display_options)?;
+    println!();
+
+    profile.print_most_consuming_ops(model)?;
+    println!();
+
+    println!("Entire network performance: {}", dur_avg_oneline(entire));
+    println!(
+        "Accounted by ops: {}",
+        dur_avg_oneline_ratio(profile.summed(), entire)
+    );
+    profile.print_efficiency(model, entire)?;
+
+    if log_enabled!(Info) {
+        println!(
+            "(Real: {} in total, with max_iters={:e} and max_time={:?}ms.)",
+            White.paint(format!("{:.3} ms", profile.summed().total_real * 1e3)),
+            max_iters as f32,
+            max_time,
+        );
+    }
+
+    Ok(())
+}
+
+/// Measures each node of the model on `inputs`, for up to `max_iters`
+/// iterations or `max_time` ms per node. With `report`, shows progress and
+/// per-node timings as they come.
+pub fn profile_nodes<TI: TensorInfo>(
+    model: &Model<TI>,
+    params: &Parameters,
+    inputs: TVec<Tensor>,
+    max_iters: u64,
+    max_time: u64,
+    report: bool,
+) -> CliResult<ProfileData> {
     info!("Running {} iterations max. for each node.", max_iters);
     info!("Running for {} ms max. for each node.", max_time);
 
+    let plan = SimplePlan::new(model)?;
     let mut state = SimpleState::new(&plan)?;
-    state.set_inputs(make_inputs(&[model.input_fact()?.to_tensor_fact()])?)?;
+    state.set_inputs(inputs)?;
     debug!("Using execution plan: {:?}", plan);
 
     let mut profile = ProfileData::new(model);
     let mut progress = ProgressBar::new(plan.order.len() as u64);
+    let interactive = report && atty::is(atty::Stream::Stdout) && !params.json;
+    let verbose = report && log_enabled!(Info) && !params.json;
 
-    if log_enabled!(Info) {
+    if verbose {
         println!();
         print_header(format!("Profiling for {}:", params.name), &White.normal());
     }
@@ -113,12 +167,12 @@ pub fn handle_t<TI:TensorInfo>(
     for &n in &plan.order {
         let node = &model.nodes()[n];
 
-        if atty::is(atty::Stream::Stdout) {
+        if interactive {
             progress.inc();
         }
 
         if node.op.name() == "Source" {
-            if log_enabled!(Info) {
+            if verbose {
                 print_node(
                     &node,
                     &params.graph,
@@ -142,7 +196,7 @@ pub fn handle_t<TI:TensorInfo>(
         let measure = Duration::since(&start, iters);
 
         // Print the results for the node.
-        if log_enabled!(Info) {
+        if verbose {
             print_node(
                 &node,
                 &params.graph,
@@ -157,32 +211,9 @@ pub fn handle_t<TI:TensorInfo>(
         profile.add(&node, measure)?;
     }
 
-    if atty::is(atty::Stream::Stdout) {
+    if interactive {
         progress.finish_print("");
     }
 
-    print_header(format!("Summary for {}:", params.name), &White.normal());
-
-    profile.print_most_consuming_nodes(model, &params.graph, display_options)?;
-    println!();
-
-    profile.print_most_consuming_ops(model)?;
-    println!();
-
-    println!("Entire network performance: {}", dur_avg_oneline(entire));
-    println!(
-        "Accounted by ops: {}",
-        dur_avg_oneline_ratio(profile.summed(), entire)
-    );
-
-    if log_enabled!(Info) {
-        println!(
-            "(Real: {} in total, with max_iters={:e} and max_time={:?}ms.)",
-            White.paint(format!("{:.3} ms", profile.summed().total_real * 1e3)),
-            max_iters as f32,
-            max_time,
-        );
-    }
-
-    Ok(())
+    Ok(profile)
 }
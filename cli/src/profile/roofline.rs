@@ -0,0 +1,96 @@
+use std::time::Instant;
+
+use tract_core::model::{Model, Node, TensorInfo};
+use tract_core::ops::math::MatMul;
+use tract_core::ops::prelude::*;
+
+use crate::errors::*;
+
+/// Nodes achieving less than this fraction of their roofline bound are
+/// flagged in reports.
+pub const LOW_EFFICIENCY: f64 = 0.1;
+
+/// Measured capabilities of the machine: the arithmetic throughput of a
+/// large matrix multiplication, and the memory bandwidth of a large copy.
+#[derive(Clone, Copy, Debug)]
+pub struct Roofline {
+    /// Floating point operations per second.
+    pub peak_flops: f64,
+    /// Bytes per second.
+    pub bandwidth: f64,
+}
+
+impl Roofline {
+    pub fn measure() -> CliResult<Roofline> {
+        Ok(Roofline { peak_flops: measure_mat_mul()?, bandwidth: measure_copy()? })
+    }
+
+    /// The best throughput a node of this cost can hope for: bound by the
+    /// bandwidth for low arithmetic intensities, by the peak otherwise.
+    pub fn attainable(&self, cost: &Cost) -> f64 {
+        self.peak_flops.min(cost.intensity() * self.bandwidth)
+    }
+
+    /// The fraction of its attainable throughput a node of this cost
+    /// achieves in `seconds`.
+    pub fn efficiency(&self, cost: &Cost, seconds: f64) -> f64 {
+        cost.flops as f64 / seconds / self.attainable(cost)
+    }
+}
+
+/// Runs `f` for about 100ms, returning the number of calls and the time.
+fn time<F: FnMut() -> CliResult<()>>(mut f: F) -> CliResult<(u64, f64)> {
+    f()?;
+    let start = Instant::now();
+    let mut iters = 0;
+    loop {
+        f()?;
+        iters += 1;
+        let elapsed = start.elapsed();
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+        if elapsed > 0.1 {
+            return Ok((iters, elapsed));
+        }
+    }
+}
+
+fn measure_mat_mul() -> CliResult<f64> {
+    const SIZE: usize = 256;
+    let fact = TensorFact::dt_shape(DatumType::F32, [SIZE, SIZE].as_ref());
+    let a: SharedTensor = crate::tensor::tensor_for_fact(&fact, None)?.into();
+    let b: SharedTensor = crate::tensor::tensor_for_fact(&fact, None)?.into();
+    let op = MatMul::default();
+    let (iters, elapsed) = time(|| {
+        op.eval(tvec!(a.clone(), b.clone()))?;
+        Ok(())
+    })?;
+    Ok((2 * SIZE * SIZE * SIZE) as f64 * iters as f64 / elapsed)
+}
+
+fn measure_copy() -> CliResult<f64> {
+    const LEN: usize = 16 * 1024 * 1024;
+    let source = vec![1.0f32; LEN];
+    let mut dest = vec![0.0f32; LEN];
+    // reading back the copy keeps it from being optimized away
+    let mut sink = 0.0;
+    let (iters, elapsed) = time(|| {
+        dest.copy_from_slice(&source);
+        sink += dest[LEN - 1];
+        Ok(())
+    })?;
+    debug!("Copied {} times", sink);
+    Ok((2 * LEN * std::mem::size_of::<f32>()) as f64 * iters as f64 / elapsed)
+}
+
+/// The estimated cost of one evaluation of the node, from the model facts.
+pub fn node_cost<TI: TensorInfo>(model: &Model<TI>, node: &Node<TI>) -> CliResult<Option<Cost>> {
+    let inputs: Vec<TensorFact> = node
+        .inputs
+        .iter()
+        .map(|i| Ok(model.fact(*i)?.to_tensor_fact()))
+        .collect::<CliResult<_>>()?;
+    let outputs: Vec<TensorFact> = node.outputs.iter().map(|o| o.fact.to_tensor_fact()).collect();
+    let inputs: Vec<&TensorFact> = inputs.iter().collect();
+    let outputs: Vec<&TensorFact> = outputs.iter().collect();
+    Ok(node.op.cost(&inputs, &outputs)?)
+}
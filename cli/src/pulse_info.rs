@@ -0,0 +1,70 @@
+use crate::errors::*;
+use crate::{Parameters, SomeModel};
+use serde_json::json;
+
+/// Handles the `pulse-info` subcommand.
+pub fn handle(params: Parameters) -> CliResult<()> {
+    let pulsed = if let SomeModel::Pulsed(_, ref p) = params.tract_model {
+        p
+    } else {
+        bail!("pulse-info requires a pulsed model (use --pulse)")
+    };
+    let input = pulsed.input_fact()?;
+    let infos = pulsed.output_info()?;
+
+    if params.json {
+        let outputs = infos
+            .iter()
+            .enumerate()
+            .map(|(ix, info)| {
+                Ok(json!({
+                    "name": pulsed.node(pulsed.outputs()?[ix].node).name,
+                    "axis": info.axis,
+                    "pulse": info.pulse,
+                    "delay": info.delay,
+                    "input_delay": info.input_delay,
+                    "len": crate::json::dim(&info.len),
+                }))
+            })
+            .collect::<CliResult<Vec<_>>>()?;
+        return crate::json::print(&json!({
+            "input": { "pulse": input.pulse(), "axis": input.axis },
+            "outputs": outputs,
+        }));
+    }
+
+    if params.machine_friendly {
+        println!("input_pulse: {}", input.pulse());
+        for (ix, info) in infos.iter().enumerate() {
+            println!("output_{}_axis: {}", ix, info.axis);
+            println!("output_{}_pulse: {}", ix, info.pulse);
+            println!("output_{}_delay: {}", ix, info.delay);
+            println!("output_{}_input_delay: {}", ix, info.input_delay);
+            println!("output_{}_len: {:?}", ix, info.len);
+        }
+        return Ok(());
+    }
+
+    println!("Input pulse: {} (axis {})", input.pulse(), input.axis);
+    let mut table = table!([
+        "output",
+        "axis",
+        "pulse",
+        "delay (frames)",
+        "delay (input samples)",
+        "length"
+    ]);
+    for (ix, info) in infos.iter().enumerate() {
+        let name = &pulsed.node(pulsed.outputs()?[ix].node).name;
+        table.add_row(row![
+            format!("#{} {}", ix, name),
+            info.axis,
+            info.pulse,
+            info.delay,
+            info.input_delay,
+            format!("{:?}", info.len)
+        ]);
+    }
+    table.printstd();
+    Ok(())
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use ansi_term::Color::*;
+use serde_json::json;
+use tract_core::ops::prelude::*;
+use tract_core::plan::{SimplePlan, SimpleState};
+use tract_core::tensor::Discrepancy;
+
+use crate::errors::*;
+use crate::{Parameters, SomeModel};
+
+/// Absolute and relative tolerances, with overrides by reference name.
+pub struct Tolerances {
+    pub atol: f64,
+    pub rtol: f64,
+    pub by_name: HashMap<String, (f64, f64)>,
+}
+
+impl Tolerances {
+    /// Reads `--atol`, `--rtol` and `--tolerance`, with the given defaults.
+    pub fn from_clap(matches: &clap::ArgMatches, atol: f64, rtol: f64) -> CliResult<Tolerances> {
+        let atol = matches.value_of("atol").map(|s| s.parse()).transpose()?.unwrap_or(atol);
+        let rtol = matches.value_of("rtol").map(|s| s.parse()).transpose()?.unwrap_or(rtol);
+        let mut by_name = HashMap::new();
+        for spec in matches.values_of("tolerance").into_iter().flat_map(|v| v) {
+            let mut split = spec.rsplitn(2, '=');
+            let (tols, name) = match (split.next(), split.next()) {
+                (Some(tols), Some(name)) => (tols, name),
+                _ => bail!("Tolerance should be given as name=atol[,rtol], got {}", spec),
+            };
+            let mut tols = tols.split(',');
+            let node_atol = tols.next().unwrap().parse()?;
+            let node_rtol = tols.next().map(|s| s.parse()).transpose()?.unwrap_or(rtol);
+            by_name.insert(name.to_string(), (node_atol, node_rtol));
+        }
+        Ok(Tolerances { atol, rtol, by_name })
+    }
+
+    pub fn for_name(&self, name: &str) -> (f64, f64) {
+        self.by_name.get(name).cloned().unwrap_or((self.atol, self.rtol))
+    }
+}
+
+/// Handles `run --assert-outputs-from`: runs the model and checks outputs,
+/// and intermediate values, against the named arrays of a .npz file.
+pub fn handle(params: Parameters, reference: &str, tolerances: Tolerances) -> CliResult<()> {
+    let mut expected = vec![];
+    for (name, fact) in crate::tensor::for_npz(reference)? {
+        let value =
+            fact.value.concretize().ok_or(format!("No value for {} in {}", name, reference))?;
+        expected.push((name, value.as_tensor().clone()));
+    }
+    handle_named(params, &expected, tolerances)
+}
+
+/// Runs the model and checks the node outputs named in `expected`. Model
+/// inputs found there are used as inputs, unless given on the command line.
+pub fn handle_named(
+    params: Parameters,
+    expected: &[(String, Tensor)],
+    tolerances: Tolerances,
+) -> CliResult<()> {
+    match &params.tract_model {
+        SomeModel::Inference(m) => check(m, &params, expected, &tolerances),
+        SomeModel::Typed(m) => check(m, &params, expected, &tolerances),
+        SomeModel::Normalized(m) => check(m, &params, expected, &tolerances),
+        SomeModel::Pulsed(_, _) => bail!("Regression checks do not support pulsed models"),
+    }
+}
+
+fn outlet_name<TI: TensorInfo>(model: &Model<TI>, outlet: OutletId) -> String {
+    let node = model.node(outlet.node);
+    if node.outputs.len() == 1 {
+        node.name.clone()
+    } else {
+        format!("{}:{}", node.name, outlet.slot)
+    }
+}
+
+fn outlet_for_name<TI: TensorInfo>(model: &Model<TI>, name: &str) -> CliResult<OutletId> {
+    if let Ok(node) = model.node_by_name(name) {
+        return Ok(OutletId::new(node.id, 0));
+    }
+    if let Some(colon) = name.rfind(':') {
+        if let (Ok(node), Ok(slot)) =
+            (model.node_by_name(&name[..colon]), name[colon + 1..].parse::<usize>())
+        {
+            if slot < node.outputs.len() {
+                return Ok(OutletId::new(node.id, slot));
+            }
+        }
+    }
+    let keys: Vec<String> = model
+        .inputs()?
+        .iter()
+        .chain(model.outputs()?.iter())
+        .map(|&o| outlet_name(model, o))
+        .collect();
+    bail!(
+        "Reference {} does not match any node output: keys should be node or node:slot names, \
+         like the model inputs and outputs {}",
+        name,
+        keys.join(", ")
+    )
+}
+
+fn check<TI: TensorInfo>(
+    model: &Model<TI>,
+    params: &Parameters,
+    named: &[(String, Tensor)],
+    tolerances: &Tolerances,
+) -> CliResult<()> {
+    let mut expected: HashMap<OutletId, (String, Tensor)> = HashMap::new();
+    for (name, tensor) in named {
+        let outlet = outlet_for_name(model, name)?;
+        expected.insert(outlet, (name.clone(), tensor.clone()));
+    }
+    for output in model.outputs()? {
+        if !expected.contains_key(output) {
+            warn!("No reference for output {}", model.node(output.node).name);
+        }
+    }
+
+    let mut inputs = tvec!();
+    for (ix, input) in model.inputs()?.iter().enumerate() {
+        let given = params.inputs.as_ref().and_then(|v| v.get(ix)).and_then(|t| t.as_ref());
+        inputs.push(match (given, expected.get(input)) {
+            (Some(value), _) => value.as_tensor().clone(),
+            (None, Some((_, value))) => value.clone(),
+            (None, None) => bail!(
+                "No value for input {}: give it with --input or in the reference file",
+                model.node(input.node).name
+            ),
+        });
+    }
+
+    let plan = SimplePlan::new(model)?;
+    let mut state = SimpleState::new(&plan)?;
+    state.set_inputs(inputs)?;
+
+    let mut checks = vec![];
+    for n in model.eval_order()? {
+        let node = model.node(n);
+        if !node.op_is::<tract_core::ops::source::Source>() {
+            state.compute_one(n)?;
+        }
+        for slot in 0..node.outputs.len() {
+            let (name, exp) = match expected.get(&OutletId::new(n, slot)) {
+                Some(it) => it,
+                None => continue,
+            };
+            let (atol, rtol) = tolerances.for_name(name);
+            let got = &state.values[n].as_ref().unwrap()[slot];
+            checks.push(Check::new(name.clone(), got, exp, atol, rtol));
+        }
+    }
+    report(&checks, params.json)
+}
+
+/// Prints the checks, as a table or as JSON, and fails if any of them does.
+pub fn report(checks: &[Check], json: bool) -> CliResult<()> {
+    let failures: Vec<&Check> = checks.iter().filter(|c| !c.is_ok()).collect();
+
+    if json {
+        let json: Vec<_> = checks.iter().map(Check::to_json).collect();
+        crate::json::print(&json!({
+            "checks": json,
+            "ok": failures.len() == 0,
+            "first_failure": failures.first().map(|c| &c.name),
+        }))?;
+    } else {
+        let mut table =
+            table!(["node", "shape", "max abs", "max rel", "max ulp", "atol", "rtol", "status"]);
+        for check in checks {
+            let shape = format!("{:?}", check.shape);
+            let (atol, rtol) = (check.atol, check.rtol);
+            table.add_row(match &check.result {
+                Ok(d) => {
+                    let status = if d.is_ok() {
+                        Green.paint("OK").to_string()
+                    } else {
+                        let first = d.first_failure.unwrap();
+                        Red.paint(format!("{} out of tolerance, first at {}", d.failures, first))
+                            .to_string()
+                    };
+                    row![check.name, shape, format!("{:e}", d.max_abs), format!("{:e}", d.max_rel),
+                        d.max_ulp, atol, rtol, status]
+                }
+                Err(e) => row![check.name, shape, "", "", "", atol, rtol, Red.paint(&**e)],
+            });
+        }
+        table.printstd();
+        if let Some(first) = failures.first() {
+            println!("First failing node: {}", Red.bold().paint(&*first.name));
+        } else {
+            println!(
+                "{}",
+                Green.paint(format!("{} reference value(s) within tolerance", checks.len()))
+            );
+        }
+    }
+
+    if failures.len() > 0 {
+        bail!("{} of {} reference value(s) out of tolerance", failures.len(), checks.len())
+    }
+    Ok(())
+}
+
+/// The comparison of a computed value to its reference.
+pub struct Check {
+    name: String,
+    shape: Vec<usize>,
+    atol: f64,
+    rtol: f64,
+    result: Result<Discrepancy, String>,
+}
+
+impl Check {
+    pub fn new(name: String, got: &Tensor, expected: &Tensor, atol: f64, rtol: f64) -> Check {
+        let result = got.compare(expected, atol, rtol).map_err(|e| e.to_string());
+        Check { name, shape: got.shape().to_vec(), atol, rtol, result }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.result.as_ref().map(|d| d.is_ok()).unwrap_or(false)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = json!({
+            "name": self.name,
+            "shape": self.shape,
+            "atol": self.atol,
+            "rtol": self.rtol,
+            "ok": self.is_ok(),
+        });
+        match &self.result {
+            Ok(d) => {
+                json["max_abs"] = d.max_abs.into();
+                json["max_rel"] = d.max_rel.into();
+                json["max_ulp"] = d.max_ulp.into();
+                json["failures"] = d.failures.into();
+                json["first_failure"] = d.first_failure.into();
+            }
+            Err(e) => json["error"] = e.clone().into(),
+        }
+        json
+    }
+}
@@ -1,21 +1,49 @@
 use crate::errors::*;
 use crate::{Parameters, SomeModel};
+use serde_json::json;
 use tract_core::ops::prelude::*;
 use tract_core::SimplePlan;
 
-pub fn handle(params: Parameters) -> CliResult<()> {
+pub fn handle(params: Parameters, save_outputs: Option<&str>) -> CliResult<()> {
 
-    let outputs = match &params.tract_model {
-        SomeModel::Inference(ref m) => run_regular_t(m, &params)?,
-        SomeModel::Typed(ref m) => run_regular_t(m, &params)?,
-        SomeModel::Normalized(ref m) => run_regular_t(m, &params)?,
-        SomeModel::Pulsed(_, m) => run_pulse_t(m, &params)?,
+    let (outputs, names) = match &params.tract_model {
+        SomeModel::Inference(ref m) => (run_regular_t(m, &params)?, output_names(m)?),
+        SomeModel::Typed(ref m) => (run_regular_t(m, &params)?, output_names(m)?),
+        SomeModel::Normalized(ref m) => (run_regular_t(m, &params)?, output_names(m)?),
+        SomeModel::Pulsed(_, m) => (run_pulse_t(m, &params)?, output_names(m)?),
     };
 
-    for (ix, output) in outputs.iter().enumerate() {
-        println!("output #{}\n{}\n", ix, output.dump(true)?);
+    let checked = check_assertions(&params, &outputs);
+
+    if params.json {
+        let outputs = names
+            .iter()
+            .zip(outputs.iter())
+            .map(|(name, output)| {
+                let mut json = crate::json::tensor(output)?;
+                json["name"] = name.clone().into();
+                Ok(json)
+            })
+            .collect::<CliResult<Vec<_>>>()?;
+        let error = checked.as_ref().err().map(|e| e.to_string());
+        crate::json::print(&json!({
+            "outputs": outputs,
+            "assertions": { "ok": checked.is_ok(), "error": error },
+        }))?;
+    } else {
+        for (ix, output) in outputs.iter().enumerate() {
+            println!("output #{}\n{}\n", ix, output.dump(true)?);
+        }
+    }
+
+    if let Some(path) = save_outputs {
+        save(path, &names, &outputs)?;
     }
 
+    checked
+}
+
+fn check_assertions(params: &Parameters, outputs: &[SharedTensor]) -> CliResult<()> {
     if let Some(asserts) = &params.assertions {
         if let Some(asserts) = &asserts.assert_outputs {
             crate::utils::check_outputs(&*outputs, &asserts)?;
@@ -26,7 +54,39 @@ pub fn handle(params: Parameters) -> CliResult<()> {
             crate::utils::check_inferred(&*outputs, &*facts)?;
         }
     }
+    Ok(())
+}
+
+/// Output names, from their nodes, suffixed by the slot for nodes with
+/// several outputs.
+pub fn output_names<TI: TensorInfo>(model: &Model<TI>) -> CliResult<Vec<String>> {
+    Ok(model
+        .outputs()?
+        .iter()
+        .map(|o| {
+            let node = model.node(o.node);
+            if node.outputs.len() == 1 {
+                node.name.clone()
+            } else {
+                format!("{}:{}", node.name, o.slot)
+            }
+        })
+        .collect())
+}
 
+/// Saves tensors to a .npy file if there is one, or as named arrays of a .npz.
+pub fn save(path: &str, names: &[String], outputs: &[SharedTensor]) -> CliResult<()> {
+    let mut file = std::fs::File::create(path)?;
+    if path.ends_with(".npy") {
+        if outputs.len() != 1 {
+            bail!("Can only save a single output to {}, use .npz", path)
+        }
+        tract_core::npy::write_npy(&mut file, &outputs[0])?;
+    } else {
+        let named: Vec<(String, &Tensor)> =
+            names.iter().cloned().zip(outputs.iter().map(|t| t.as_tensor())).collect();
+        tract_core::npy::write_npz(file, &named)?;
+    }
     Ok(())
 }
 
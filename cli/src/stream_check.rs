@@ -1,6 +1,7 @@
 use itertools::Itertools;
 use ndarray::ArrayD;
 use ndarray::Axis;
+use serde_json::json;
 
 use tract_core::model::{OutletId, TensorInfo};
 use tract_core::plan::{SimplePlan, SimpleState};
@@ -101,27 +102,37 @@ pub fn handle(params: Parameters, options: display_graph::DisplayOptions) -> Cli
                 let valid_fixed_result =
                     fixed_result.slice_axis(Axis(output_axis), (f_o..f_o + count).into());
                 if valid_pulse_result != valid_fixed_result {
-                    display_graph.render_node(pulsed.node(pulsed_node))?;
-                    println!("pulse: {} ({}..{})", i, i * output_pulse, (i + 1) * output_pulse);
-                    println!(
-                        "expected: {}",
-                        valid_fixed_result
-                            .axis_iter(Axis(output_axis))
-                            .map(|s| *s.iter().next().unwrap())
-                            .join(" ")
-                    );
-                    println!(
-                        "got: {}",
-                        valid_pulse_result
-                            .axis_iter(Axis(output_axis))
-                            .map(|s| *s.iter().next().unwrap())
-                            .join(" ")
-                    );
+                    let expected: Vec<f32> = valid_fixed_result
+                        .axis_iter(Axis(output_axis))
+                        .map(|s| *s.iter().next().unwrap())
+                        .collect();
+                    let got: Vec<f32> = valid_pulse_result
+                        .axis_iter(Axis(output_axis))
+                        .map(|s| *s.iter().next().unwrap())
+                        .collect();
+                    if params.json {
+                        crate::json::print(&json!({
+                            "ok": false,
+                            "node": crate::json::node(&pulsed, pulsed.node(pulsed_node))?,
+                            "output": pulsed_outlet.slot,
+                            "pulse": i,
+                            "expected": expected,
+                            "got": got,
+                        }))?;
+                    } else {
+                        display_graph.render_node(pulsed.node(pulsed_node))?;
+                        println!("pulse: {} ({}..{})", i, i * output_pulse, (i + 1) * output_pulse);
+                        println!("expected: {}", expected.iter().join(" "));
+                        println!("got: {}", got.iter().join(" "));
+                    }
                     bail!("Error checking pulse mode")
                 }
             }
         }
     }
 
+    if params.json {
+        crate::json::print(&json!({ "ok": true }))?;
+    }
     Ok(())
 }
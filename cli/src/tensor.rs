@@ -2,9 +2,106 @@ use std::fs;
 use std::io::Read;
 
 use crate::CliResult;
+use tract_core::model::{Model, TensorInfo};
 use tract_core::ops::prelude::*;
 
+/// How to fill a tensor generated from a size, as in `3x4xf32:normal(0,1)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Real values in [low, high).
+    Uniform(f64, f64),
+    /// Real values of the given mean and standard deviation.
+    Normal(f64, f64),
+    /// Integer values in [low, high), like token ids.
+    Int(i64, i64),
+    /// The same value everywhere.
+    Const(f64),
+}
+
+impl Distribution {
+    /// Parses `uniform(low,high)`, `normal(mean,std)`, `int(low,high)` or
+    /// `const(value)`.
+    fn parse(spec: &str) -> CliResult<Distribution> {
+        let (name, args) = match (spec.find('('), spec.ends_with(')')) {
+            (Some(open), true) => (&spec[..open], &spec[open + 1..spec.len() - 1]),
+            _ => bail!("Distributions are formatted as name(arguments), not {}", spec),
+        };
+        let args = args.split(',').map(|a| a.trim()).collect::<Vec<_>>();
+        let distribution = match (name, &*args) {
+            ("uniform", [low, high]) => Distribution::Uniform(low.parse()?, high.parse()?),
+            ("normal", [mean, std]) => Distribution::Normal(mean.parse()?, std.parse()?),
+            ("int", [low, high]) => Distribution::Int(low.parse()?, high.parse()?),
+            ("const", [value]) => Distribution::Const(value.parse()?),
+            _ => bail!(
+                "Unknown distribution {}, use uniform(low,high), normal(mean,std), \
+                 int(low,high) or const(value)",
+                spec
+            ),
+        };
+        match distribution {
+            Distribution::Uniform(low, high) if low >= high => {
+                bail!("Empty range in {}", spec)
+            }
+            Distribution::Int(low, high) if low >= high => bail!("Empty range in {}", spec),
+            Distribution::Normal(_, std) if std < 0.0 => {
+                bail!("Negative standard deviation in {}", spec)
+            }
+            _ => Ok(distribution),
+        }
+    }
+
+    /// Draws a tensor of the given shape and type.
+    pub fn sample(&self, shape: &[usize], datum_type: DatumType) -> CliResult<Tensor> {
+        use rand::distributions::{Distribution as _, Normal, Uniform};
+        let mut rng = rand::thread_rng();
+        let len = shape.iter().product();
+        let values: Vec<f64> = match *self {
+            Distribution::Uniform(low, high) => {
+                Uniform::new(low, high).sample_iter(&mut rng).take(len).collect()
+            }
+            Distribution::Normal(mean, std) => {
+                Normal::new(mean, std).sample_iter(&mut rng).take(len).collect()
+            }
+            Distribution::Int(low, high) => Uniform::new(low, high)
+                .sample_iter(&mut rng)
+                .take(len)
+                .map(|v: i64| v as f64)
+                .collect(),
+            Distribution::Const(value) => vec![value; len],
+        };
+        tensor_from_f64(shape, &values, datum_type)
+    }
+}
+
+/// Converts values to a tensor of the given type, with `as` semantics.
+fn tensor_from_f64(shape: &[usize], values: &[f64], datum_type: DatumType) -> CliResult<Tensor> {
+    macro_rules! make {
+        ($t:ty) => {
+            ndarray::ArrayD::from_shape_vec(
+                shape.to_vec(),
+                values.iter().map(|&v| v as $t).collect::<Vec<$t>>(),
+            )?
+            .into()
+        };
+    }
+    Ok(match datum_type {
+        DatumType::F64 => make!(f64),
+        DatumType::F32 => make!(f32),
+        DatumType::I64 => make!(i64),
+        DatumType::I32 => make!(i32),
+        DatumType::I8 => make!(i8),
+        DatumType::U8 => make!(u8),
+        _ => bail!("Can not generate {:?} values", datum_type),
+    })
+}
+
+/// Parses a size like `3x4xf32`, with `S` for a streaming dimension, and an
+/// optional distribution to draw the values from, as in `3x4xf32:int(0,10)`.
 pub fn for_size(size: &str) -> CliResult<TensorFact> {
+    let (size, distribution) = match size.find(':') {
+        Some(colon) => (&size[..colon], Some(Distribution::parse(&size[colon + 1..])?)),
+        None => (size, None),
+    };
     let splits = size.split("x").collect::<Vec<_>>();
 
     if splits.len() < 1 {
@@ -28,13 +125,31 @@ pub fn for_size(size: &str) -> CliResult<TensorFact> {
     let datum_type = match datum_type.to_lowercase().as_str() {
         "f64" => DatumType::F64,
         "f32" => DatumType::F32,
+        "i64" => DatumType::I64,
         "i32" => DatumType::I32,
         "i8" => DatumType::I8,
         "u8" => DatumType::U8,
-        _ => bail!("Type of the input should be f64, f32, i32, i8 or u8."),
+        _ => bail!("Type of the input should be f64, f32, i64, i32, i8 or u8."),
     };
 
-    Ok(TensorFact::dt_shape(datum_type, shape))
+    match distribution {
+        None => Ok(TensorFact::dt_shape(datum_type, shape)),
+        Some(distribution) => {
+            let shape = concrete_shape(&shape, size)?;
+            Ok(distribution.sample(&shape, datum_type)?.into())
+        }
+    }
+}
+
+/// The dimensions of a size, which must not be streaming.
+fn concrete_shape(shape: &[TDim], size: &str) -> CliResult<Vec<usize>> {
+    shape
+        .iter()
+        .map(|d| match d.to_integer() {
+            Ok(d) => Ok(d as usize),
+            Err(_) => bail!("Generating values needs a fixed size, not {}", size),
+        })
+        .collect()
 }
 
 fn tensor_for_text_data(filename: &str) -> CliResult<Tensor> {
@@ -74,6 +189,7 @@ fn tensor_for_text_data(filename: &str) -> CliResult<Tensor> {
     let tensor = match proto.datum_type.concretize().unwrap() {
         DatumType::F64 => for_type!(f64).into(),
         DatumType::F32 => for_type!(f32).into(),
+        DatumType::I64 => for_type!(i64).into(),
         DatumType::I32 => for_type!(i32).into(),
         DatumType::I8 => for_type!(i8).into(),
         DatumType::U8 => for_type!(u8).into(),
@@ -92,6 +208,9 @@ fn for_data(filename: &str) -> CliResult<TensorFact> {
         #[cfg(not(feature="onnx"))] {
             panic!("Loading tensor from protobuf requires onnx features");
         }
+    } else if filename.ends_with(".npy") {
+        let mut file = fs::File::open(filename)?;
+        tract_core::npy::read_npy(&mut file)?
     } else {
         tensor_for_text_data(filename)?
     };
@@ -99,18 +218,137 @@ fn for_data(filename: &str) -> CliResult<TensorFact> {
     Ok(tensor.into())
 }
 
+/// Loads the named arrays of a .npz file.
+pub fn for_npz(filename: &str) -> CliResult<Vec<(String, TensorFact)>> {
+    let file = fs::File::open(filename)?;
+    let tensors = tract_core::npy::read_npz(file)?;
+    Ok(tensors.into_iter().map(|(name, t)| (name, t.into())).collect())
+}
+
+/// Parses an input value: `@file` for a data file, `@file:1x224x224x3xu8`
+/// for an image or raw data of that size, or a size to generate values for.
 pub fn for_string(value: &str) -> CliResult<TensorFact> {
     if value.starts_with("@") {
-        for_data(&value[1..])
+        let path = &value[1..];
+        // paths may contain colons too, only split where a valid size follows
+        for (colon, _) in path.match_indices(':') {
+            if for_size(&path[colon + 1..]).is_ok() {
+                return for_file_and_size(&path[..colon], &path[colon + 1..]);
+            }
+        }
+        for_data(path)
     } else {
         for_size(value)
     }
 }
 
+/// Loads an image or a raw binary blob, shaped as `size`.
+fn for_file_and_size(filename: &str, size: &str) -> CliResult<TensorFact> {
+    let fact = for_size(size)?;
+    let datum_type = fact.datum_type.concretize().unwrap();
+    let shape = concrete_shape(&fact.shape.concretize().unwrap(), size)?;
+    let extension = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+    let tensor = match &*extension {
+        "png" | "jpg" | "jpeg" => for_image(filename, &shape, datum_type)?,
+        _ => for_blob(filename, &shape, datum_type)?,
+    };
+    Ok(tensor.into())
+}
+
+/// Decodes an image, resized to the height and width of `shape`, and laid
+/// out as NHWC if its last axis has 1 or 3 channels, as NCHW otherwise. The
+/// values are the pixels, from 0 to 255, repeated along the batch axis.
+fn for_image(filename: &str, shape: &[usize], datum_type: DatumType) -> CliResult<Tensor> {
+    if shape.len() != 4 {
+        bail!("Images are loaded as NCHW or NHWC tensors, not {:?}", shape)
+    }
+    let (nhwc, channels, height, width) = if shape[3] == 1 || shape[3] == 3 {
+        (true, shape[3], shape[1], shape[2])
+    } else if shape[1] == 1 || shape[1] == 3 {
+        (false, shape[1], shape[2], shape[3])
+    } else {
+        bail!("Images have 1 or 3 channels, in NCHW or NHWC layout, not {:?}", shape)
+    };
+    let image =
+        image::open(filename).map_err(|e| format!("Decoding image {}: {}", filename, e))?;
+    let image = image.resize_exact(width as u32, height as u32, image::FilterType::Triangle);
+    let pixels = if channels == 3 { image.to_rgb().into_raw() } else { image.to_luma().into_raw() };
+    let hwc = ndarray::Array3::from_shape_vec((height, width, channels), pixels)?;
+    let image = if nhwc { hwc } else { hwc.permuted_axes([2, 0, 1]) };
+    let batch = image
+        .insert_axis(ndarray::Axis(0))
+        .broadcast(shape)
+        .ok_or("Images are loaded along the batch axis")?
+        .to_owned();
+    let values: Vec<f64> = batch.iter().map(|&p| p as f64).collect();
+    tensor_from_f64(shape, &values, datum_type)
+}
+
+/// Reads values of the given type, little-endian and packed, from a file.
+fn for_blob(filename: &str, shape: &[usize], datum_type: DatumType) -> CliResult<Tensor> {
+    let bytes = fs::read(filename)?;
+    let len = shape.iter().product::<usize>();
+    if bytes.len() != len * datum_type.size_of() {
+        bail!(
+            "{} has {} bytes, {} values of type {:?} need {}",
+            filename,
+            bytes.len(),
+            len,
+            datum_type,
+            len * datum_type.size_of()
+        )
+    }
+    macro_rules! from_le_bytes {
+        ($t:ty) => {{
+            let values = bytes
+                .chunks(std::mem::size_of::<$t>())
+                .map(|chunk| {
+                    let mut le = [0u8; std::mem::size_of::<$t>()];
+                    le.copy_from_slice(chunk);
+                    <$t>::from_le_bytes(le)
+                })
+                .collect::<Vec<$t>>();
+            ndarray::ArrayD::from_shape_vec(shape.to_vec(), values)?.into()
+        }};
+    }
+    Ok(match datum_type {
+        DatumType::F64 => from_le_bytes!(f64),
+        DatumType::F32 => from_le_bytes!(f32),
+        DatumType::I64 => from_le_bytes!(i64),
+        DatumType::I32 => from_le_bytes!(i32),
+        DatumType::I8 => from_le_bytes!(i8),
+        DatumType::U8 => from_le_bytes!(u8),
+        _ => bail!("Can not read {:?} values", datum_type),
+    })
+}
+
 pub fn make_inputs(values: &[TensorFact]) -> CliResult<TVec<Tensor>> {
     values.iter().map(|v| tensor_for_fact(v, None)).collect()
 }
 
+/// The inputs to run a model on: the values given on the command line, which
+/// must fit the input facts, and random values for the others.
+pub fn make_inputs_for_model<TI: TensorInfo>(
+    model: &Model<TI>,
+    given: Option<&Vec<Option<SharedTensor>>>,
+) -> CliResult<TVec<Tensor>> {
+    let mut inputs = tvec!();
+    for (ix, &input) in model.inputs()?.iter().enumerate() {
+        let fact = model.fact(input)?.to_tensor_fact();
+        let given = given.and_then(|v| v.get(ix)).and_then(|t| t.as_ref());
+        match given {
+            Some(value) => {
+                if let Err(e) = TensorFact::from(value.clone()).unify(&fact) {
+                    bail!("Input {} does not fit the model ({:?}): {}", ix, fact, e)
+                }
+                inputs.push(value.as_tensor().clone())
+            }
+            None => inputs.push(tensor_for_fact(&fact, None)?),
+        }
+    }
+    Ok(inputs)
+}
+
 pub fn tensor_for_fact(fact: &TensorFact, streaming_dim: Option<usize>) -> CliResult<Tensor> {
     if let Some(value) = fact.concretize() {
         Ok(value.as_tensor().to_owned())
@@ -118,21 +356,19 @@ pub fn tensor_for_fact(fact: &TensorFact, streaming_dim: Option<usize>) -> CliRe
         if fact.stream_info()?.is_some() && streaming_dim.is_none() {
             Err("random tensor requires a streaming dim")?
         }
-        Ok(random(
-            fact.shape
-                .concretize()
-                .unwrap()
-                .iter()
-                .map(|d| {
-                    d.to_integer()
-                        .ok()
-                        .map(|d| d as usize)
-                        .or(streaming_dim)
-                        .unwrap()
-                })
-                .collect(),
-            fact.datum_type.concretize().unwrap(),
-        ))
+        let shape = fact.shape.concretize().ok_or_else(|| format!("Partial shape in {:?}", fact))?;
+        let shape = shape
+            .iter()
+            .map(|d| {
+                d.to_integer()
+                    .ok()
+                    .map(|d| d as usize)
+                    .or(streaming_dim)
+                    .ok_or_else(|| format!("Can not evaluate {:?} in {:?}", d, fact))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        let dt = fact.datum_type.concretize().ok_or_else(|| format!("Unknown type in {:?}", fact))?;
+        Ok(random(shape, dt))
     }
 }
 
@@ -157,6 +393,7 @@ pub fn random(sizes: Vec<usize>, datum_type: DatumType) -> Tensor {
     match datum_type {
         DatumType::F64 => make::<f64>(sizes),
         DatumType::F32 => make::<f32>(sizes),
+        DatumType::I64 => make::<i64>(sizes),
         DatumType::I32 => make::<i32>(sizes),
         DatumType::I8 => make::<i8>(sizes),
         DatumType::U8 => make::<u8>(sizes),
@@ -1,8 +1,10 @@
 use std::borrow::BorrowMut;
 use std::collections::BTreeSet;
+use std::fmt;
 
 use crate::model::*;
 use crate::ops::prelude::*;
+use crate::TractErrorKind;
 
 pub mod types;
 
@@ -13,35 +15,85 @@ pub mod helpers;
 #[macro_use]
 pub mod rules;
 
+/// Details about the node on which the analysis failed.
+#[derive(Clone, Debug)]
+pub struct AnalyseFailure {
+    pub node: usize,
+    pub node_name: String,
+    pub op: String,
+    /// The rule that failed, if the failure comes from the op rules.
+    pub rule: Option<rules::RuleFailure>,
+    /// The facts around the node when it failed.
+    pub inputs: Vec<TensorFact>,
+    pub outputs: Vec<TensorFact>,
+    pub message: String,
+}
+
+impl fmt::Display for AnalyseFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "Analysing node #{} {} ({}), {}",
+            self.node, self.node_name, self.op, self.message
+        )?;
+        if let Some(rule) = &self.rule {
+            write!(fmt, "\n  {} rule: {}", rule.kind, rule.rule)?;
+            for (path, value) in &rule.paths {
+                write!(fmt, "\n    {} is {}", path, value)?;
+            }
+            for (tensor, fact) in &rule.facts {
+                write!(fmt, "\n    {} is {:?}", tensor, fact)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A graph analyser, along with its current state.
 pub struct Analyser<M: BorrowMut<InferenceModel>> {
     model: M,
+    diagnostic: bool,
 }
 
 impl<M: BorrowMut<InferenceModel>> Analyser<M> {
     pub fn new(model: M) -> TractResult<Analyser<M>> {
-        Ok(Analyser { model })
+        Ok(Analyser { model, diagnostic: false })
+    }
+
+    /// Reports failures as `TractErrorKind::AnalyseFailure`, after running
+    /// the failing node rules again with a solver in diagnostic mode.
+    pub fn with_diagnostic(mut self) -> Analyser<M> {
+        self.diagnostic = true;
+        self
     }
 
     /// Runs the entire analysis at once.
     pub fn analyse(&mut self) -> TractResult<()> {
-        let mut nodes_to_visit: BTreeSet<usize> =
-            self.model.borrow().eval_order()?.iter().cloned().collect();
+        let nodes = self.model.borrow().eval_order()?;
+        self.analyse_nodes(nodes)
+    }
+
+    /// Runs the analysis from `nodes`, only visiting other nodes when the
+    /// facts on their edges change.
+    pub fn analyse_nodes(&mut self, nodes: impl IntoIterator<Item = usize>) -> TractResult<()> {
+        let mut nodes_to_visit: BTreeSet<usize> = nodes.into_iter().collect();
         loop {
             trace!("Remaining nodes {}", nodes_to_visit.len());
             let node = match nodes_to_visit.iter().next() {
                 None => return Ok(()),
                 Some(n) => *n,
             };
-            let changed_edges = self.analyse_one(node).map_err(|e| {
-                format!(
-                    "Analysing node #{} {} ({}), {}",
-                    node,
-                    self.model.borrow().nodes()[node].name,
-                    self.model.borrow().nodes()[node].op.name(),
-                    e
-                )
-            })?;
+            let changed_edges = match self.analyse_one(node) {
+                Ok(changed_edges) => changed_edges,
+                Err(e) => {
+                    let failure = self.failure(node, e.to_string())?;
+                    if self.diagnostic {
+                        bail!(TractErrorKind::AnalyseFailure(Box::new(failure)))
+                    } else {
+                        bail!(failure.to_string())
+                    }
+                }
+            };
             for (edge, _fact) in changed_edges {
                 trace!("Changed edge: {:?}", edge);
                 for dst in
@@ -61,6 +113,26 @@ impl<M: BorrowMut<InferenceModel>> Analyser<M> {
         }
     }
 
+    fn failure(&self, node: usize, message: String) -> TractResult<AnalyseFailure> {
+        let model = self.model.borrow();
+        let node = &model.nodes()[node];
+        let (inputs, outputs) = model.facts(node.id)?;
+        let rule = if self.diagnostic {
+            node.op.diagnose_facts(inputs.clone(), outputs.clone())
+        } else {
+            None
+        };
+        Ok(AnalyseFailure {
+            node: node.id,
+            node_name: node.name.clone(),
+            op: node.op.name().to_string(),
+            rule,
+            inputs: inputs.into_iter().cloned().collect(),
+            outputs: outputs.into_iter().cloned().collect(),
+            message,
+        })
+    }
+
     /// Tries to run a single step of the analysis, and returns whether
     /// there was any additional information gained during the step.
     pub fn analyse_one(&mut self, node: usize) -> TractResult<Vec<(OutletId, TensorFact)>> {
@@ -31,7 +31,7 @@ mod proxies;
 mod solver;
 
 pub use self::proxies::*;
-pub use self::solver::Solver;
+pub use self::solver::{RuleFailure, Solver};
 
 pub type InferenceResult = TractResult<()>;
 
@@ -58,4 +58,19 @@ impl<O: InferenceRulesOp> crate::ops::InferenceOp for O {
         self.rules(&mut solver, &inputs_proxy, &outputs_proxy)?;
         solver.infer_facts((inputs, outputs))
     }
+
+    fn diagnose_facts(
+        &self,
+        inputs: TVec<&TensorFact>,
+        outputs: TVec<&TensorFact>,
+    ) -> Option<RuleFailure> {
+        let inputs_proxy: TVec<TensorProxy> =
+            (0..inputs.len()).map(|ix| TensorProxy::new(tvec!(0, ix as isize).into())).collect();
+        let outputs_proxy: TVec<TensorProxy> =
+            (0..outputs.len()).map(|ix| TensorProxy::new(tvec!(1, ix as isize).into())).collect();
+
+        let mut solver = Solver::diagnostic();
+        self.rules(&mut solver, &inputs_proxy, &outputs_proxy).ok()?;
+        solver.solve((inputs, outputs)).err()
+    }
 }
@@ -30,6 +30,18 @@ impl ::std::ops::Deref for Path {
     }
 }
 
+impl Path {
+    /// The path of the tensor this path is about, if it designates a tensor
+    /// property.
+    pub fn tensor(&self) -> Option<Path> {
+        if self.0.len() >= 2 && self.0[1] >= 0 {
+            Some(Path(self.0[..2].into()))
+        } else {
+            None
+        }
+    }
+}
+
 impl fmt::Debug for Path {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         debug_path(self, formatter)
@@ -5,7 +5,7 @@ use num_traits::Zero;
 
 use crate::ops::prelude::*;
 
-use self::super::expr::{Exp, IntoExp, Output, TExp};
+use self::super::expr::{Exp, IntoExp, Output, TExp, Wrapped};
 use self::super::path::{get_path, set_path, Path};
 
 /// A structure that holds the current sets of TensorFacts.
@@ -45,6 +45,74 @@ pub trait Rule<'rules>: fmt::Debug {
 
     /// Returns the paths that the rule depends on.
     fn get_paths(&self) -> Vec<&Path>;
+
+    /// The kind of rule, for diagnostics.
+    ///
+    /// Defaults to the name of the implementing type, without its path and
+    /// generic parameters.
+    fn kind(&self) -> &'static str {
+        let name = ::std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+/// What made a rule fail while solving.
+#[derive(Clone, Debug)]
+pub struct RuleFailure {
+    /// Kind of rule: Equals, EqualsZero, With, Given or GivenAll for the
+    /// built-in ones.
+    pub kind: &'static str,
+    /// The rule itself.
+    pub rule: String,
+    /// The paths the rule depends on, and their value when it failed.
+    pub paths: Vec<(String, String)>,
+    /// The tensors these paths refer to, and their facts when it failed.
+    pub facts: Vec<(String, TensorFact)>,
+    /// The rules applied before the failure, in order. Only recorded in
+    /// diagnostic mode.
+    pub applied: Vec<String>,
+    /// The error raised by the rule.
+    pub message: String,
+}
+
+impl RuleFailure {
+    fn new<'r>(
+        rule: &(Rule<'r> + 'r),
+        context: &Context,
+        applied: Vec<String>,
+        message: String,
+    ) -> RuleFailure {
+        let mut facts: Vec<(String, TensorFact)> = vec![];
+        let mut paths = vec![];
+        for path in rule.get_paths() {
+            let value = match get_path(context, path) {
+                Ok(Wrapped::Int(v)) => format!("{:?}", v),
+                Ok(Wrapped::Type(v)) => format!("{:?}", v),
+                Ok(Wrapped::Shape(v)) => format!("{:?}", v),
+                Ok(Wrapped::SharedTensor(v)) => format!("{:?}", v),
+                Ok(Wrapped::Dim(v)) => format!("{:?}", v),
+                Err(e) => format!("<{}>", e),
+            };
+            paths.push((format!("{:?}", path), value));
+            if let Some(tensor) = path.tensor() {
+                let name = format!("{:?}", tensor);
+                if facts.iter().all(|f| f.0 != name) {
+                    let set = if tensor[0] == 0 { &context.inputs } else { &context.outputs };
+                    if let Some(fact) = set.get(tensor[1] as usize) {
+                        facts.push((name, fact.clone()));
+                    }
+                }
+            }
+        }
+        RuleFailure { kind: rule.kind(), rule: format!("{:?}", rule), paths, facts, applied, message }
+    }
+}
+
+impl fmt::Display for RuleFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Applying rule {}: {}", self.rule, self.message)
+    }
 }
 
 /// The `equals` rule.
@@ -84,6 +152,10 @@ impl<'rules, T: Output + Fact> Rule<'rules> for EqualsRule<T> {
     fn get_paths(&self) -> Vec<&Path> {
         self.items.iter().flat_map(|e| e.get_paths()).collect()
     }
+
+    fn kind(&self) -> &'static str {
+        "Equals"
+    }
 }
 
 impl<'rules, T: Output + Fact> fmt::Debug for EqualsRule<T> {
@@ -120,6 +192,10 @@ where
     fn get_paths(&self) -> Vec<&Path> {
         self.0.get_paths()
     }
+
+    fn kind(&self) -> &'static str {
+        "EqualsZero"
+    }
 }
 
 impl<F> fmt::Debug for EqualsZeroRule<F>
@@ -172,6 +248,10 @@ impl<'rules, T: Output + Fact> Rule<'rules> for WithRule<'rules, T> {
     fn get_paths(&self) -> Vec<&Path> {
         self.item.get_paths()
     }
+
+    fn kind(&self) -> &'static str {
+        "With"
+    }
 }
 
 impl<'s, T: Output + Fact> fmt::Debug for WithRule<'s, T> {
@@ -235,6 +315,10 @@ impl<'rules, T: Output + Fact> Rule<'rules> for GivenRule<'rules, T> {
     fn get_paths(&self) -> Vec<&Path> {
         self.item.get_paths()
     }
+
+    fn kind(&self) -> &'static str {
+        "Given"
+    }
 }
 
 impl<'s, T: Output + Fact> fmt::Debug for GivenRule<'s, T> {
@@ -296,6 +380,10 @@ impl<'rules, T: Output + Fact> Rule<'rules> for GivenAllRule<'rules, T> {
     fn get_paths(&self) -> Vec<&Path> {
         self.items.iter().flat_map(|it| it.get_paths()).collect()
     }
+
+    fn kind(&self) -> &'static str {
+        "GivenAll"
+    }
 }
 
 impl<'s, T: Output + Fact> fmt::Debug for GivenAllRule<'s, T> {
@@ -309,9 +397,17 @@ impl<'s, T: Output + Fact> fmt::Debug for GivenAllRule<'s, T> {
 pub struct Solver<'rules> {
     // The rules used by the solver.
     pub rules: Vec<Box<Rule<'rules> + 'rules>>,
+    // Whether to record the applied rules for diagnostics.
+    diagnostic: bool,
 }
 
 impl<'rules> Solver<'rules> {
+    /// A solver in diagnostic mode, which keeps track of the rules it
+    /// applies in order to report them on failure.
+    pub fn diagnostic() -> Solver<'rules> {
+        Solver { diagnostic: true, ..Solver::default() }
+    }
+
     /// Consumes the solver and returns the rules that it uses.
     pub fn take_rules(self) -> Vec<Box<Rule<'rules> + 'rules>> {
         self.rules
@@ -327,6 +423,15 @@ impl<'rules> Solver<'rules> {
         self,
         facts: (TVec<&TensorFact>, TVec<&TensorFact>),
     ) -> TractResult<(TVec<TensorFact>, TVec<TensorFact>)> {
+        self.solve(facts).map_err(|failure| failure.to_string().into())
+    }
+
+    /// Runs the solver on a set of TensorFacts, reporting in details the
+    /// rule that failed, if any.
+    pub fn solve(
+        self,
+        facts: (TVec<&TensorFact>, TVec<&TensorFact>),
+    ) -> Result<(TVec<TensorFact>, TVec<TensorFact>), RuleFailure> {
         let mut context = Context::new(
             facts.0.into_iter().cloned().collect(),
             facts.1.into_iter().cloned().collect(),
@@ -335,6 +440,7 @@ impl<'rules> Solver<'rules> {
         // Apply the rules until reaching a fixed point.
         let mut changed = true;
         let mut added_rules = vec![];
+        let mut applied = vec![];
         let mut rules: Vec<_> = self.rules.into_iter().map(|r| (false, r)).collect();
 
         while changed {
@@ -347,10 +453,16 @@ impl<'rules> Solver<'rules> {
                 }
 
                 trace!("  Applying rule {:?}", rule);
-                let (step_used, mut step_added) = rule
-                    .apply(&mut context)
-                    .map_err(|e| format!("Applying rule {:?}: {:}", rule, e))?;
+                let (step_used, mut step_added) = match rule.apply(&mut context) {
+                    Ok(it) => it,
+                    Err(e) => {
+                        return Err(RuleFailure::new(&**rule, &context, applied, e.to_string()))
+                    }
+                };
                 *used |= step_used;
+                if step_used && self.diagnostic {
+                    applied.push(format!("{:?}", rule));
+                }
 
                 // There is a change if the rule was used, or if it added new rules.
                 changed |= step_used;
@@ -537,6 +649,10 @@ macro_rules! given_tuple {
                 $(v.extend(self.$id.get_paths());)*;
                 v
             }
+
+            fn kind(&self) -> &'static str {
+                "Given"
+            }
         }
 
         #[allow(non_camel_case_types)]
@@ -783,4 +899,44 @@ mod tests {
 
         assert_eq!(facts, expected);
     }
+
+    #[test]
+    fn solver_diagnostic() {
+        let (_, inputs, outputs) = bootstrap();
+        let mut solver = Solver::diagnostic();
+        solver.equals(&inputs[0].datum_type, &outputs[0].datum_type).unwrap();
+        solver.equals(&inputs[0].shape[1], &outputs[0].shape[1]).unwrap();
+
+        let input = TensorFact::dt_shape(DatumType::F32, shapefact![1, 3]);
+        let output = TensorFact::shape(shapefact![_, 2]);
+        let failure = solver.solve((tvec![&input], tvec![&output])).unwrap_err();
+        assert_eq!(failure.kind, "Equals");
+        assert_eq!(failure.applied.len(), 1);
+        let paths: Vec<&str> = failure.paths.iter().map(|p| &*p.0).collect();
+        assert_eq!(paths, vec!["inputs[0].shape[1]", "outputs[0].shape[1]"]);
+        let tensors: Vec<&str> = failure.facts.iter().map(|f| &*f.0).collect();
+        assert_eq!(tensors, vec!["inputs[0]", "outputs[0]"]);
+        assert_eq!(failure.facts[0].1, input);
+    }
+
+    #[derive(Debug)]
+    struct NoopRule<T>(T);
+
+    impl<'rules, T: fmt::Debug> Rule<'rules> for NoopRule<T> {
+        fn apply(
+            &self,
+            _context: &mut Context,
+        ) -> TractResult<(bool, Vec<Box<Rule<'rules> + 'rules>>)> {
+            Ok((false, vec![]))
+        }
+
+        fn get_paths(&self) -> Vec<&Path> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn rule_kind_defaults_to_type_name() {
+        assert_eq!(NoopRule(0usize).kind(), "NoopRule");
+    }
 }
@@ -0,0 +1,396 @@
+//! Batching of independent requests into a single plan run.
+//!
+//! The batch axis is the streaming dimension `S`: once the model inputs have
+//! `S` on their batch axis, the analyser tells which outputs carry it.
+//! Requests are concatenated along the batch axis, the plan is run once, and
+//! outputs are sliced back per request. An output without a batch axis, like
+//! a sum over it, would mix the data of all requests: models having one run
+//! each request alone.
+
+use std::borrow::Borrow;
+
+use ndarray::*;
+
+use crate::model::{Model, TensorInfo};
+use crate::ops::array::{Concat, Slice};
+use crate::ops::prelude::*;
+use crate::plan::SimplePlan;
+
+/// How to reconcile requests that disagree on axes other than the batch one.
+#[derive(Debug, Clone)]
+pub enum Padding {
+    /// Requests must agree on all axes but the batch axis.
+    Forbid,
+    /// Pad shorter requests with this scalar value.
+    Value(Tensor),
+}
+
+impl Default for Padding {
+    fn default() -> Padding {
+        Padding::Forbid
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchPlan<TI: TensorInfo, M: Borrow<Model<TI>>> {
+    plan: SimplePlan<TI, M>,
+    input_axes: TVec<usize>,
+    input_dims: TVec<TVec<Option<usize>>>,
+    output_axes: TVec<Option<usize>>,
+    padding: TVec<Padding>,
+    mask: Option<(usize, usize)>,
+}
+
+impl BatchPlan<TensorFact, InferenceModel> {
+    /// Makes `batch_axis` the batch axis of every input and analyses the
+    /// model to find the batch axis of the outputs.
+    pub fn for_model(
+        mut model: InferenceModel,
+        batch_axis: usize,
+    ) -> TractResult<BatchPlan<TensorFact, InferenceModel>> {
+        for ix in 0..model.inputs()?.len() {
+            let fact = model.fact(model.inputs()?[ix])?;
+            let fact = crate::pulse::streaming_fact(fact, batch_axis)?;
+            model.set_input_fact(ix, fact)?;
+        }
+        model.analyse()?;
+        BatchPlan::new(SimplePlan::new(model)?)
+    }
+}
+
+impl<TI: TensorInfo, M: Borrow<Model<TI>>> BatchPlan<TI, M> {
+    /// Wraps a plan whose inputs have the streaming dimension on their batch
+    /// axis.
+    pub fn new(plan: SimplePlan<TI, M>) -> TractResult<BatchPlan<TI, M>> {
+        let model = plan.model();
+        let (input_axes, input_dims) = model
+            .inputs()?
+            .iter()
+            .map(|&i| {
+                let fact = model.fact(i)?.to_tensor_fact();
+                let axis = batch_axis(&fact)
+                    .ok_or_else(|| format!("Input {:?} has no batch axis", fact))?;
+                let dims = fact
+                    .shape
+                    .dims()
+                    .map(|d| d.concretize().and_then(|d| d.to_integer().ok()).map(|d| d as usize))
+                    .collect();
+                Ok((axis, dims))
+            })
+            .collect::<TractResult<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let output_axes = model
+            .outputs()?
+            .iter()
+            .map(|&o| {
+                let fact = model.fact(o)?.to_tensor_fact();
+                match batch_axis(&fact) {
+                    Some(axis) => Ok(Some(axis)),
+                    None if fact.shape.is_concrete() => Ok(None),
+                    None => bail!("Can not determine batch axis of output {:?}", fact),
+                }
+            })
+            .collect::<TractResult<_>>()?;
+        let padding = tvec!(Padding::Forbid; model.inputs()?.len());
+        Ok(BatchPlan { plan, input_axes, input_dims, output_axes, padding, mask: None })
+    }
+
+    /// Sets the padding policy for an input.
+    pub fn with_padding(mut self, input: usize, padding: Padding) -> TractResult<Self> {
+        if input >= self.padding.len() {
+            bail!("Model has no input {}", input)
+        }
+        self.padding[input] = padding;
+        Ok(self)
+    }
+
+    /// Feeds the `mask_input` with a mask of the padding of `padded_input`:
+    /// ones where request data is, zeros where padding was added. Requests
+    /// do not provide a value for the mask input.
+    pub fn with_mask(mut self, mask_input: usize, padded_input: usize) -> TractResult<Self> {
+        if mask_input >= self.padding.len() || padded_input >= self.padding.len() {
+            bail!("Model has no input {}", mask_input.max(padded_input))
+        }
+        if mask_input == padded_input {
+            bail!("Mask input can not be the padded input")
+        }
+        self.mask = Some((mask_input, padded_input));
+        Ok(self)
+    }
+
+    /// Batch axis of each output, or None for outputs without one.
+    pub fn output_axes(&self) -> &[Option<usize>] {
+        &self.output_axes
+    }
+
+    /// Whether requests are run as one batch: every output has a batch axis.
+    pub fn batches(&self) -> bool {
+        self.output_axes.iter().all(|a| a.is_some())
+    }
+
+    /// Runs all requests, at once if the model `batches`, else one by one.
+    ///
+    /// Outputs are split along the batch axis. Axes that have been padded
+    /// are returned padded.
+    pub fn run(&self, requests: Vec<TVec<Tensor>>) -> TractResult<Vec<TVec<SharedTensor>>> {
+        if self.batches() {
+            return self.run_batch(requests);
+        }
+        let mut results = Vec::with_capacity(requests.len());
+        for request in requests {
+            results.extend(self.run_batch(vec![request])?);
+        }
+        Ok(results)
+    }
+
+    fn run_batch(&self, requests: Vec<TVec<Tensor>>) -> TractResult<Vec<TVec<SharedTensor>>> {
+        if requests.len() == 0 {
+            return Ok(vec![]);
+        }
+        let mask_input = self.mask.map(|(m, _)| m);
+        let data_inputs: TVec<usize> =
+            (0..self.input_axes.len()).filter(|&ix| Some(ix) != mask_input).collect();
+        let mut columns: TVec<Vec<Tensor>> = tvec!(vec!(); self.input_axes.len());
+        for (rix, request) in requests.into_iter().enumerate() {
+            if request.len() != data_inputs.len() {
+                bail!(
+                    "Request {} has {} inputs, expected {}",
+                    rix,
+                    request.len(),
+                    data_inputs.len()
+                )
+            }
+            for (&ix, tensor) in data_inputs.iter().zip(request.into_iter()) {
+                columns[ix].push(tensor);
+            }
+        }
+        let batch_sizes: Vec<usize> = (0..columns[data_inputs[0]].len())
+            .map(|r| columns[data_inputs[0]][r].shape()[self.input_axes[data_inputs[0]]])
+            .collect();
+        let mut inputs: TVec<Option<Tensor>> = tvec!(None; self.input_axes.len());
+        for &ix in &data_inputs {
+            let axis = self.input_axes[ix];
+            let column = &columns[ix];
+            for (r, t) in column.iter().enumerate() {
+                if t.shape().len() <= axis || t.shape()[axis] != batch_sizes[r] {
+                    bail!("Inconsistent batch size for request {}, input {}", r, ix)
+                }
+            }
+            let shape = padded_shape(column, &self.input_dims[ix], axis)?;
+            let padded = column
+                .iter()
+                .map(|t| self.pad(ix, t, &shape, axis))
+                .collect::<TractResult<TVec<SharedTensor>>>()?;
+            if Some(ix) == self.mask.map(|(_, p)| p) {
+                let mask_ix = mask_input.unwrap();
+                let masks = column
+                    .iter()
+                    .map(|t| mask(t.shape(), &shape, axis))
+                    .collect::<TractResult<TVec<SharedTensor>>>()?;
+                let mask = Concat::new(axis as i64).eval(masks)?.remove(0);
+                let dt = self.plan.model().fact(self.plan.model().inputs()?[mask_ix])?;
+                let dt = dt.to_tensor_fact().datum_type.concretize().unwrap_or(DatumType::F32);
+                inputs[mask_ix] = Some(mask.cast_to_dt(dt)?.into_owned());
+            }
+            inputs[ix] = Some(Concat::new(axis as i64).eval(padded)?.remove(0).to_tensor());
+        }
+        let inputs = inputs.into_iter().map(|t| t.unwrap()).collect();
+        let outputs = self.plan.run(inputs)?;
+
+        let total: usize = batch_sizes.iter().sum();
+        let mut results = vec![tvec!(); batch_sizes.len()];
+        for (output, axis) in outputs.into_iter().zip(self.output_axes.iter()) {
+            let mut offset = 0;
+            for (r, &size) in batch_sizes.iter().enumerate() {
+                let split = if let Some(axis) = *axis {
+                    if output.shape()[axis] != total {
+                        bail!("Output batch axis is {}, expected {}", output.shape()[axis], total)
+                    }
+                    let mut prune = vec![(0, 0); output.shape().len()];
+                    prune[axis] = (offset, total - offset - size);
+                    Slice::new(prune).eval(tvec!(output.clone()))?.remove(0)
+                } else {
+                    // only reached with a single request
+                    output.clone()
+                };
+                results[r].push(split);
+                offset += size;
+            }
+        }
+        Ok(results)
+    }
+
+    fn pad(
+        &self,
+        input: usize,
+        t: &Tensor,
+        shape: &[usize],
+        axis: usize,
+    ) -> TractResult<SharedTensor> {
+        let mut shape = shape.to_vec();
+        shape[axis] = t.shape()[axis];
+        if t.shape() == &*shape {
+            return Ok(t.clone().into());
+        }
+        match &self.padding[input] {
+            Padding::Forbid => {
+                bail!(
+                    "Input {} shapes differ ({:?} vs {:?}) and padding is forbidden",
+                    input,
+                    t.shape(),
+                    shape
+                )
+            }
+            Padding::Value(v) => {
+                Ok(dispatch_datum!(self::pad_t(t.datum_type())(t, &shape, v))?.into())
+            }
+        }
+    }
+}
+
+/// Position of the streaming dimension, other dimensions may be unknown.
+fn batch_axis(fact: &TensorFact) -> Option<usize> {
+    fact.shape.dims().position(|d| match d {
+        GenericFact::Only(d) => d.is_stream(),
+        GenericFact::Any => false,
+    })
+}
+
+/// Extent of the batched tensor on each axis but the batch one: the model
+/// declared dimension if any, the maximum of the requests otherwise.
+fn padded_shape(
+    tensors: &[Tensor],
+    dims: &[Option<usize>],
+    axis: usize,
+) -> TractResult<TVec<usize>> {
+    let rank = dims.len();
+    let mut shape: TVec<usize> = tensors[0].shape().into();
+    for t in tensors {
+        if t.shape().len() != rank || t.datum_type() != tensors[0].datum_type() {
+            bail!("Inconsistent requests: {:?} and {:?}", tensors[0].shape(), t.shape())
+        }
+        for (d, &t) in shape.iter_mut().zip(t.shape().iter()) {
+            *d = (*d).max(t);
+        }
+    }
+    for (ix, (d, declared)) in shape.iter_mut().zip(dims.iter()).enumerate() {
+        if let Some(declared) = *declared {
+            if *d > declared {
+                bail!("Requests exceed model dimension {} on axis {}", declared, ix)
+            }
+            *d = declared;
+        }
+    }
+    shape[axis] = 0;
+    Ok(shape)
+}
+
+fn region(shape: &[usize]) -> SliceInfo<Vec<SliceOrIndex>, IxDyn> {
+    let spec = shape
+        .iter()
+        .map(|&d| SliceOrIndex::Slice { start: 0, end: Some(d as isize), step: 1 })
+        .collect();
+    SliceInfo::new(spec).unwrap()
+}
+
+fn pad_t<T: Datum>(t: &Tensor, shape: &[usize], value: &Tensor) -> TractResult<Tensor> {
+    let value = value.cast_to::<T>()?.to_scalar::<T>()?.clone();
+    let mut array = ArrayD::from_elem(shape, value);
+    array.slice_mut(region(t.shape()).as_ref()).assign(&t.to_array_view::<T>()?);
+    Ok(array.into())
+}
+
+fn mask(request: &[usize], padded: &[usize], axis: usize) -> TractResult<SharedTensor> {
+    let mut shape = padded.to_vec();
+    shape[axis] = request[axis];
+    let mut array = ArrayD::from_elem(shape, 0.0f32);
+    array.slice_mut(region(request).as_ref()).fill(1.0);
+    Ok(array.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::math::Add;
+    use crate::ops::nn::Reduce;
+
+    fn model() -> InferenceModel {
+        let mut model = InferenceModel::default();
+        let a =
+            model.add_source("a", TensorFact::dt_shape(DatumType::F32, shapefact!(_, 2))).unwrap();
+        let b =
+            model.add_source("b", TensorFact::dt_shape(DatumType::F32, shapefact!(_, 2))).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(b, 0), InletId::new(add, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(add, 0)]).unwrap();
+        model
+    }
+
+    #[test]
+    fn batch_and_split() {
+        let plan = BatchPlan::for_model(model(), 0).unwrap();
+        assert_eq!(plan.output_axes(), &[Some(0)]);
+        let r1 = tvec!(arr2(&[[1.0f32, 2.0]]).into(), arr2(&[[10.0f32, 20.0]]).into());
+        let r2 = tvec!(
+            arr2(&[[3.0f32, 4.0], [5.0, 6.0]]).into(),
+            arr2(&[[30.0f32, 40.0], [50.0, 60.0]]).into()
+        );
+        let results = plan.run(vec![r1, r2]).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0][0].as_tensor(), &Tensor::from(arr2(&[[11.0f32, 22.0]])));
+        assert_eq!(
+            results[1][0].as_tensor(),
+            &Tensor::from(arr2(&[[33.0f32, 44.0], [55.0, 66.0]]))
+        );
+    }
+
+    #[test]
+    fn padding() {
+        let plan = BatchPlan::for_model(model(), 0).unwrap();
+        let r1 = tvec!(arr2(&[[1.0f32]]).into(), arr2(&[[10.0f32]]).into());
+        let r2 = tvec!(arr2(&[[3.0f32, 4.0]]).into(), arr2(&[[30.0f32, 40.0]]).into());
+        assert!(plan.run(vec![r1.clone(), r2.clone()]).is_err());
+        let plan = plan
+            .with_padding(0, Padding::Value(0.0f32.into()))
+            .unwrap()
+            .with_padding(1, Padding::Value(0.0f32.into()))
+            .unwrap();
+        let results = plan.run(vec![r1, r2]).unwrap();
+        assert_eq!(results[0][0].as_tensor(), &Tensor::from(arr2(&[[11.0f32, 0.0]])));
+        assert_eq!(results[1][0].as_tensor(), &Tensor::from(arr2(&[[33.0f32, 44.0]])));
+    }
+
+    #[test]
+    fn masking() {
+        let plan = BatchPlan::for_model(model(), 0)
+            .unwrap()
+            .with_padding(0, Padding::Value(0.0f32.into()))
+            .unwrap()
+            .with_mask(1, 0)
+            .unwrap();
+        let r1 = tvec!(arr2(&[[1.0f32]]).into());
+        let r2 = tvec!(arr2(&[[3.0f32, 4.0]]).into());
+        let results = plan.run(vec![r1, r2]).unwrap();
+        assert_eq!(results[0][0].as_tensor(), &Tensor::from(arr2(&[[2.0f32, 0.0]])));
+        assert_eq!(results[1][0].as_tensor(), &Tensor::from(arr2(&[[4.0f32, 5.0]])));
+    }
+
+    #[test]
+    fn output_without_batch_axis() {
+        let mut model = InferenceModel::default();
+        let _ =
+            model.add_source("a", TensorFact::dt_shape(DatumType::F32, shapefact!(_, 2))).unwrap();
+        let sum = Reduce::new(Some(vec![0, 1]), false, crate::ops::nn::Reducer::Sum);
+        model.chain_default("sum", sum).unwrap();
+        let plan = BatchPlan::for_model(model, 0).unwrap();
+        assert_eq!(plan.output_axes(), &[None]);
+        assert!(!plan.batches());
+        let r1 = tvec!(arr2(&[[1.0f32, 2.0]]).into());
+        let r2 = tvec!(arr2(&[[3.0f32, 4.0], [5.0, 6.0]]).into());
+        let results = plan.run(vec![r1, r2]).unwrap();
+        assert_eq!(results[0][0].as_tensor(), &Tensor::from(3.0f32));
+        assert_eq!(results[1][0].as_tensor(), &Tensor::from(18.0f32));
+    }
+}
@@ -196,6 +196,11 @@ try_into!(i16, f32);
 try_into!(i32, f32);
 try_into!(i64, f32);
 
+try_into!(i8, f64);
+try_into!(i16, f64);
+try_into!(i32, f64);
+try_into!(i64, f64);
+
 impl TryInto<TDim> for i32 {
     fn try_into(&self) -> TractResult<TDim> {
         Ok((*self).into())
@@ -230,6 +235,16 @@ impl TryInto<f32> for bool {
     }
 }
 
+impl TryInto<f64> for bool {
+    fn try_into(&self) -> TractResult<f64> {
+        if *self {
+            Ok(1.0)
+        } else {
+            Ok(0.0)
+        }
+    }
+}
+
 impl TryInto<f32> for f16 {
     fn try_into(&self) -> TractResult<f32> {
         Ok(self.0.to_f32())
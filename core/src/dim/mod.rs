@@ -93,6 +93,36 @@ impl TDim {
         self.0.eval(&hashmap!('S' => s)).ok()
     }
 
+    /// Finds the value of S for which the expression evaluates to `value`.
+    ///
+    /// Only works for expressions that are affine in S.
+    pub fn solve(&self, value: i32) -> Option<i32> {
+        let b = self.eval(0)?;
+        let a = self.eval(1)? - b;
+        if a == 0 || (value - b) % a != 0 {
+            return None;
+        }
+        let s = (value - b) / a;
+        if self.eval(s) == Some(value) {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the expression is of the form `a*S+b`, with a non-zero `a`,
+    /// so that `solve` can invert it.
+    pub fn is_affine(&self) -> bool {
+        use self::stack::StackOp::*;
+        let ops = self.0.as_ops();
+        ops.iter().filter(|op| if let Sym(_) = op { true } else { false }).count() == 1
+            && ops.iter().all(|op| match op {
+                Div | DivCeil | Rem => false,
+                _ => true,
+            })
+            && self.eval(1) != self.eval(0)
+    }
+
     pub fn is_stream(&self) -> bool {
         self.as_const().is_none()
     }
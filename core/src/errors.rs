@@ -11,8 +11,25 @@ error_chain! {
         NdarrayShape(::ndarray::ShapeError);
         StrUtf8(::std::str::Utf8Error);
         NumParseInt(::std::num::ParseIntError);
+        Zip(::zip::result::ZipError) #[cfg(feature = "npz")];
     }
     errors {
         TFString {}
+        Cancelled {
+            description("Execution cancelled")
+            display("Execution cancelled")
+        }
+        DeadlineExceeded {
+            description("Execution deadline exceeded")
+            display("Execution deadline exceeded")
+        }
+        AnalyseFailure(failure: Box<crate::analyser::AnalyseFailure>) {
+            description("Analyse failed")
+            display("{}", failure)
+        }
+        LiveBytesExceeded(live: usize, limit: usize) {
+            description("Live tensors exceed the memory limit")
+            display("Live tensors use {} bytes, limit is {} bytes", live, limit)
+        }
     }
 }
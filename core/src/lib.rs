@@ -90,6 +90,8 @@ extern crate smallvec;
 extern crate serde_derive;
 
 extern crate tract_linalg;
+#[cfg(feature = "npz")]
+extern crate zip;
 
 #[macro_use]
 pub mod macros;
@@ -98,6 +100,7 @@ pub mod analyser;
 #[macro_use]
 pub mod ops;
 
+pub mod batch;
 pub mod broadcast;
 pub mod datum;
 pub mod dim;
@@ -105,7 +108,8 @@ pub mod errors;
 pub mod framework;
 pub mod model;
 mod ndarray_dummy_packed_mm;
-mod optim;
+pub mod npy;
+pub mod optim;
 pub mod plan;
 pub mod pulse;
 pub mod tensor;
@@ -118,7 +122,7 @@ pub use crate::dim::TDim;
 pub use crate::framework::Framework;
 pub use crate::model::TVec;
 pub use crate::model::{InferenceModel, InferenceNode};
-pub use crate::plan::{SimplePlan, SimpleState};
+pub use crate::plan::{CancellationToken, SimplePlan, SimpleState};
 pub use crate::tensor::{SharedTensor, Tensor};
 
 #[cfg(test)]
@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::*;
+use crate::analyser::types::Fact;
+use crate::ops::konst::Const;
+use crate::{DatumType, SharedTensor, TDim, Tensor};
+
+/// Structural differences between two models.
+#[derive(Clone, Debug, Default)]
+pub struct ModelDiff {
+    /// Nodes only found in the second model.
+    pub added: Vec<String>,
+    /// Nodes only found in the first model.
+    pub removed: Vec<String>,
+    /// Matching nodes that differ, or have been renamed.
+    pub changed: Vec<NodeDiff>,
+}
+
+impl ModelDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Differences between two matching nodes.
+#[derive(Clone, Debug)]
+pub struct NodeDiff {
+    /// Node name in the first model.
+    pub before: String,
+    /// Node name in the second model.
+    pub after: String,
+    pub changes: Vec<NodeChange>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeChange {
+    /// Different operators.
+    Op(String, String),
+    /// Same operator, with different parameters.
+    Params(String, String),
+    /// Different inputs, as "node" or "node:slot" names in each model.
+    Inputs(Vec<String>, Vec<String>),
+    /// Different facts on an output.
+    Fact(usize, String, String),
+    /// Constants with different values: maximum absolute difference, if
+    /// the values are numeric.
+    Weights(Option<f64>),
+}
+
+impl fmt::Display for NodeChange {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeChange::Op(a, b) => write!(fmt, "op: {} -> {}", a, b),
+            NodeChange::Params(a, b) => write!(fmt, "params: {} -> {}", a, b),
+            NodeChange::Inputs(a, b) => write!(fmt, "inputs: {:?} -> {:?}", a, b),
+            NodeChange::Fact(ix, a, b) => write!(fmt, "output #{}: {} -> {}", ix, a, b),
+            NodeChange::Weights(Some(d)) => write!(fmt, "weights: max abs diff {:e}", d),
+            NodeChange::Weights(None) => write!(fmt, "weights: different values"),
+        }
+    }
+}
+
+/// Shape and type part of a fact, leaving the value out.
+fn fact_summary<TI: TensorInfo>(fact: &TI) -> String {
+    let fact = fact.to_tensor_fact();
+    format!("{:?}", TensorFact { value: Default::default(), ..fact })
+}
+
+fn const_value<TI: TensorInfo>(node: &Node<TI>) -> Option<SharedTensor> {
+    if node.op_is::<Const>() {
+        node.outputs[0].fact.to_tensor_fact().value.concretize()
+    } else {
+        None
+    }
+}
+
+fn max_abs_diff(a: &Tensor, b: &Tensor) -> Option<f64> {
+    let a = a.cast_to::<f64>().ok()?;
+    let b = b.cast_to::<f64>().ok()?;
+    let a = a.as_slice::<f64>().ok()?;
+    let b = b.as_slice::<f64>().ok()?;
+    Some(a.iter().zip(b.iter()).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max))
+}
+
+/// FNV-1a, so that hashes do not depend on the platform or the compiler
+/// version.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn feed(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn feed_usize(&mut self, n: usize) {
+        self.feed(&(n as u64).to_le_bytes())
+    }
+
+    fn feed_str(&mut self, s: &str) {
+        self.feed_usize(s.len());
+        self.feed(s.as_bytes())
+    }
+}
+
+impl<TI: TensorInfo> Model<TI> {
+    fn outlet_name(&self, outlet: OutletId) -> String {
+        let node = &self.nodes[outlet.node];
+        if node.outputs.len() == 1 {
+            node.name.clone()
+        } else {
+            format!("{}:{}", node.name, outlet.slot)
+        }
+    }
+
+    /// Compares the structure of two models.
+    ///
+    /// Nodes are matched by name first, then by topology: a node matches
+    /// one with the same operator fed by the same (matched) inputs.
+    /// Operator parameters are compared through their Debug form, and
+    /// constants by value.
+    pub fn diff(&self, other: &Model<TI>) -> TractResult<ModelDiff> {
+        let order = self.eval_order()?;
+        let other_order = other.eval_order()?;
+        let live: HashSet<usize> = order.iter().cloned().collect();
+        let mut matches: HashMap<usize, usize> = HashMap::new();
+        for &id in &other_order {
+            if let Ok(mine) = self.node_by_name(&other.nodes[id].name) {
+                if live.contains(&mine.id) {
+                    matches.insert(id, mine.id);
+                }
+            }
+        }
+        // candidates for the topological match, by op name and inputs
+        let mut by_inputs: HashMap<(String, Vec<OutletId>), Vec<usize>> = HashMap::new();
+        for &n in &order {
+            let mine = &self.nodes[n];
+            let key = (mine.op.name().into_owned(), mine.inputs.clone());
+            by_inputs.entry(key).or_insert_with(Vec::new).push(n);
+        }
+        let mut taken: HashSet<usize> = matches.values().cloned().collect();
+        for &id in &other_order {
+            let node = &other.nodes[id];
+            if matches.contains_key(&id) || node.inputs.is_empty() {
+                continue;
+            }
+            let inputs: Option<Vec<OutletId>> = node
+                .inputs
+                .iter()
+                .map(|b| matches.get(&b.node).map(|&a| OutletId::new(a, b.slot)))
+                .collect();
+            let inputs = match inputs {
+                Some(inputs) => inputs,
+                None => continue,
+            };
+            let candidate = by_inputs
+                .get(&(node.op.name().into_owned(), inputs))
+                .and_then(|candidates| candidates.iter().find(|n| !taken.contains(n)).cloned());
+            if let Some(mine) = candidate {
+                matches.insert(id, mine);
+                taken.insert(mine);
+            }
+        }
+
+        let matched: HashSet<usize> = matches.values().cloned().collect();
+        let mut diff = ModelDiff::default();
+        diff.removed = order
+            .iter()
+            .filter(|n| !matched.contains(n))
+            .map(|&n| self.nodes[n].name.clone())
+            .collect();
+        diff.added = other_order
+            .iter()
+            .filter(|n| !matches.contains_key(n))
+            .map(|&n| other.nodes[n].name.clone())
+            .collect();
+
+        for &id in &other_order {
+            let theirs = &other.nodes[id];
+            let mine = match matches.get(&id) {
+                Some(&mine) => &self.nodes[mine],
+                None => continue,
+            };
+            let mut changes = vec![];
+            let (my_const, their_const) = (const_value(mine), const_value(theirs));
+            if mine.op.name() != theirs.op.name() {
+                changes.push(NodeChange::Op(mine.op.name().into(), theirs.op.name().into()));
+            } else if my_const.is_none() && their_const.is_none() {
+                let (a, b) = (format!("{:?}", mine.op), format!("{:?}", theirs.op));
+                if a != b {
+                    changes.push(NodeChange::Params(a, b));
+                }
+            }
+            let same_inputs = mine.inputs.len() == theirs.inputs.len()
+                && mine.inputs.iter().zip(theirs.inputs.iter()).all(|(a, b)| {
+                    matches.get(&b.node) == Some(&a.node) && a.slot == b.slot
+                });
+            if !same_inputs {
+                changes.push(NodeChange::Inputs(
+                    mine.inputs.iter().map(|&i| self.outlet_name(i)).collect(),
+                    theirs.inputs.iter().map(|&i| other.outlet_name(i)).collect(),
+                ));
+            }
+            for ix in 0..mine.outputs.len().max(theirs.outputs.len()) {
+                let a = mine.outputs.get(ix).map(|o| fact_summary(&o.fact));
+                let b = theirs.outputs.get(ix).map(|o| fact_summary(&o.fact));
+                if a != b {
+                    let none = || "none".to_string();
+                    changes.push(NodeChange::Fact(
+                        ix,
+                        a.unwrap_or_else(none),
+                        b.unwrap_or_else(none),
+                    ));
+                }
+            }
+            if let (Some(a), Some(b)) = (my_const, their_const) {
+                if a.datum_type() == b.datum_type() && a.shape() == b.shape() && a != b {
+                    changes.push(NodeChange::Weights(max_abs_diff(&a, &b)));
+                }
+            }
+            if !changes.is_empty() || mine.name != theirs.name {
+                diff.changed.push(NodeDiff {
+                    before: mine.name.clone(),
+                    after: theirs.name.clone(),
+                    changes,
+                });
+            }
+        }
+        Ok(diff)
+    }
+
+    /// A hash of the model content, stable across runs and platforms, for
+    /// use as a cache key.
+    ///
+    /// It covers the topology, the operators (through their Debug form),
+    /// the facts and the constant values, but not the node names.
+    pub fn content_hash(&self) -> TractResult<u64> {
+        let order = self.eval_order()?;
+        let position: HashMap<usize, usize> =
+            order.iter().enumerate().map(|(ix, &n)| (n, ix)).collect();
+        let mut hasher = StableHasher(0xcbf29ce484222325);
+        let feed_outlet = |hasher: &mut StableHasher, o: &OutletId| -> TractResult<()> {
+            let ix = position.get(&o.node).ok_or_else(|| format!("Unreachable {:?}", o))?;
+            hasher.feed_usize(*ix);
+            hasher.feed_usize(o.slot);
+            Ok(())
+        };
+        for &n in &order {
+            let node = &self.nodes[n];
+            hasher.feed_str(&node.op.name());
+            match const_value(node) {
+                Some(value) => {
+                    hasher.feed_str(&format!("{:?}", value.datum_type()));
+                    hasher.feed_usize(value.shape().len());
+                    value.shape().iter().for_each(|&d| hasher.feed_usize(d));
+                    match value.datum_type() {
+                        DatumType::String => {
+                            for s in value.as_slice::<String>()? {
+                                hasher.feed_str(s);
+                            }
+                        }
+                        DatumType::TDim => {
+                            for d in value.as_slice::<TDim>()? {
+                                hasher.feed_str(&format!("{:?}", d));
+                            }
+                        }
+                        dt => {
+                            // values are hashed in little endian order
+                            for item in value.as_bytes().chunks(dt.size_of()) {
+                                if cfg!(target_endian = "big") {
+                                    item.iter().rev().for_each(|b| hasher.feed(&[*b]));
+                                } else {
+                                    hasher.feed(item);
+                                }
+                            }
+                        }
+                    }
+                }
+                None => hasher.feed_str(&format!("{:?}", node.op)),
+            }
+            hasher.feed_usize(node.inputs.len());
+            for i in &node.inputs {
+                feed_outlet(&mut hasher, i)?;
+            }
+            hasher.feed_usize(node.outputs.len());
+            for o in &node.outputs {
+                hasher.feed_str(&fact_summary(&o.fact));
+            }
+        }
+        for outlets in &[&self.inputs, &self.outputs] {
+            hasher.feed_usize(outlets.len());
+            for o in outlets.iter() {
+                feed_outlet(&mut hasher, o)?;
+            }
+        }
+        Ok(hasher.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::*;
+    use crate::ops::math::{Add, Mul};
+    use crate::ops::nn::Relu;
+    use crate::ops::prelude::*;
+
+    fn model(name: &str, weight: f32) -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        let a = model.add_const("a", weight.into()).unwrap();
+        let mul = model.add_node_default(name, Mul::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(mul, 0)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(mul, 1)).unwrap();
+        model.chain("relu", Relu::default(), tvec!(TensorFact::default())).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    #[test]
+    fn same_model() {
+        let diff = model("mul", 2.0).diff(&model("mul", 2.0)).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(
+            model("mul", 2.0).content_hash().unwrap(),
+            model("mul", 2.0).content_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn renamed_and_reweighted() {
+        let diff = model("mul", 2.0).diff(&model("product", 3.0)).unwrap();
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].before, "a");
+        assert_eq!(diff.changed[0].changes, vec!(NodeChange::Weights(Some(1.0))));
+        assert_eq!((&*diff.changed[1].before, &*diff.changed[1].after), ("mul", "product"));
+        assert!(diff.changed[1].changes.is_empty());
+        assert_eq!(
+            model("mul", 2.0).content_hash().unwrap(),
+            model("product", 2.0).content_hash().unwrap()
+        );
+        assert_ne!(
+            model("mul", 2.0).content_hash().unwrap(),
+            model("mul", 3.0).content_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn replaced_op() {
+        let mut other = InferenceModel::default();
+        let x = other.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        let a = other.add_const("a", 2.0f32.into()).unwrap();
+        let add = other.add_node_default("add", Add::default()).unwrap();
+        other.add_edge(OutletId::new(x, 0), InletId::new(add, 0)).unwrap();
+        other.add_edge(OutletId::new(a, 0), InletId::new(add, 1)).unwrap();
+        other.chain("relu", Relu::default(), tvec!(TensorFact::default())).unwrap();
+        let other = other.into_typed().unwrap();
+        let diff = model("mul", 2.0).diff(&other).unwrap();
+        assert_eq!(diff.added, vec!("add"));
+        assert_eq!(diff.removed, vec!("mul"));
+        assert_eq!(diff.changed[0].after, "relu");
+        assert_eq!(
+            diff.changed[0].changes,
+            vec!(NodeChange::Inputs(vec!("mul".to_string()), vec!("add".to_string())))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+impl<TI: TensorInfo> Model<TI> {
+    /// Extracts the part of the model computing `outputs` from `inputs` as a
+    /// new standalone model.
+    ///
+    /// Each of the `inputs` outlets becomes a `Source` with the outlet fact,
+    /// named after the node it comes from (`node:slot` for nodes with several
+    /// outputs). Nodes keep their names, ops and facts, so the
+    /// original model inputs reached from `outputs` are kept as inputs too.
+    pub fn extract(&self, inputs: &[OutletId], outputs: &[OutletId]) -> TractResult<Model<TI>> {
+        let mut model = Model::default();
+        let mut mapping: HashMap<OutletId, OutletId> = HashMap::new();
+        for &input in inputs {
+            let node = &self.nodes[input.node];
+            let name = if node.outputs.len() == 1 {
+                node.name.clone()
+            } else {
+                format!("{}:{}", node.name, input.slot)
+            };
+            let id = model.add_source(name, self.fact(input)?.clone())?;
+            mapping.insert(input, OutletId::new(id, 0));
+        }
+
+        let mut done = HashSet::new();
+        let mut order = vec![];
+        let mut needed: Vec<usize> =
+            outputs.iter().filter(|o| !mapping.contains_key(o)).map(|o| o.node).collect();
+        while let Some(&node) = needed.last() {
+            if done.contains(&node) {
+                needed.pop();
+                continue;
+            }
+            let pending: Vec<usize> = self.nodes[node]
+                .inputs
+                .iter()
+                .filter(|i| !mapping.contains_key(i) && !done.contains(&i.node))
+                .map(|i| i.node)
+                .collect();
+            if pending.is_empty() {
+                order.push(node);
+                done.insert(node);
+                needed.pop();
+            } else {
+                needed.extend(pending.into_iter().rev());
+            }
+        }
+
+        for old_id in order {
+            let node = &self.nodes[old_id];
+            let facts = node.outputs.iter().map(|o| o.fact.clone()).collect();
+            let id = model.add_node(node.name.clone(), node.op.clone(), facts)?;
+            for (ix, input) in node.inputs.iter().enumerate() {
+                let outlet = mapping.get(input).cloned().unwrap_or(*input);
+                model.add_edge(outlet, InletId::new(id, ix))?;
+            }
+            for slot in 0..node.outputs.len() {
+                mapping.entry(OutletId::new(old_id, slot)).or_insert(OutletId::new(id, slot));
+            }
+        }
+        let outputs: Vec<OutletId> = outputs.iter().map(|o| mapping[o]).collect();
+        model.set_output_outlets(&outputs)?;
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::*;
+    use crate::ops::math::{Add, Mul};
+    use crate::ops::nn::Relu;
+    use crate::ops::prelude::*;
+
+    fn model() -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        let a = model.add_const("a", 2.0f32.into()).unwrap();
+        let mul = model.add_node_default("mul", Mul::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(mul, 0)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(mul, 1)).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(mul, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(add, 1)).unwrap();
+        model.chain("relu", Relu::default(), tvec!(TensorFact::default())).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    #[test]
+    fn extract_middle() {
+        let model = model();
+        let mul = OutletId::new(model.node_by_name("mul").unwrap().id, 0);
+        let x = OutletId::new(model.node_by_name("x").unwrap().id, 0);
+        let add = OutletId::new(model.node_by_name("add").unwrap().id, 0);
+        let part = model.extract(&[mul, x], &[add]).unwrap();
+        assert_eq!(part.node_names(), vec!["mul", "x", "add"]);
+        assert!(part.node(0).op_is::<crate::ops::source::Source>());
+        assert_eq!(
+            format!("{:?}", part.inputs_fact(0).unwrap()),
+            format!("{:?}", model.fact(mul).unwrap())
+        );
+        assert_eq!(part.outputs().unwrap(), &[OutletId::new(2, 0)]);
+
+        let found = crate::plan::SimplePlan::new(&part)
+            .unwrap()
+            .run(tvec!(ndarray::arr1(&[1.0f32, -4.0]).into(), ndarray::arr1(&[2.0f32, 1.0]).into()))
+            .unwrap();
+        assert_eq!(found[0], ndarray::arr1(&[3.0f32, -3.0]).into());
+    }
+
+    #[test]
+    fn extract_keeps_reached_inputs() {
+        let model = model();
+        let mul = OutletId::new(model.node_by_name("mul").unwrap().id, 0);
+        let add = OutletId::new(model.node_by_name("add").unwrap().id, 0);
+        let part = model.extract(&[mul], &[add]).unwrap();
+        assert_eq!(part.node_names(), vec!["mul", "x", "add"]);
+        assert_eq!(part.inputs().unwrap(), &[OutletId::new(0, 0), OutletId::new(1, 0)]);
+    }
+
+    #[test]
+    fn extract_names_sources_by_slot() {
+        let mut model = InferenceModel::default();
+        model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(4))).unwrap();
+        let facts = tvec!(TensorFact::default(), TensorFact::default());
+        let split = model.chain("split", crate::ops::array::Split::new(0, 2, None), facts).unwrap();
+        let relu = model.add_node_default("relu", Relu::default()).unwrap();
+        model.add_edge(OutletId::new(split, 1), InletId::new(relu, 0)).unwrap();
+        model.set_output_outlets(&[OutletId::new(relu, 0)]).unwrap();
+        let model = model.into_typed().unwrap();
+        let split = OutletId::new(model.node_by_name("split").unwrap().id, 1);
+        let relu = OutletId::new(model.node_by_name("relu").unwrap().id, 0);
+        let part = model.extract(&[split], &[relu]).unwrap();
+        assert_eq!(part.node_names(), vec!["split:1", "relu"]);
+        assert!(part.node(0).op_is::<crate::ops::source::Source>());
+    }
+}
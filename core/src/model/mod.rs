@@ -1,15 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str;
 use std::sync::Arc;
 
 pub mod compact;
+mod diff;
 mod dsl;
+mod extract;
 mod model;
 mod node;
 mod order;
 mod patch;
 mod tensor_info;
 
+pub use self::diff::{ModelDiff, NodeChange, NodeDiff};
 pub use self::dsl::*;
 pub use self::model::*;
 pub use self::node::*;
@@ -24,6 +27,7 @@ use crate::TractResult;
 pub type InferenceModel = Model<TensorFact>;
 /// Node for InferenceModel graph
 pub type InferenceNode = Node<TensorFact>;
+pub type InferenceModelPatch = patch::ModelPatch<TensorFact>;
 
 /// A model with completely determined types and shapes.
 pub type TypedModel = Model<TypedTensorInfo>;
@@ -47,6 +51,33 @@ impl InferenceModel {
         crate::analyser::Analyser::new(self)?.analyse()
     }
 
+    /// Applies an editing patch, then runs the analyser again on the part
+    /// of the model downstream of the edit. Facts in that part are inferred
+    /// from scratch, except for the ones on the nodes the patch added.
+    pub fn edit(&mut self, patch: InferenceModelPatch) -> TractResult<()> {
+        let first_new = self.nodes().len();
+        let touched = patch.apply_checked(self)?;
+        let live: HashSet<usize> = self.eval_order()?.into_iter().collect();
+        let mut region = HashSet::new();
+        let mut todo: Vec<usize> = touched.into_iter().filter(|n| live.contains(n)).collect();
+        while let Some(node) = todo.pop() {
+            if !region.insert(node) {
+                continue;
+            }
+            for output in &self.nodes[node].outputs {
+                todo.extend(output.successors.iter().map(|s| s.node).filter(|n| live.contains(n)));
+            }
+        }
+        for &node in &region {
+            if node < first_new && !self.nodes[node].op_is::<crate::ops::source::Source>() {
+                for output in &mut self.nodes[node].outputs {
+                    output.fact = TensorFact::default();
+                }
+            }
+        }
+        crate::analyser::Analyser::new(self)?.analyse_nodes(region)
+    }
+
     pub fn missing_type_shape(&self) -> TractResult<Vec<OutletId>> {
         use crate::analyser::types::Fact;
         Ok(self
@@ -79,40 +110,27 @@ impl InferenceModel {
 }
 
 impl TypedModel {
+    /// Applies an editing patch. The facts on the nodes it adds must be
+    /// complete, as typed models are not analysed again.
+    pub fn edit(&mut self, patch: TypedModelPatch) -> TractResult<()> {
+        patch.apply_checked(self)?;
+        Ok(())
+    }
+
     pub fn declutter(self) -> TractResult<TypedModel> {
-        let mut model = self;
-        loop {
-            let mut done_something = false;
-            for p in crate::optim::declutter() {
-                done_something = done_something || p.pass(&mut model)?;
-                if cfg!(debug_assertions) {
-                    model.check_edges()?;
-                }
-            }
-            if !done_something {
-                break;
-            }
-            model = compact::compact(&model)?;
-        }
-        Ok(model)
+        self.declutter_with(&crate::optim::declutter())
+    }
+
+    /// Runs the given declutter passes until none of them changes the model.
+    pub fn declutter_with(
+        self,
+        passes: &[Box<crate::optim::DeclutterPass>],
+    ) -> TractResult<TypedModel> {
+        Ok(crate::optim::run_to_fixpoint(self, passes, |p| p.name(), |p, m| p.pass(m), None)?.0)
     }
 
     pub fn codegen(self) -> TractResult<TypedModel> {
-        let mut model = self;
-        loop {
-            let mut done_something = false;
-            for p in crate::optim::codegen() {
-                done_something = done_something || p.pass(&mut model)?;
-                if cfg!(debug_assertions) {
-                    model.check_edges()?;
-                }
-            }
-            if !done_something {
-                break;
-            }
-            model = compact::compact(&model)?;
-        }
-        Ok(model)
+        Ok(crate::optim::Optimizer::default().codegen(self)?.0)
     }
 
     pub fn into_normalized(self) -> TractResult<NormalizedModel> {
@@ -7,6 +7,8 @@ pub struct ModelPatch<TI: TensorInfo> {
     pub model: Model<TI>,
     incoming: HashMap<OutletId, OutletId>,
     shunt_outlet_by: HashMap<OutletId, OutletId>,
+    /// Model inputs the patch replaces, to be removed from the model inputs.
+    replaced_inputs: Vec<OutletId>,
 }
 
 impl<TI: TensorInfo> Default for ModelPatch<TI> {
@@ -15,6 +17,7 @@ impl<TI: TensorInfo> Default for ModelPatch<TI> {
             model: Model::default(),
             incoming: HashMap::new(),
             shunt_outlet_by: HashMap::new(),
+            replaced_inputs: vec![],
         }
     }
 }
@@ -72,8 +75,85 @@ impl<TI: TensorInfo> ModelPatch<TI> {
         Self::replace_single_op(patched_model, node, tvec!(node.inputs[0]), new_op)
     }
 
+    /// Splices `op` on `outlet`: the new node consumes the outlet, and its
+    /// output replaces the outlet for every former consumer.
+    pub fn intercept<O: Into<Box<Op>>>(
+        patched_model: &Model<TI>,
+        outlet: OutletId,
+        name: impl Into<String>,
+        op: O,
+        fact: TI,
+    ) -> TractResult<ModelPatch<TI>> {
+        let mut patch = ModelPatch::default();
+        let tap = patch.tap_model(patched_model, outlet)?;
+        let id = patch.chain_after(tap, name, op, tvec!(fact))?;
+        patch.shunt_outside(outlet, OutletId::new(id, 0))?;
+        Ok(patch)
+    }
+
+    /// Removes a single-output node, reconnecting its consumers to its input
+    /// number `input`.
+    pub fn bypass(
+        patched_model: &Model<TI>,
+        node: usize,
+        input: usize,
+    ) -> TractResult<ModelPatch<TI>> {
+        let node = patched_model.node(node);
+        if node.outputs.len() != 1 {
+            bail!("Can only bypass single-output nodes, {} has {}", node, node.outputs.len())
+        }
+        let outlet = *node
+            .inputs
+            .get(input)
+            .ok_or_else(|| format!("{} has no input #{}", node, input))?;
+        let mut patch = ModelPatch::default();
+        let tap = patch.tap_model(patched_model, outlet)?;
+        patch.shunt_outside(OutletId::new(node.id, 0), tap)?;
+        Ok(patch)
+    }
+
+    /// Replaces the output of a single-output node, typically a model input,
+    /// by a constant.
+    pub fn replace_with_const(
+        patched_model: &Model<TI>,
+        node: usize,
+        value: SharedTensor,
+    ) -> TractResult<ModelPatch<TI>>
+    where
+        Model<TI>: ModelDslConst,
+    {
+        let node = patched_model.node(node);
+        if node.outputs.len() != 1 {
+            bail!("Can only replace single-output nodes, {} has {}", node, node.outputs.len())
+        }
+        let mut patch = ModelPatch::default();
+        let id = patch.add_const(&*node.name, value)?;
+        patch.shunt_outside(OutletId::new(node.id, 0), OutletId::new(id, 0))?;
+        patch.replaced_inputs.push(OutletId::new(node.id, 0));
+        Ok(patch)
+    }
+
+    /// Applies the patch, then checks the patched model edges. Returns the
+    /// nodes the patch added and the ones it connected them to.
+    pub fn apply_checked(self, model: &mut Model<TI>) -> TractResult<Vec<usize>> {
+        let first_new = model.nodes().len();
+        let shunted: Vec<OutletId> = self.shunt_outlet_by.keys().cloned().collect();
+        let mut touched: Vec<usize> = shunted
+            .iter()
+            .flat_map(|o| model.node(o.node).outputs[o.slot].successors.iter().map(|i| i.node))
+            .collect();
+        self.apply(model)?;
+        model.check_edges()?;
+        touched.extend(first_new..model.nodes().len());
+        touched.sort();
+        touched.dedup();
+        Ok(touched)
+    }
+
     pub fn apply(self, model: &mut Model<TI>) -> TractResult<()> {
-        let ModelPatch { model: patch, incoming: mut mapping, shunt_outlet_by } = self;
+        let ModelPatch { model: patch, incoming: mut mapping, shunt_outlet_by, replaced_inputs } =
+            self;
+        let first_new = model.nodes().len();
         for node in patch.nodes {
             if node.op_is::<crate::ops::source::Source>() {
                 continue;
@@ -92,7 +172,7 @@ impl<TI: TensorInfo> ModelPatch<TI> {
         for (outlet, by) in shunt_outlet_by {
             let fixed_by = mapping[&by];
             let succs = model.nodes()[outlet.node].outputs[outlet.slot].successors.clone();
-            for succ in succs {
+            for succ in succs.into_iter().filter(|succ| succ.node < first_new) {
                 model.add_edge(fixed_by, succ)?;
             }
             for o in model.outputs.iter_mut() {
@@ -101,6 +181,82 @@ impl<TI: TensorInfo> ModelPatch<TI> {
                 }
             }
         }
+        model.inputs.retain(|i| !replaced_inputs.contains(i));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::math::{Add, Neg};
+    use crate::ops::nn::Relu;
+
+    fn model() -> InferenceModel {
+        let mut model = InferenceModel::default();
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        model.chain_default("neg", Neg::default()).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(1, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(add, 1)).unwrap();
+        model.analyse().unwrap();
+        model
+    }
+
+    fn run(model: &InferenceModel, inputs: TVec<Tensor>) -> TVec<SharedTensor> {
+        crate::plan::SimplePlan::new(model).unwrap().run(inputs).unwrap()
+    }
+
+    #[test]
+    fn intercept_source() {
+        let mut model = model();
+        let patch = InferenceModelPatch::intercept(
+            &model,
+            OutletId::new(0, 0),
+            "relu",
+            Relu::default(),
+            TensorFact::default(),
+        )
+        .unwrap();
+        model.edit(patch).unwrap();
+        assert_eq!(model.node_by_name("neg").unwrap().inputs, vec!(OutletId::new(3, 0)));
+        assert_eq!(model.node_by_name("add").unwrap().inputs[1], OutletId::new(3, 0));
+        assert_eq!(model.fact(OutletId::new(3, 0)).unwrap(), model.input_fact().unwrap());
+        let found = run(&model, tvec!(ndarray::arr1(&[-1.0f32, 2.0]).into()));
+        assert_eq!(found[0], ndarray::arr1(&[0.0f32, 0.0]).into());
+    }
+
+    #[test]
+    fn bypass_node() {
+        let mut model = model();
+        let patch = InferenceModelPatch::bypass(&model, 1, 0).unwrap();
+        model.edit(patch).unwrap();
+        assert_eq!(model.eval_order().unwrap(), vec!(0, 2));
+        let found = run(&model, tvec!(ndarray::arr1(&[-1.0f32, 2.0]).into()));
+        assert_eq!(found[0], ndarray::arr1(&[-2.0f32, 4.0]).into());
+    }
+
+    #[test]
+    fn input_to_const() {
+        let mut model = model();
+        let value: SharedTensor = ndarray::arr1(&[1i32, 2, 3]).into();
+        let patch = InferenceModelPatch::replace_with_const(&model, 0, value).unwrap();
+        model.edit(patch).unwrap();
+        assert!(model.inputs().unwrap().is_empty());
+        let fact = model.output_fact().unwrap();
+        assert_eq!(fact.datum_type, typefact!(DatumType::I32));
+        assert_eq!(fact.shape, shapefact!(3));
+        let found = run(&model, tvec!());
+        assert_eq!(found[0], ndarray::arr1(&[0i32, 0, 0]).into());
+    }
+
+    #[test]
+    fn shunting_input_keeps_it() {
+        let mut model = model();
+        let mut patch = InferenceModelPatch::default();
+        let id = patch.add_const("zero", ndarray::arr1(&[0.0f32, 0.0]).into()).unwrap();
+        patch.shunt_outside(OutletId::new(0, 0), OutletId::new(id, 0)).unwrap();
+        model.edit(patch).unwrap();
+        assert_eq!(model.inputs().unwrap(), &[OutletId::new(0, 0)]);
+    }
+}
@@ -95,6 +95,43 @@ impl ShapeInfo {
     pub fn to_shape_fact(&self) -> ShapeFact {
         ShapeFact::from(self.iter())
     }
+
+    /// The value S takes when a tensor of this shape has the actual `shape`.
+    pub fn stream_value(&self, shape: &[usize]) -> TractResult<Option<i32>> {
+        if shape.len() != self.rank() {
+            bail!("Expected a shape like {:?}, got {:?}", self, shape);
+        }
+        match self.stream_info {
+            None => Ok(None),
+            Some(info) => match info.len.solve(shape[info.axis] as i32) {
+                Some(s) => Ok(Some(s)),
+                None => bail!("Can not find S for {:?} in {:?}", shape, self),
+            },
+        }
+    }
+
+    /// The concrete shape for the value `s` of S.
+    pub fn eval(&self, s: Option<i32>) -> TractResult<TVec<usize>> {
+        match (self.stream_info, s) {
+            (None, _) => Ok(self.shape.clone()),
+            (Some(info), Some(s)) => {
+                let mut shape = self.shape.clone();
+                shape[info.axis] = info.len.eval(s).ok_or("Can not evaluate dimension")? as usize;
+                Ok(shape)
+            }
+            (Some(_), None) => bail!("Can not evaluate {:?} without a value for S", self),
+        }
+    }
+
+    /// The shape after a `PermuteAxes` with `axes`.
+    pub fn permuted(&self, axes: &[usize]) -> ShapeInfo {
+        let shape = axes.iter().map(|&ax| self.shape[ax]).collect();
+        let stream_info = self.stream_info.map(|info| StreamInfo {
+            axis: axes.iter().position(|&ax| ax == info.axis).unwrap(),
+            len: info.len,
+        });
+        ShapeInfo { shape, stream_info }
+    }
 }
 
 impl<T: AsRef<[usize]>> From<T> for ShapeInfo {
@@ -117,6 +154,21 @@ pub struct TypedTensorInfo {
     pub konst: Option<SharedTensor>,
 }
 
+impl TypedTensorInfo {
+    /// Whether the fact is a constant depending on S.
+    ///
+    /// Such a constant can not be evaluated at runtime, so it must not be
+    /// baked into an op unless the op recovers S by itself.
+    pub fn is_symbolic_konst(&self) -> TractResult<bool> {
+        match self.konst {
+            Some(ref konst) if konst.datum_type() == DatumType::TDim => {
+                Ok(konst.as_slice::<TDim>()?.iter().any(|d| d.is_stream()))
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
 impl TensorInfo for TypedTensorInfo {
     fn to_tensor_fact(&self) -> TensorFact {
         match self.konst.clone() {
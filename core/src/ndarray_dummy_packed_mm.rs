@@ -1,7 +1,8 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
-use tract_linalg::MatMul;
+use tract_linalg::frame::fused::apply_fused;
 use tract_linalg::frame::matmul::PackedWriter;
+use tract_linalg::{FusedSpec, MatMul};
 
 #[derive(Copy, Clone, Debug, new)]
 pub struct NdArrayDummyPackedMatMul<T: ndarray::LinalgScalar + Copy> {
@@ -48,7 +49,15 @@ impl<T: ndarray::LinalgScalar + Copy + Send + Sync + Debug> MatMul<T>
         PackedWriter::new(pb, self.n, self.n, self.k)
     }
 
-    fn mat_mul_prepacked(&self, pa: *const T, pb: *const T, pc: *mut T, rsc: isize, csc: isize) {
+    fn mat_mul_prepacked_fused(
+        &self,
+        pa: *const T,
+        pb: *const T,
+        pc: *mut T,
+        rsc: isize,
+        csc: isize,
+        fused: &[FusedSpec<T>],
+    ) {
         unsafe {
             assert_eq!(rsc, self.n as isize);
             assert_eq!(csc, 1);
@@ -56,6 +65,7 @@ impl<T: ndarray::LinalgScalar + Copy + Send + Sync + Debug> MatMul<T>
             let b = ndarray::ArrayView::from_shape_ptr((self.k, self.n), pb);
             let mut c = ndarray::ArrayViewMut::from_shape_ptr((self.m, self.n), pc);
             ndarray::linalg::general_mat_mul(T::one(), &a, &b, T::zero(), &mut c);
+            apply_fused(fused, pc, rsc, csc, (0, 0), (self.m, self.n));
         }
     }
 }
@@ -102,12 +112,21 @@ impl<T: ndarray::LinalgScalar + Copy + Send + Sync + Debug> MatMul<T>
         PackedWriter::new(pb, self.n, self.n, self.k)
     }
 
-    fn mat_mul_prepacked(&self, pa: *const T, pb: *const T, pc: *mut T, _rsc: isize, _csc: isize) {
+    fn mat_mul_prepacked_fused(
+        &self,
+        pa: *const T,
+        pb: *const T,
+        pc: *mut T,
+        _rsc: isize,
+        _csc: isize,
+        fused: &[FusedSpec<T>],
+    ) {
         unsafe {
             let a = ndarray::ArrayView::from_shape_ptr(self.k, pa);
             let b = ndarray::ArrayView::from_shape_ptr((self.k, self.n), pb);
             let mut c = ndarray::ArrayViewMut::from_shape_ptr(self.n, pc);
             ndarray::linalg::general_mat_vec_mul(T::one(), &b.t(), &a, T::zero(), &mut c);
+            apply_fused(fused, pc, 0, 1, (0, 0), (1, self.n));
         }
     }
 }
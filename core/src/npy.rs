@@ -0,0 +1,358 @@
+//! Reading and writing tensors in NumPy .npy format, and .npz archives
+//! with the `npz` feature.
+//!
+//! Tensors are written in native byte order. Strings map to fixed-width
+//! unicode arrays, and dimensions to 64-bit integers.
+
+use std::io::{Read, Write};
+
+use crate::datum::Datum;
+use crate::tensor::Tensor;
+use crate::{DatumType, TDim, TractResult};
+use tract_linalg::f16::f16;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+fn native_endian() -> char {
+    if cfg!(target_endian = "little") {
+        '<'
+    } else {
+        '>'
+    }
+}
+
+enum Dtype {
+    Datum(DatumType),
+    Unicode(usize),
+    Bytes(usize),
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> TractResult<(char, Dtype)> {
+        let mut chars = descr.chars();
+        let endian = match chars.next() {
+            Some('=') => native_endian(),
+            Some(c @ '<') | Some(c @ '>') | Some(c @ '|') => c,
+            _ => bail!("Unsupported dtype {}", descr),
+        };
+        let code = chars.as_str();
+        let dtype = match code {
+            "b1" => Dtype::Datum(DatumType::Bool),
+            "u1" => Dtype::Datum(DatumType::U8),
+            "u2" => Dtype::Datum(DatumType::U16),
+            "i1" => Dtype::Datum(DatumType::I8),
+            "i2" => Dtype::Datum(DatumType::I16),
+            "i4" => Dtype::Datum(DatumType::I32),
+            "i8" => Dtype::Datum(DatumType::I64),
+            "f2" => Dtype::Datum(DatumType::F16),
+            "f4" => Dtype::Datum(DatumType::F32),
+            "f8" => Dtype::Datum(DatumType::F64),
+            _ if code.starts_with('U') => Dtype::Unicode(code[1..].parse()?),
+            _ if code.starts_with('S') => Dtype::Bytes(code[1..].parse()?),
+            _ => bail!("Unsupported dtype {}", descr),
+        };
+        Ok((endian, dtype))
+    }
+
+    fn item_size(&self) -> usize {
+        match self {
+            Dtype::Datum(dt) => dt.size_of(),
+            Dtype::Unicode(n) => 4 * n,
+            Dtype::Bytes(n) => *n,
+        }
+    }
+}
+
+/// Extracts the value of `key` from the header dictionary.
+fn header_value<'h>(header: &'h str, key: &str) -> TractResult<&'h str> {
+    let pattern = format!("'{}':", key);
+    let start = header.find(&*pattern).ok_or_else(|| format!("No {} in npy header", key))?;
+    Ok(header[start + pattern.len()..].trim_start())
+}
+
+fn parse_header(header: &str) -> TractResult<(String, bool, Vec<usize>)> {
+    let descr = header_value(header, "descr")?;
+    if !descr.starts_with('\'') {
+        bail!("Unsupported structured dtype in npy header: {}", header)
+    }
+    let descr = descr[1..].split('\'').next().unwrap().to_string();
+    let fortran = header_value(header, "fortran_order")?.starts_with("True");
+    let shape = header_value(header, "shape")?;
+    let end = shape.find(')').ok_or_else(|| format!("Invalid npy shape in {}", header))?;
+    let shape = shape[1..end]
+        .split(',')
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .map(|d| d.trim_end_matches('L').parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()?;
+    Ok((descr, fortran, shape))
+}
+
+unsafe fn from_raw(dt: DatumType, shape: &[usize], data: &[u8]) -> TractResult<Tensor> {
+    match dt {
+        // any byte but 0 or 1 would be an invalid bool
+        DatumType::Bool => from_vec(shape, data.iter().map(|&b| b != 0).collect()),
+        DatumType::U8 => Tensor::from_raw::<u8>(shape, data),
+        DatumType::U16 => Tensor::from_raw::<u16>(shape, data),
+        DatumType::I8 => Tensor::from_raw::<i8>(shape, data),
+        DatumType::I16 => Tensor::from_raw::<i16>(shape, data),
+        DatumType::I32 => Tensor::from_raw::<i32>(shape, data),
+        DatumType::I64 => Tensor::from_raw::<i64>(shape, data),
+        DatumType::F16 => Tensor::from_raw::<f16>(shape, data),
+        DatumType::F32 => Tensor::from_raw::<f32>(shape, data),
+        DatumType::F64 => Tensor::from_raw::<f64>(shape, data),
+        _ => bail!("{:?} has no raw npy representation", dt),
+    }
+}
+
+fn from_vec<T: Datum>(shape: &[usize], data: Vec<T>) -> TractResult<Tensor> {
+    Ok(::ndarray::ArrayD::from_shape_vec(shape, data)?.into())
+}
+
+/// Reads a tensor in .npy format.
+pub fn read_npy<R: Read>(r: &mut R) -> TractResult<Tensor> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic[0..6] != MAGIC {
+        bail!("Not a npy file")
+    }
+    let header_len = if magic[6] == 1 {
+        let mut len = [0u8; 2];
+        r.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        r.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let mut header = vec![0u8; header_len];
+    r.read_exact(&mut header)?;
+    let (descr, fortran, shape) = parse_header(std::str::from_utf8(&header)?)?;
+    let (endian, dtype) = Dtype::parse(&descr)?;
+    let item_size = dtype.item_size();
+    let len = shape.iter().product::<usize>();
+    let mut data = vec![0u8; len * item_size];
+    r.read_exact(&mut data)?;
+    if item_size > 1 && endian != '|' && endian != native_endian() {
+        let swapped = match dtype {
+            Dtype::Unicode(_) => 4,
+            _ => item_size,
+        };
+        data.chunks_mut(swapped).for_each(|item| item.reverse());
+    }
+    let read_shape: Vec<usize> =
+        if fortran { shape.iter().rev().cloned().collect() } else { shape.clone() };
+    let tensor = match dtype {
+        Dtype::Datum(dt) => unsafe { from_raw(dt, &read_shape, &data)? },
+        Dtype::Unicode(_) => {
+            let strings = data
+                .chunks(item_size.max(1))
+                .take(len)
+                .map(|item| {
+                    item.chunks(4)
+                        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                        .take_while(|&c| c != 0)
+                        .map(|c| std::char::from_u32(c).ok_or("Invalid unicode in npy string"))
+                        .collect::<Result<String, _>>()
+                })
+                .collect::<Result<Vec<String>, _>>()?;
+            from_vec(&read_shape, strings)?
+        }
+        Dtype::Bytes(_) => {
+            let strings = data
+                .chunks(item_size.max(1))
+                .take(len)
+                .map(|item| {
+                    let end = item.iter().position(|&b| b == 0).unwrap_or(item.len());
+                    std::str::from_utf8(&item[..end]).map(|s| s.to_string())
+                })
+                .collect::<Result<Vec<String>, _>>()?;
+            from_vec(&read_shape, strings)?
+        }
+    };
+    if fortran {
+        let axes: Vec<usize> = (0..shape.len()).rev().collect();
+        tensor.permute_axes(&axes)
+    } else {
+        Ok(tensor)
+    }
+}
+
+/// Writes a tensor in .npy format.
+pub fn write_npy<W: Write>(w: &mut W, tensor: &Tensor) -> TractResult<()> {
+    let (descr, data) = match tensor.datum_type() {
+        DatumType::String => {
+            let strings = tensor.as_slice::<String>()?;
+            let width = strings.iter().map(|s| s.chars().count()).max().unwrap_or(0).max(1);
+            let mut data = Vec::with_capacity(strings.len() * width * 4);
+            for s in strings {
+                for c in s.chars().chain(std::iter::repeat('\0')).take(width) {
+                    data.extend_from_slice(&(c as u32).to_ne_bytes());
+                }
+            }
+            (format!("{}U{}", native_endian(), width), data)
+        }
+        DatumType::TDim => {
+            let mut data = Vec::with_capacity(tensor.shape().iter().product::<usize>() * 8);
+            for d in tensor.as_slice::<TDim>()? {
+                data.extend_from_slice(&(d.to_integer()? as i64).to_ne_bytes());
+            }
+            (format!("{}i8", native_endian()), data)
+        }
+        dt => {
+            let code = match dt {
+                DatumType::Bool => "b1",
+                DatumType::U8 => "u1",
+                DatumType::U16 => "u2",
+                DatumType::I8 => "i1",
+                DatumType::I16 => "i2",
+                DatumType::I32 => "i4",
+                DatumType::I64 => "i8",
+                DatumType::F16 => "f2",
+                DatumType::F32 => "f4",
+                DatumType::F64 => "f8",
+                _ => unreachable!(),
+            };
+            let endian = if dt.size_of() == 1 { '|' } else { native_endian() };
+            (format!("{}{}", endian, code), tensor.as_bytes().to_vec())
+        }
+    };
+    let shape = match tensor.shape() {
+        [] => "()".to_string(),
+        [d] => format!("({},)", d),
+        dims => format!("({})", dims.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header =
+        format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    while (MAGIC.len() + 4 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+    if header.len() > std::u16::MAX as usize {
+        bail!("npy header too long")
+    }
+    w.write_all(MAGIC)?;
+    w.write_all(&[1, 0])?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    w.write_all(&data)?;
+    Ok(())
+}
+
+/// Reads all the tensors from a .npz archive, with their names.
+#[cfg(feature = "npz")]
+pub fn read_npz<R: Read + std::io::Seek>(r: R) -> TractResult<Vec<(String, Tensor)>> {
+    let mut archive = zip::ZipArchive::new(r)?;
+    let mut tensors = vec![];
+    for ix in 0..archive.len() {
+        let mut file = archive.by_index(ix)?;
+        let name = file.name().trim_end_matches(".npy").to_string();
+        tensors.push((name, read_npy(&mut file)?));
+    }
+    Ok(tensors)
+}
+
+/// Writes named tensors to a .npz archive.
+#[cfg(feature = "npz")]
+pub fn write_npz<W: Write + std::io::Seek>(w: W, tensors: &[(String, &Tensor)]) -> TractResult<()> {
+    let mut archive = zip::ZipWriter::new(w);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, tensor) in tensors {
+        archive.start_file(format!("{}.npy", name), options)?;
+        write_npy(&mut archive, tensor)?;
+    }
+    archive.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dim::ToDim;
+    use ndarray::arr2;
+
+    fn roundtrip(tensor: Tensor) {
+        let mut buffer = vec![];
+        write_npy(&mut buffer, &tensor).unwrap();
+        let read = read_npy(&mut &*buffer).unwrap();
+        assert_eq!(read, tensor);
+    }
+
+    #[test]
+    fn roundtrip_all_types() {
+        roundtrip(arr2(&[[true, false], [false, true]]).into());
+        roundtrip(arr2(&[[1u8, 2], [3, 4]]).into());
+        roundtrip(arr2(&[[1u16, 2], [3, 4]]).into());
+        roundtrip(arr2(&[[1i8, -2], [3, 4]]).into());
+        roundtrip(arr2(&[[1i16, -2], [3, 4]]).into());
+        roundtrip(arr2(&[[1i32, -2], [3, 4]]).into());
+        roundtrip(arr2(&[[1i64, -2], [3, 4]]).into());
+        roundtrip(arr2(&[[f16::from(1.5f32), f16::from(-2f32)]]).into());
+        roundtrip(arr2(&[[1.5f32, -2.0], [3.25, 4.0]]).into());
+        roundtrip(arr2(&[[1.5f64, -2.0], [3.25, 4.0]]).into());
+        roundtrip(arr2(&[["a".to_string(), "été".to_string()]]).into());
+        roundtrip(Tensor::from(3.0f32));
+    }
+
+    #[test]
+    fn dims_as_i64() {
+        let dims: Tensor = ndarray::arr1(&[2.to_dim(), 3.to_dim()]).into();
+        let mut buffer = vec![];
+        write_npy(&mut buffer, &dims).unwrap();
+        assert_eq!(read_npy(&mut &*buffer).unwrap(), ndarray::arr1(&[2i64, 3]).into());
+    }
+
+    #[test]
+    fn numpy_layout() {
+        // np.save(f, np.array([1, 2, 3], dtype='<i4'))
+        let mut expected = b"\x93NUMPY\x01\x00\x76\x00".to_vec();
+        let header = "{'descr': '<i4', 'fortran_order': False, 'shape': (3,), }";
+        expected.extend(header.bytes());
+        expected.extend(std::iter::repeat(b' ').take(118 - header.len() - 1));
+        expected.push(b'\n');
+        expected.extend(&[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]);
+        if cfg!(target_endian = "little") {
+            let mut buffer = vec![];
+            write_npy(&mut buffer, &ndarray::arr1(&[1i32, 2, 3]).into()).unwrap();
+            assert_eq!(buffer, expected);
+        }
+        assert_eq!(read_npy(&mut &*expected).unwrap(), ndarray::arr1(&[1i32, 2, 3]).into());
+    }
+
+    #[test]
+    fn fortran_big_endian() {
+        let header = "{'descr': '>i2', 'fortran_order': True, 'shape': (2, 3), }\n";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend(&(header.len() as u16).to_le_bytes());
+        bytes.extend(header.bytes());
+        for v in &[1i16, 4, 2, 5, 3, 6] {
+            bytes.extend(&v.to_be_bytes());
+        }
+        let read = read_npy(&mut &*bytes).unwrap();
+        assert_eq!(read, arr2(&[[1i16, 2, 3], [4, 5, 6]]).into());
+    }
+
+    #[test]
+    fn non_canonical_bools() {
+        let header = "{'descr': '|b1', 'fortran_order': False, 'shape': (3,), }\n";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend(&(header.len() as u16).to_le_bytes());
+        bytes.extend(header.bytes());
+        bytes.extend(&[0, 1, 255]);
+        let read = read_npy(&mut &*bytes).unwrap();
+        assert_eq!(read, ndarray::arr1(&[false, true, true]).into());
+    }
+
+    #[cfg(feature = "npz")]
+    #[test]
+    fn npz_roundtrip() {
+        let a: Tensor = arr2(&[[1.0f32, 2.0]]).into();
+        let b: Tensor = ndarray::arr1(&[3i64]).into();
+        let mut buffer = std::io::Cursor::new(vec![]);
+        write_npz(&mut buffer, &[("a".to_string(), &a), ("b".to_string(), &b)]).unwrap();
+        buffer.set_position(0);
+        let read = read_npz(buffer).unwrap();
+        assert_eq!(read, vec!(("a".to_string(), a), ("b".to_string(), b)));
+    }
+}
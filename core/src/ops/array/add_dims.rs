@@ -1,6 +1,6 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct AddDims {
     pub axes: Vec<usize>,
 }
@@ -26,6 +26,8 @@ impl Op for AddDims {
         "AddDims".into()
     }
 
+    impl_op_same_as!();
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
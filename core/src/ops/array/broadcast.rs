@@ -21,11 +21,8 @@ impl StatelessOp for MultiBroadcastTo {
     /// Evaluates the operation given the input tensors.
     fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
         let (input, dims) = args_2!(inputs);
-        let dims: Vec<usize> = dims
-            .to_array_view::<i64>()?
-            .iter()
-            .map(|i| *i as usize)
-            .collect();
+        let dims: Vec<usize> =
+            dims.cast_to::<i64>()?.to_array_view::<i64>()?.iter().map(|i| *i as usize).collect();
         let dims = crate::broadcast::multi_broadcast(&[&*dims, &*input.shape()])
             .ok_or("incompatible shapes")?;
         dispatch_datum!(Self::eval_t(input.datum_type())(input.as_tensor(), &*dims))
@@ -41,22 +38,58 @@ impl InferenceRulesOp for MultiBroadcastTo {
     ) -> InferenceResult {
         check_input_arity(&inputs, 2)?;
         check_output_arity(&outputs, 1)?;
-        s.equals(&inputs[1].datum_type, DatumType::I64)?;
         s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
         s.equals(&inputs[1].rank, 1)?;
         s.given(&inputs[0].shape, move |s, shape| {
             s.given(&inputs[1].value, move |s, dims| {
-                let dims: Vec<TDim> = dims
-                    .to_array_view::<i64>()
-                    .unwrap()
-                    .iter()
-                    .map(|i| TDim::from(*i))
-                    .collect();
-                let dims = crate::broadcast::multi_broadcast(&[&*dims, &*shape])
-                    .ok_or("incompatible shapes")
-                    .unwrap();
+                // dims may be symbolic when they come from a Shape
+                let dims = dims.cast_to::<TDim>()?;
+                let dims = crate::broadcast::multi_broadcast(&[dims.as_slice::<TDim>()?, &*shape])
+                    .ok_or("incompatible shapes")?;
                 s.equals(&outputs[0].shape, ShapeFact::from(dims))
             })
         })
     }
 }
+
+/// Broadcast to a constant, possibly symbolic, shape, as produced by
+/// constant propagation on a MultiBroadcastTo.
+///
+/// S is recovered from the actual input shape at evaluation time.
+#[derive(Debug, Clone, PartialEq, new)]
+pub struct MultiBroadcastToUnary {
+    input_shape: ShapeInfo,
+    shape: ShapeInfo,
+}
+
+impl Op for MultiBroadcastToUnary {
+    fn name(&self) -> Cow<str> {
+        "MultiBroadcastToUnary".into()
+    }
+
+    impl_op_same_as!();
+}
+
+impl StatelessOp for MultiBroadcastToUnary {
+    fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
+        let input = args_1!(inputs);
+        let s = self.input_shape.stream_value(input.shape())?;
+        let dims = self.shape.eval(s)?;
+        dispatch_datum!(MultiBroadcastTo::eval_t(input.datum_type())(input.as_tensor(), &*dims))
+    }
+}
+
+impl InferenceRulesOp for MultiBroadcastToUnary {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&inputs[0].shape, self.input_shape.to_shape_fact())?;
+        s.equals(&outputs[0].shape, self.shape.to_shape_fact())
+    }
+}
@@ -5,7 +5,7 @@ use crate::pulse::delay::Delay;
 use std::ops::Range;
 
 /// Concat: high level concat op
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Concat {
     axis: i64,
 }
@@ -41,12 +41,20 @@ impl Op for Concat {
         "Concat".into()
     }
 
+    impl_op_same_as!();
+
     fn declutter(
         &self,
         model: &TypedModel,
         node: &TypedNode,
     ) -> TractResult<Option<TypedModelPatch>> {
         let inputs = model.node_input_facts(node.id)?;
+        // symbolic slices could not be evaluated by NormConcat
+        for input in &inputs {
+            if input.is_symbolic_konst()? {
+                return Ok(None);
+            }
+        }
 
         if let Some(super_type) = DatumType::super_type_for(inputs.iter().map(|x| x.datum_type)) {
             let axis = self.resolve_axis(inputs[0].shape.rank() as i64)?;
@@ -166,6 +174,31 @@ impl<T: Datum + Copy> Op for NormConcat<T> {
         format!("NormConcat<{:?}>", T::datum_type()).into()
     }
 
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        if node.inputs.len() != 1 {
+            return Ok(None);
+        }
+        let inverse = super::PermuteAxes::inverse(axes);
+        let slices = self
+            .slices
+            .iter()
+            .map(|slice| match slice {
+                NormConcatSlice::Const(c) => NormConcatSlice::Const(
+                    c.view().permuted_axes(&*inverse).to_owned(),
+                ),
+                NormConcatSlice::Var(shape) => NormConcatSlice::Var(shape.permuted(&inverse)),
+            })
+            .collect();
+        let op = NormConcat::new(axes[self.axis], slices);
+        Ok(Some((Box::new(op), Some(axes.to_vec()))))
+    }
+
     fn pulsify(
         &self,
         source: &NormalizedModel,
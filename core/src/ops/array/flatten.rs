@@ -1,6 +1,6 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new, Default)]
+#[derive(Debug, Clone, PartialEq, new, Default)]
 pub struct Flatten {
     axis: usize,
 }
@@ -20,6 +20,36 @@ impl Op for Flatten {
     fn name(&self) -> Cow<str> {
         "Flatten".into()
     }
+
+    impl_op_same_as!();
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let mut fact = target.fact(input)?.clone();
+        let (group, outer, inner) = if fact.axis < self.axis {
+            (0, &fact.shape[..fact.axis], &fact.shape[fact.axis + 1..self.axis])
+        } else {
+            (1, &fact.shape[self.axis..fact.axis], &fact.shape[fact.axis + 1..])
+        };
+        if outer.iter().any(|&d| d != 1) {
+            bail!("Can not pulsify Flatten: streaming axis must be the outermost axis of its group")
+        }
+        let inner = inner.iter().product::<usize>();
+        let shape_0 = fact.shape[..self.axis].iter().product::<usize>();
+        let shape_1 = fact.shape[self.axis..].iter().product::<usize>();
+        fact.shape = tvec!(shape_0, shape_1);
+        fact.axis = group;
+        fact.dim = fact.dim * inner;
+        fact.delay *= inner;
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
 }
 
 impl StatelessOp for Flatten {
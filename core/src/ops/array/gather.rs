@@ -1,7 +1,7 @@
 use crate::ops::prelude::*;
 use ndarray::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Gather {
     axis: i64,
 }
@@ -10,28 +10,41 @@ impl Op for Gather {
     fn name(&self) -> Cow<str> {
         "Gather".into()
     }
+
+    impl_op_same_as!();
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if let Some(ref indices) = model.fact(node.inputs[1])?.konst {
+            let op = GatherUnary::new(self.clone(), indices.clone());
+            return Ok(Some(TypedModelPatch::single_unary_op(model, node, op)?));
+        }
+        Ok(None)
+    }
 }
 
 impl Gather {
+    fn resolved_axis(&self, rank: usize) -> TractResult<usize> {
+        let rank = rank as i64;
+        if 0 <= self.axis && self.axis <= rank - 1 {
+            Ok(self.axis as usize)
+        } else if -rank <= self.axis && self.axis < 0 {
+            Ok((self.axis + rank) as usize)
+        } else {
+            bail!("Illegal combination of values for rank and axis")
+        }
+    }
+
     fn eval_t<T: Datum>(
         &self,
         data: SharedTensor,
         indices: &SharedTensor,
     ) -> TractResult<SharedTensor> {
         let data_view = data.to_array_view::<T>()?;
-        let rank = data.shape().len() as i64;
-        let axis = {
-            let axis_res: TractResult<i64> = {
-                if 0 <= self.axis && self.axis <= rank - 1 {
-                    Ok(self.axis)
-                } else if -rank <= self.axis && self.axis < 0 {
-                    Ok(self.axis + rank)
-                } else {
-                    bail!("Illegal combination of values for rank and axis")
-                }
-            };
-            axis_res? as usize
-        };
+        let axis = self.resolved_axis(data.shape().len())?;
 
         if indices.shape().len() == 0 {
             return Ok(data_view
@@ -93,6 +106,74 @@ impl InferenceRulesOp for Gather {
     }
 }
 
+/// Gather with constant indices, as produced by decluttering a Gather.
+#[derive(Debug, Clone, PartialEq, new)]
+pub struct GatherUnary {
+    gather: Gather,
+    indices: SharedTensor,
+}
+
+impl Op for GatherUnary {
+    fn name(&self) -> Cow<str> {
+        "GatherUnary".into()
+    }
+
+    impl_op_same_as!();
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let mut fact = target.fact(input)?.clone();
+        let axis = self.gather.resolved_axis(fact.shape.len())?;
+        if fact.axis == axis {
+            bail!("Can not pulsify Gather along the streaming axis");
+        }
+        if fact.axis > axis {
+            fact.axis = fact.axis + self.indices.shape().len() - 1;
+        }
+        let mut shape: TVec<usize> = fact.shape[..axis].into();
+        shape.extend(self.indices.shape().iter().cloned());
+        shape.extend(fact.shape[axis + 1..].iter().cloned());
+        fact.shape = shape;
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
+}
+
+impl StatelessOp for GatherUnary {
+    fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
+        let data = args_1!(inputs);
+        Ok(tvec!(dispatch_datum!(Gather::eval_t(data.datum_type())(
+            &self.gather, data, &self.indices
+        ))?))
+    }
+}
+
+impl InferenceRulesOp for GatherUnary {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.given(&inputs[0].shape, move |s, ishape| {
+            let axis = self.gather.resolved_axis(ishape.len())?;
+            let mut shape: TVec<TDim> = ishape[..axis].into();
+            shape.extend(self.indices.shape().iter().map(|&d| d.to_dim()));
+            shape.extend(ishape[axis + 1..].iter().cloned());
+            s.equals(&outputs[0].shape, ShapeFact::from(shape))
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
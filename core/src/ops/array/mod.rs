@@ -59,16 +59,16 @@ mod split;
 mod squeeze;
 
 pub use self::add_dims::AddDims;
-pub use self::broadcast::MultiBroadcastTo;
+pub use self::broadcast::{MultiBroadcastTo, MultiBroadcastToUnary};
 pub use self::concat::Concat;
 pub use self::constant_like::ConstantLike;
 pub use self::constant_like::EyeLike;
 pub use self::constant_of_shape::ConstantOfShape;
 pub use self::flatten::Flatten;
-pub use self::gather::Gather;
+pub use self::gather::{Gather, GatherUnary};
 pub use self::pad::{Pad, PadMode};
 pub use self::permute_axes::PermuteAxes;
-pub use self::reshape::Reshape;
+pub use self::reshape::{Reshape, ReshapeUnary, SymbolicReshape};
 pub use self::rm_dims::RmDims;
 pub use self::shape::Shape;
 pub use self::size::Size;
@@ -90,6 +90,20 @@ impl Op for Pad {
         "Pad".into()
     }
 
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        let mut pads = self.pads.clone();
+        for (ix, &ax) in axes.iter().enumerate() {
+            pads[ax] = self.pads[ix];
+        }
+        Ok(Some((Box::new(Pad::new(pads, self.mode.clone())), Some(axes.to_vec()))))
+    }
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
@@ -1,11 +1,25 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct PermuteAxes {
     pub axes: Option<Vec<usize>>,
 }
 
 impl PermuteAxes {
+    /// The permutation undoing `axes`.
+    pub fn inverse(axes: &[usize]) -> Vec<usize> {
+        let mut inverse = vec![0; axes.len()];
+        for (ix, &ax) in axes.iter().enumerate() {
+            inverse[ax] = ix;
+        }
+        inverse
+    }
+
+    /// The actual axes permutation for an input of rank `rank`.
+    pub fn axes(&self, rank: usize) -> Vec<usize> {
+        self.axes.clone().unwrap_or_else(|| (0..rank).rev().collect())
+    }
+
     fn compute_shape<D: DimLike>(&self, input: &[D]) -> TVec<D> {
         if let Some(ref axes) = self.axes {
             let mut new_shape = tvec![D::zero(); input.len()];
@@ -35,6 +49,8 @@ impl Op for PermuteAxes {
         "PermuteAxes".into()
     }
 
+    impl_op_same_as!();
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
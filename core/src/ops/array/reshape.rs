@@ -1,67 +1,129 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new, Default)]
+#[derive(Debug, Clone, PartialEq, new, Default)]
 pub struct Reshape {}
 
-impl Reshape {
-    fn compute_shape<D: DimLike>(&self, input: &[D], shape: &[isize]) -> TractResult<Vec<D>> {
-        if shape.iter().all(|d| *d > 0) {
-            return Ok(shape.iter().map(|&d| D::from(d as usize)).collect());
+/// Splits dims into the (at most one) symbolic dimension and the product
+/// of the others.
+fn split_dims<'a, D: DimLike>(dims: impl Iterator<Item = &'a D>) -> TractResult<(Option<D>, usize)> {
+    let mut symbolic = None;
+    let mut product = 1;
+    for d in dims {
+        match d.to_integer() {
+            Ok(i) => product *= i as usize,
+            Err(_) if symbolic.is_none() => symbolic = Some(*d),
+            Err(_) => bail!("Reshape can not handle more than one symbolic dimension"),
         }
-        let mut result: Vec<D> = shape
-            .iter()
-            .zip(input.iter())
-            .map(|(&shape, &input)| {
-                if shape > 0 {
-                    D::from(shape as usize)
-                } else {
-                    input
-                }
-            })
-            .collect();
-        if let Some(minus_one) = shape.iter().position(|d| *d == -1) {
-            let prod_input: usize = input
-                .iter()
-                .try_fold(1, |acc, dim| dim.to_integer().map(|a| a as usize * acc))?;
-            let prod_shape: usize = result
-                .iter()
-                .enumerate()
-                .filter(|(ix, _)| *ix != minus_one)
-                .try_fold(1, |acc, (_, dim)| {
-                    dim.to_integer().map(|a| a as usize * acc)
-                })?;
-            result[minus_one] = D::from(prod_input / prod_shape);
-        }
-        Ok(result)
     }
+    Ok((symbolic, product))
+}
 
-    /// Evaluates the operation given the input tensors.
-    fn eval_t<T: Datum>(
-        &self,
-        input: SharedTensor,
-        shape: &[usize],
-    ) -> TractResult<TVec<SharedTensor>> {
-        Ok(tvec![input.to_array::<T>()?.into_shape(shape)?.into()])
+fn compute_shape<D: DimLike>(input: &[D], shape: &[isize]) -> TractResult<Vec<D>> {
+    if shape.iter().all(|d| *d > 0) {
+        return Ok(shape.iter().map(|&d| D::from(d as usize)).collect());
+    }
+    let mut result: Vec<D> = shape
+        .iter()
+        .enumerate()
+        .map(|(ix, &shape)| {
+            if shape > 0 {
+                Ok(D::from(shape as usize))
+            } else if shape == 0 {
+                input.get(ix).cloned().ok_or_else(|| "Reshape 0 refers to a missing axis".into())
+            } else {
+                Ok(D::one())
+            }
+        })
+        .collect::<TractResult<_>>()?;
+    if let Some(minus_one) = shape.iter().position(|d| *d == -1) {
+        result[minus_one] = infer_minus_one(input, &result, minus_one)?;
+    }
+    Ok(result)
+}
+
+/// Same as `compute_shape`, but the specification may contain symbolic
+/// dimensions, which are kept as they are.
+fn compute_symbolic_shape(input: &[TDim], shape: &[TDim]) -> TractResult<Vec<TDim>> {
+    if let Ok(spec) = shape.iter().map(|d| d.to_integer()).collect::<TractResult<Vec<i32>>>() {
+        return compute_shape(input, &*spec.iter().map(|&d| d as isize).collect::<Vec<_>>());
     }
+    let mut result: Vec<TDim> = shape
+        .iter()
+        .enumerate()
+        .map(|(ix, d)| match d.to_integer() {
+            Ok(0) => {
+                input.get(ix).cloned().ok_or_else(|| "Reshape 0 refers to a missing axis".into())
+            }
+            Ok(i) if i < 0 => Ok(1.to_dim()),
+            _ => Ok(*d),
+        })
+        .collect::<TractResult<_>>()?;
+    if let Some(minus_one) = shape.iter().position(|d| d.to_integer().ok() == Some(-1)) {
+        result[minus_one] = infer_minus_one(input, &result, minus_one)?;
+    }
+    Ok(result)
+}
+
+/// Computes the dimension at `minus_one` from the input dimensions and the
+/// other output ones.
+fn infer_minus_one<D: DimLike>(input: &[D], result: &[D], minus_one: usize) -> TractResult<D> {
+    let (input_sym, prod_input) = split_dims(input.iter())?;
+    let (shape_sym, prod_shape) =
+        split_dims(result.iter().enumerate().filter(|(ix, _)| *ix != minus_one).map(|(_, d)| d))?;
+    if prod_shape == 0 || prod_input % prod_shape != 0 {
+        bail!("Can not reshape {:?} to {:?}", input, result);
+    }
+    Ok(match (input_sym, shape_sym) {
+        (None, None) => D::from(prod_input / prod_shape),
+        (Some(sym), None) => sym * (prod_input / prod_shape),
+        (Some(a), Some(b)) if a == b => D::from(prod_input / prod_shape),
+        _ => bail!("Can not reshape {:?} to {:?}", input, result),
+    })
+}
+
+/// Evaluates the operation given the input tensors.
+fn eval_t<T: Datum>(input: SharedTensor, shape: &[usize]) -> TractResult<TVec<SharedTensor>> {
+    Ok(tvec![input.to_array::<T>()?.into_shape(shape)?.into()])
+}
+
+fn shape_spec(shape: &Tensor) -> TractResult<Vec<isize>> {
+    Ok(shape.cast_to::<i64>()?.to_array_view::<i64>()?.iter().map(|&i| i as isize).collect())
+}
+
+fn symbolic_shape_spec(shape: &Tensor) -> TractResult<Vec<TDim>> {
+    Ok(shape.cast_to::<TDim>()?.as_slice::<TDim>()?.to_vec())
 }
 
 impl Op for Reshape {
     fn name(&self) -> Cow<str> {
         "Reshape".into()
     }
+
+    impl_op_same_as!();
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if let Some(ref shape) = model.fact(node.inputs[1])?.konst {
+            // symbolic specifications are folded by PropConst
+            if symbolic_shape_spec(shape)?.iter().any(|d| d.is_stream()) {
+                return Ok(None);
+            }
+            let op = ReshapeUnary::new(shape_spec(shape)?);
+            return Ok(Some(TypedModelPatch::single_unary_op(model, node, op)?));
+        }
+        Ok(None)
+    }
 }
 
 impl StatelessOp for Reshape {
     fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
         let (input, shape) = args_2!(inputs);
-        let shape: Vec<isize> = shape
-            .cast_to::<i64>()?
-            .to_array_view::<i64>()?
-            .iter()
-            .map(|&i| i as isize)
-            .collect();
-        let oshape = self.compute_shape(input.shape(), &shape)?;
-        dispatch_datum!(Self::eval_t(input.datum_type())(self, input, &oshape))
+        let shape = shape_spec(&shape)?;
+        let oshape = compute_shape(input.shape(), &shape)?;
+        dispatch_datum!(self::eval_t(input.datum_type())(input, &oshape))
     }
 }
 
@@ -73,19 +135,150 @@ impl InferenceRulesOp for Reshape {
         outputs: &'p [TensorProxy],
     ) -> InferenceResult {
         s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
-        s.given_2(
-            &inputs[0].shape,
-            &inputs[1].value,
-            move |s, ishape, shape| {
-                let shape: Vec<isize> = shape
-                    .cast_to::<i64>()?
-                    .to_array_view::<i64>()?
-                    .iter()
-                    .map(|&i| i as isize)
-                    .collect();
-                let shape = self.compute_shape(&ishape, &shape)?;
-                s.equals(&outputs[0].shape, ShapeFact::from(shape))
-            },
-        )
+        s.given_2(&inputs[0].shape, &inputs[1].value, move |s, ishape, shape| {
+            let shape = compute_symbolic_shape(&ishape, &symbolic_shape_spec(&shape)?)?;
+            s.equals(&outputs[0].shape, ShapeFact::from(shape))
+        })
+    }
+}
+
+/// Reshape with a constant shape specification, as produced by decluttering
+/// a Reshape.
+#[derive(Debug, Clone, PartialEq, new)]
+pub struct ReshapeUnary {
+    shape: Vec<isize>,
+}
+
+impl Op for ReshapeUnary {
+    fn name(&self) -> Cow<str> {
+        "ReshapeUnary".into()
+    }
+
+    impl_op_same_as!();
+
+    fn pulsify(
+        &self,
+        source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let mut fact = target.fact(input)?.clone();
+        let input_stream = source.fact(node.inputs[0])?.shape.stream_info;
+        let output_shape = &node.outputs[0].fact.shape;
+        let preserved = match (input_stream, output_shape.stream_info) {
+            (Some(i), Some(o)) => {
+                i.len == o.len
+                    && fact.shape[..fact.axis].iter().product::<usize>()
+                        == (0..o.axis).map(|ax| output_shape.dim(ax).to_integer()).product::<TractResult<i32>>()? as usize
+            }
+            _ => false,
+        };
+        if !preserved {
+            bail!("Can not pulsify a Reshape that alters the streaming axis");
+        }
+        let stream_axis = output_shape.stream_info.unwrap().axis;
+        let shape = compute_shape(&fact.shape, &self.shape)?;
+        for (ix, &d) in shape.iter().enumerate() {
+            let expected = if ix == stream_axis { fact.pulse().to_dim() } else { output_shape.dim(ix) };
+            if d.to_dim() != expected {
+                bail!("Can not pulsify a Reshape that alters the streaming axis");
+            }
+        }
+        fact.shape = shape.into();
+        fact.axis = stream_axis;
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
+}
+
+impl StatelessOp for ReshapeUnary {
+    fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
+        let input = args_1!(inputs);
+        let oshape = compute_shape(input.shape(), &self.shape)?;
+        dispatch_datum!(self::eval_t(input.datum_type())(input, &oshape))
+    }
+}
+
+impl InferenceRulesOp for ReshapeUnary {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.given(&inputs[0].shape, move |s, ishape| {
+            let shape = compute_shape(&ishape, &self.shape)?;
+            s.equals(&outputs[0].shape, ShapeFact::from(shape))
+        })
+    }
+}
+
+/// Reshape to a shape depending on the streaming dimension, as produced by
+/// constant propagation on a Reshape which shape specification is symbolic.
+///
+/// S is recovered from the actual input shape at evaluation time.
+#[derive(Debug, Clone, PartialEq, new)]
+pub struct SymbolicReshape {
+    input_shape: ShapeInfo,
+    shape: ShapeInfo,
+}
+
+impl Op for SymbolicReshape {
+    fn name(&self) -> Cow<str> {
+        "SymbolicReshape".into()
+    }
+
+    impl_op_same_as!();
+
+    fn pulsify(
+        &self,
+        source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let stream_axis = self.shape.stream_info.map(|info| info.axis);
+        let spec = self
+            .shape
+            .iter()
+            .enumerate()
+            .map(|(ix, d)| {
+                if Some(ix) == stream_axis {
+                    Ok(-1)
+                } else {
+                    Ok(d.to_integer()? as isize)
+                }
+            })
+            .collect::<TractResult<Vec<isize>>>()?;
+        ReshapeUnary::new(spec).pulsify(source, node, target, mapping)
+    }
+}
+
+impl StatelessOp for SymbolicReshape {
+    fn eval(&self, mut inputs: TVec<SharedTensor>) -> TractResult<TVec<SharedTensor>> {
+        let input = args_1!(inputs);
+        let s = self.input_shape.stream_value(input.shape())?;
+        let oshape = self.shape.eval(s)?;
+        dispatch_datum!(self::eval_t(input.datum_type())(input, &oshape))
+    }
+}
+
+impl InferenceRulesOp for SymbolicReshape {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&inputs[0].shape, self.input_shape.to_shape_fact())?;
+        s.equals(&outputs[0].shape, self.shape.to_shape_fact())
     }
 }
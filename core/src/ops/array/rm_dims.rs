@@ -1,6 +1,6 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct RmDims {
     pub axes: Vec<usize>,
 }
@@ -27,6 +27,8 @@ impl Op for RmDims {
         "RmDims".into()
     }
 
+    impl_op_same_as!();
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
@@ -3,7 +3,7 @@ use num_traits::AsPrimitive;
 
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Shape {
     dt: DatumType,
 }
@@ -23,6 +23,8 @@ impl Op for Shape {
     fn name(&self) -> Cow<str> {
         "Shape".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Shape {
@@ -3,7 +3,7 @@ use num_traits::AsPrimitive;
 
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Size {
     dt: DatumType,
 }
@@ -22,6 +22,8 @@ impl Op for Size {
     fn name(&self) -> Cow<str> {
         "Size".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Size {
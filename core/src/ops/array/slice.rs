@@ -2,7 +2,7 @@ use crate::ops::prelude::*;
 use crate::ops::identity::Identity;
 use ndarray::*;
 
-#[derive(Debug, Clone, new, Default)]
+#[derive(Debug, Clone, PartialEq, new, Default)]
 pub struct Slice {
     prune: Vec<(usize, usize)>,
 }
@@ -30,6 +30,8 @@ impl Op for Slice {
         "Slice".into()
     }
 
+    impl_op_same_as!();
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
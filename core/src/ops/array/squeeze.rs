@@ -2,7 +2,7 @@ use crate::ops::prelude::*;
 
 use super::RmDims;
 
-#[derive(Debug, Clone, new, Default)]
+#[derive(Debug, Clone, PartialEq, new, Default)]
 pub struct Squeeze {
     axes: Option<Vec<usize>>,
 }
@@ -34,6 +34,8 @@ impl Op for Squeeze {
         "Squeeze".into()
     }
 
+    impl_op_same_as!();
+
     fn declutter(
         &self,
         model: &TypedModel,
@@ -42,7 +44,14 @@ impl Op for Squeeze {
         if let Some(dims) = &self.axes {
             return Ok(Some(TypedModelPatch::single_unary_op(model, node, RmDims::new(dims.clone()))?));
         }
-        Ok(None)
+        let dims: Vec<usize> = model.node_input_facts(node.id)?[0]
+            .shape
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.is_one())
+            .map(|(ix, _)| ix)
+            .collect();
+        Ok(Some(TypedModelPatch::single_unary_op(model, node, RmDims::new(dims))?))
     }
 }
 
@@ -1,6 +1,6 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Cast {
     to: DatumType,
 }
@@ -16,6 +16,8 @@ impl Op for Cast {
     fn name(&self) -> Cow<str> {
         "Cast".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Cast {
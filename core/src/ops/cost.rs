@@ -0,0 +1,100 @@
+//! Estimates of the work done by ops, for profiling.
+use crate::analyser::types::{Fact, TensorFact};
+use crate::tensor::Tensor;
+
+/// Estimated work of one evaluation of an op: arithmetic operations, and
+/// bytes read from the inputs and weights plus bytes written to the outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cost {
+    pub flops: u64,
+    pub bytes: u64,
+}
+
+impl Cost {
+    /// `flops` operations, each input and output being moved once.
+    ///
+    /// None if the type or shape of any of them is not known.
+    pub fn new(flops: u64, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> Option<Cost> {
+        let mut bytes = 0;
+        for fact in inputs.iter().chain(outputs.iter()) {
+            bytes += fact_bytes(fact)?;
+        }
+        Some(Cost { flops, bytes })
+    }
+
+    /// `per_element` operations for each element of the first output.
+    pub fn element_wise(
+        per_element: u64,
+        inputs: &[&TensorFact],
+        outputs: &[&TensorFact],
+    ) -> Option<Cost> {
+        let len = fact_len(outputs.get(0)?)?;
+        Cost::new(per_element * len, inputs, outputs)
+    }
+
+    /// Adds the reading of weights embedded in the op.
+    pub fn with_weights(self, weights: &Tensor) -> Cost {
+        let len = weights.shape().iter().product::<usize>();
+        let bytes = (len * weights.datum_type().size_of()) as u64;
+        Cost { bytes: self.bytes + bytes, ..self }
+    }
+
+    /// Operations per byte moved, the x axis of a roofline plot.
+    pub fn intensity(&self) -> f64 {
+        self.flops as f64 / self.bytes.max(1) as f64
+    }
+}
+
+impl std::ops::Add for Cost {
+    type Output = Cost;
+    fn add(self, other: Cost) -> Cost {
+        Cost { flops: self.flops + other.flops, bytes: self.bytes + other.bytes }
+    }
+}
+
+/// The dimensions of a fact, if they are all known and finite.
+pub fn fact_shape(fact: &TensorFact) -> Option<Vec<usize>> {
+    fact.shape.as_concrete_finite().ok()?.map(|s| s.into_iter().collect())
+}
+
+/// The number of elements of a fact, if its shape is known and finite.
+pub fn fact_len(fact: &TensorFact) -> Option<u64> {
+    fact_shape(fact).map(|s| s.iter().product::<usize>() as u64)
+}
+
+fn fact_bytes(fact: &TensorFact) -> Option<u64> {
+    let dt = fact.datum_type.concretize()?;
+    Some(fact_len(fact)? * dt.size_of() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::prelude::*;
+
+    fn fact(shape: &[usize]) -> TensorFact {
+        TensorFact::dt_shape(DatumType::F32, shape)
+    }
+
+    #[test]
+    fn element_wise() {
+        let (a, b) = (fact(&[2, 3]), fact(&[2, 3]));
+        let cost = Cost::element_wise(1, &[&a, &b], &[&a]).unwrap();
+        assert_eq!(cost, Cost { flops: 6, bytes: 3 * 6 * 4 });
+    }
+
+    #[test]
+    fn unknown_shape() {
+        let a = TensorFact::dt(DatumType::F32);
+        assert_eq!(Cost::element_wise(1, &[&a], &[&a]), None);
+    }
+
+    #[test]
+    fn mat_mul() {
+        let (a, b, c) = (fact(&[2, 3]), fact(&[3, 4]), fact(&[2, 4]));
+        let op = crate::ops::math::MatMul::default();
+        let cost = op.cost(&[&a, &b], &[&c]).unwrap().unwrap();
+        assert_eq!(cost.flops, 2 * 2 * 3 * 4);
+        assert_eq!(cost.bytes, (6 + 12 + 8) * 4);
+    }
+}
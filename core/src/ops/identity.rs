@@ -1,12 +1,14 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Identity;
 
 impl Op for Identity {
     fn name(&self) -> Cow<str> {
         "Identity".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Identity {
@@ -1,6 +1,6 @@
 use crate::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Const {
     value: SharedTensor,
 }
@@ -17,6 +17,8 @@ impl Op for Const {
     fn name(&self) -> Cow<str> {
         "Const".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Const {
@@ -9,7 +9,7 @@ macro_rules! element_map {
         #[allow(unused_imports)]
         use $crate::ops::prelude::*;
 
-        #[derive(Debug, Clone, new, Default)]
+        #[derive(Debug, Clone, PartialEq, new, Default)]
         pub struct $Name(TypeFact);
 
         impl StatelessOp for $Name {
@@ -29,6 +29,19 @@ macro_rules! element_map {
                 stringify!($Name).into()
             }
 
+            impl_op_same_as!();
+            impl_op_element_wise_cost!(1);
+
+            fn absorb_permute_axes(
+                &self,
+                _model: &TypedModel,
+                _node: &TypedNode,
+                _slot: usize,
+                axes: &[usize],
+            ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+                Ok(Some((Box::new(self.clone()), Some(axes.to_vec()))))
+            }
+
             fn pulsify(
                 &self,
                 _source: &NormalizedModel,
@@ -72,9 +85,9 @@ macro_rules! element_map_with_params {
         #[allow(unused_imports)]
         use $crate::ops::prelude::*;
 
-        #[derive(Debug, Clone, new, Default)]
+        #[derive(Debug, Clone, PartialEq, new, Default)]
         pub struct $Name {
-            $( $pname: $pty ),*
+            $( pub $pname: $pty ),*
         }
 
         impl StatelessOp for $Name {
@@ -95,6 +108,19 @@ macro_rules! element_map_with_params {
             fn name(&self) -> Cow<str> {
                 stringify!($Name).into()
             }
+
+            impl_op_same_as!();
+            impl_op_element_wise_cost!(1);
+
+            fn absorb_permute_axes(
+                &self,
+                _model: &TypedModel,
+                _node: &TypedNode,
+                _slot: usize,
+                axes: &[usize],
+            ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+                Ok(Some((Box::new(self.clone()), Some(axes.to_vec()))))
+            }
         }
 
         impl InferenceRulesOp for $Name {
@@ -157,7 +183,7 @@ macro_rules! element_bin {
                 bail!("{} not covering {:?}", stringify!($name), dt)
             }
 
-            #[derive(Debug, Clone, Default, new)]
+            #[derive(Debug, Clone, PartialEq, Default, new)]
             pub struct Bin(TypeFact);
 
             impl StatelessOp for Bin {
@@ -173,6 +199,9 @@ macro_rules! element_bin {
                     concat!(stringify!($name), "::Binary").into()
                 }
 
+                impl_op_same_as!();
+                impl_op_element_wise_cost!(1);
+
                 fn declutter(&self, model: &$crate::model::TypedModel, node: &$crate::model::TypedNode)
                  -> TractResult<Option<TypedModelPatch>> {
                      let inputs = model.node_input_facts(node.id)?;
@@ -262,10 +291,10 @@ macro_rules! element_bin {
                 }
             }
 
-            #[derive(Debug, Clone, new)]
+            #[derive(Debug, Clone, PartialEq, new)]
             pub struct UnaryA {
-                dt: TypeFact,
-                b: SharedTensor,
+                pub dt: TypeFact,
+                pub b: SharedTensor,
             }
 
             impl StatelessOp for UnaryA {
@@ -280,6 +309,29 @@ macro_rules! element_bin {
                     concat!(stringify!($name), "::UnaryA").into()
                 }
 
+                impl_op_same_as!();
+                impl_op_element_wise_cost!(1);
+
+                fn absorb_permute_axes(
+                    &self,
+                    _model: &$crate::model::TypedModel,
+                    _node: &$crate::model::TypedNode,
+                    _slot: usize,
+                    axes: &[usize],
+                ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+                    let rank = axes.len();
+                    if self.b.shape().len() > rank {
+                        return Ok(None);
+                    }
+                    // align b on the input rank, then undo the permutation on it
+                    let mut shape = vec![1; rank - self.b.shape().len()];
+                    shape.extend(self.b.shape());
+                    let b = self.b.as_tensor().clone().into_shape(&shape)?;
+                    let inverse = $crate::ops::array::PermuteAxes::inverse(axes);
+                    let b = b.permute_axes(&inverse)?.into();
+                    Ok(Some((Box::new(UnaryA { dt: self.dt, b }), Some(axes.to_vec()))))
+                }
+
                 fn pulsify(
                     &self,
                     _source: &NormalizedModel,
@@ -340,7 +392,7 @@ macro_rules! element_nary {
         #[allow(unused_imports)]
         use $crate::ops::prelude::*;
 
-        #[derive(Debug, Clone, new, Default)]
+        #[derive(Debug, Clone, PartialEq, new, Default)]
         pub struct $Name {
             datum: TypeFact,
             n: Option<usize>,
@@ -351,6 +403,14 @@ macro_rules! element_nary {
                 stringify!($Name).into()
             }
 
+            impl_op_same_as!();
+
+            fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact])
+                -> TractResult<Option<$crate::ops::cost::Cost>> {
+                let per_element = inputs.len().max(2) as u64 - 1;
+                Ok($crate::ops::cost::Cost::element_wise(per_element, inputs, outputs))
+            }
+
             fn pulsify(
                 &self,
                 _source: &NormalizedModel,
@@ -639,6 +699,19 @@ macro_rules! impl_op_same_as {
     }
 }
 
+#[macro_export]
+macro_rules! impl_op_element_wise_cost {
+    ($per_element:expr) => {
+        fn cost(
+            &self,
+            inputs: &[&TensorFact],
+            outputs: &[&TensorFact],
+        ) -> TractResult<Option<$crate::ops::cost::Cost>> {
+            Ok($crate::ops::cost::Cost::element_wise($per_element, inputs, outputs))
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! assert_close {
     ($left:expr, $right:expr) => ({
@@ -58,11 +58,30 @@ impl Gemm {
     }
 }
 
+/// One multiply and one add for each of the `k` terms of each output value.
+fn gemm_cost(
+    a_shape: &[usize],
+    trans_a: bool,
+    inputs: &[&TensorFact],
+    outputs: &[&TensorFact],
+) -> Option<Cost> {
+    let k = *a_shape.get(if trans_a { 0 } else { 1 })? as u64;
+    Cost::element_wise(2 * k, inputs, outputs)
+}
+
 impl Op for Gemm {
     fn name(&self) -> Cow<str> {
         "Gemm".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let a_shape = match crate::ops::cost::fact_shape(inputs[0]) {
+            Some(shape) => shape,
+            None => return Ok(None),
+        };
+        Ok(gemm_cost(&a_shape, self.trans_a, inputs, outputs))
+    }
+
     fn declutter(
         &self,
         model: &TypedModel,
@@ -218,6 +237,40 @@ impl GemmUnaryA {
         ::ndarray::linalg::general_mat_mul(self.alpha.as_(), &at, &bt, self.beta.as_(), &mut c);
         Ok(tvec!(c.into()))
     }
+
+    /// Rewrites as a MatMulUnaryA followed by a bias Add, so that codegen can
+    /// fuse the bias (and a trailing activation) into the matrix product.
+    fn to_mat_mul<T: Datum + Float>(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<TypedModelPatch>
+    where
+        f32: AsPrimitive<T>,
+    {
+        use crate::ops::math::mat_mul::MatMulUnaryA;
+        use crate::ops::math::Add;
+        let alpha: T = self.alpha.as_();
+        let b = self.b.to_array_view::<T>()?.into_dimensionality::<Ix2>()?;
+        let bt = if self.trans_b { b.t() } else { b };
+        let b = Array2::from_shape_fn(bt.dim(), |ix| bt[ix] * alpha);
+        let mut patch = TypedModelPatch::default();
+        patch.tap_model(model, node.inputs[0])?;
+        let fact = node.outputs[0].fact.clone();
+        let mut wire = patch.chain(
+            format!("{}-matmul", node.name),
+            MatMulUnaryA::new(b.into()),
+            tvec!(fact.clone()),
+        )?;
+        if self.beta != 0.0 {
+            let beta: T = self.beta.as_();
+            let c = self.c.to_array_view::<T>()?.mapv(|x| x * beta);
+            let add = Add::UnaryA::new(T::datum_type().into(), Tensor::from(c).into());
+            wire = patch.chain(format!("{}-bias", node.name), add, tvec!(fact))?;
+        }
+        patch.shunt_outside(OutletId::new(node.id, 0), OutletId::new(wire, 0))?;
+        Ok(patch)
+    }
 }
 
 impl Op for GemmUnaryA {
@@ -225,6 +278,27 @@ impl Op for GemmUnaryA {
         "GemmUnaryA".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let a_shape = match crate::ops::cost::fact_shape(inputs[0]) {
+            Some(shape) => shape,
+            None => return Ok(None),
+        };
+        let cost = gemm_cost(&a_shape, self.trans_a, inputs, outputs);
+        Ok(cost.map(|c| c.with_weights(&self.b).with_weights(&self.c)))
+    }
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        let dt = node.outputs[0].fact.datum_type;
+        if self.trans_a || (dt != DatumType::F32 && dt != DatumType::F64) {
+            return Ok(None);
+        }
+        Ok(Some(dispatch_floatlike!(Self::to_mat_mul(dt)(self, model, node))?))
+    }
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
@@ -314,6 +388,11 @@ impl Op for GemmUnaryB {
     fn name(&self) -> Cow<str> {
         "GemmUnaryB".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let cost = gemm_cost(self.a.shape(), self.trans_a, inputs, outputs);
+        Ok(cost.map(|c| c.with_weights(&self.a).with_weights(&self.c)))
+    }
 }
 
 impl StatelessOp for GemmUnaryB {
@@ -4,6 +4,8 @@ use std::ops::{Add, Mul};
 use crate::ops::prelude::*;
 use ndarray::*;
 
+use tract_linalg::FusedSpec;
+
 fn eval_t<T: Copy + Datum + LinalgScalar>(a: &Tensor, b: &Tensor) -> TractResult<Tensor> {
     let a = a.to_array_view::<T>()?;
     let b = b.to_array_view::<T>()?;
@@ -155,6 +157,16 @@ impl<T: Copy + Datum + Add + Mul + Zero> Geo<T> {
     }
 }
 
+/// One multiply and one add for each of the `k` terms of each output value.
+fn mat_mul_cost(k: usize, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> Option<Cost> {
+    Cost::element_wise(2 * k as u64, inputs, outputs)
+}
+
+/// The `k` dimension of the left operand.
+fn inner_dim(a: &TensorFact) -> Option<usize> {
+    crate::ops::cost::fact_shape(a)?.last().cloned()
+}
+
 #[derive(Debug, Clone, new, Default)]
 pub struct MatMul {}
 
@@ -162,6 +174,10 @@ impl Op for MatMul {
     fn name(&self) -> Cow<str> {
         "MatMul".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(inner_dim(inputs[0]).and_then(|k| mat_mul_cost(k, inputs, outputs)))
+    }
 }
 
 impl StatelessOp for MatMul {
@@ -220,6 +236,11 @@ impl Op for MatMulUnaryA {
         "MatMulUnaryA".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let cost = inner_dim(inputs[0]).and_then(|k| mat_mul_cost(k, inputs, outputs));
+        Ok(cost.map(|c| c.with_weights(&self.b)))
+    }
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
@@ -292,6 +313,7 @@ pub struct MatMulUnaryImplASimpleB<T: Copy + Datum + Add + Mul + Zero> {
     packed_b: Tensor,
     a_shape: TVec<usize>,
     c_shape: TVec<usize>,
+    pub fused_ops: Vec<FusedSpec<T>>,
 }
 
 impl<T: Copy + Datum + Add + Mul + Zero> MatMulUnaryImplASimpleB<T> {
@@ -308,7 +330,13 @@ impl<T: Copy + Datum + Add + Mul + Zero> MatMulUnaryImplASimpleB<T> {
             Tensor::uninitialized_aligned::<T>(&[packed_b_len], geo.mm.packed_b_alignment())?
         };
         geo.mm.pack_b(packed_b.as_ptr_mut()?, b.as_ptr(), b.strides()[0], b.strides()[1]);
-        Ok(MatMulUnaryImplASimpleB { geo, packed_b, c_shape, a_shape: a_shape.into() })
+        Ok(MatMulUnaryImplASimpleB {
+            geo,
+            packed_b,
+            c_shape,
+            a_shape: a_shape.into(),
+            fused_ops: vec![],
+        })
     }
 }
 
@@ -317,6 +345,10 @@ impl<T: Copy + Datum + Add + Mul + Zero> Op for MatMulUnaryImplASimpleB<T> {
         "MatMulUnaryImplASimpleB".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(mat_mul_cost(self.geo.k, inputs, outputs).map(|c| c.with_weights(&self.packed_b)))
+    }
+
     fn info(&self) -> TractResult<Option<String>> {
         Ok(Some(format!("{:?}", self.geo.mm)))
     }
@@ -337,12 +369,13 @@ impl<T: Copy + Datum + Add + Mul + Zero> StatelessOp for MatMulUnaryImplASimpleB
         };
 
         self.geo.mm.pack_a(pa.as_ptr_mut()?, a.as_ptr(), self.geo.k as isize, 1);
-        self.geo.mm.mat_mul_prepacked(
+        self.geo.mm.mat_mul_prepacked_fused(
             pa.as_ptr()?,
             self.packed_b.as_ptr()?,
             c.as_mut_ptr(),
             self.geo.n as isize,
             1,
+            &self.fused_ops,
         );
 
         Ok(tvec!(c.into()))
@@ -370,6 +403,7 @@ impl<T: Copy + Datum + Add + Mul + Zero> InferenceRulesOp for MatMulUnaryImplASi
 pub struct MatMulUnaryImplA<T: Copy + Datum + Add + Mul + Zero> {
     geo: Geo<T>,
     packed_bs: Tensor,
+    pub fused_ops: Vec<FusedSpec<T>>,
 }
 
 impl<T: Copy + Datum + Add + Mul + Zero> MatMulUnaryImplA<T> {
@@ -397,7 +431,7 @@ impl<T: Copy + Datum + Add + Mul + Zero> MatMulUnaryImplA<T> {
                 );
             }
         }
-        Ok(MatMulUnaryImplA { geo, packed_bs })
+        Ok(MatMulUnaryImplA { geo, packed_bs, fused_ops: vec![] })
     }
 }
 
@@ -406,6 +440,10 @@ impl<T: Copy + Datum + Add + Mul + Zero> Op for MatMulUnaryImplA<T> {
         "MatMulUnaryImplA".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(mat_mul_cost(self.geo.k, inputs, outputs).map(|c| c.with_weights(&self.packed_bs)))
+    }
+
     fn info(&self) -> TractResult<Option<String>> {
         Ok(Some(format!("{:?}", self.geo.mm)))
     }
@@ -443,12 +481,13 @@ impl<T: Copy + Datum + Add + Mul + Zero> StatelessOp for MatMulUnaryImplA<T> {
                 a.strides()[prefix.ndim()],
                 a.strides()[prefix.ndim() + 1],
             );
-            self.geo.mm.mat_mul_prepacked(
+            self.geo.mm.mat_mul_prepacked_fused(
                 pa.as_ptr_mut()?,
                 b.as_ptr(),
                 c.as_mut_ptr(),
                 c.strides()[prefix.ndim()],
                 c.strides()[prefix.ndim() + 1],
+                &self.fused_ops,
             );
         }
         Ok(tvec!(c.into()))
@@ -481,6 +520,11 @@ impl Op for MatMulUnaryB {
     fn name(&self) -> Cow<str> {
         "MatMulUnaryB".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let k = *self.a.shape().last().unwrap_or(&1);
+        Ok(mat_mul_cost(k, inputs, outputs).map(|c| c.with_weights(&self.a)))
+    }
 }
 
 impl StatelessOp for MatMulUnaryB {
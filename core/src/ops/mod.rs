@@ -10,6 +10,7 @@ mod macros;
 
 pub mod array;
 pub mod cast;
+pub mod cost;
 pub mod identity;
 #[cfg(features = "image_ops")]
 pub mod image;
@@ -21,6 +22,7 @@ pub mod source;
 pub mod unimpl;
 
 pub mod prelude {
+    pub use super::cost::Cost;
     pub use super::{InferenceOp, Op, OpState, StatefullOp, StatelessOp};
     pub use crate::analyser::rules::expr::{IntoExp, ToDimExp};
     pub use crate::analyser::rules::{InferenceResult, InferenceRulesOp, Solver, TensorProxy};
@@ -154,6 +156,20 @@ pub trait Op:
         Ok(None)
     }
 
+    /// Called by the layout pass when input `slot` of `node` is produced by a
+    /// `PermuteAxes` with `axes`. Returns an op doing the same work on the
+    /// unpermuted input, and the permutation to apply to its output to get
+    /// the original output back (None if the output is left unchanged).
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        _axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        Ok(None)
+    }
+
     fn rounding_errors(&self) -> bool {
         false
     }
@@ -165,6 +181,13 @@ pub trait Op:
     fn info(&self) -> TractResult<Option<String>> {
         Ok(None)
     }
+
+    /// Estimates the work of one evaluation, given the input and output
+    /// facts. Returns None for ops without an estimate, or if the facts are
+    /// not precise enough.
+    fn cost(&self, _inputs: &[&TensorFact], _outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(None)
+    }
 }
 
 pub trait InferenceOp {
@@ -173,6 +196,16 @@ pub trait InferenceOp {
         inputs: TVec<&TensorFact>,
         outputs: TVec<&TensorFact>,
     ) -> TractResult<(TVec<TensorFact>, TVec<TensorFact>)>;
+
+    /// Runs the inference rules with a solver in diagnostic mode, and
+    /// reports the rule that failed, if any.
+    fn diagnose_facts(
+        &self,
+        _inputs: TVec<&TensorFact>,
+        _outputs: TVec<&TensorFact>,
+    ) -> Option<crate::analyser::rules::RuleFailure> {
+        None
+    }
 }
 
 clone_trait_object!(Op);
@@ -31,6 +31,31 @@ impl Op for ArgMaxMin {
     fn name(&self) -> Cow<str> {
         "ArgMaxMin".into()
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let mut fact = target.fact(input)?.clone();
+        if fact.axis == self.axis {
+            bail!("Can not pulsify ArgMaxMin along the streaming axis");
+        }
+        fact.dt = DatumType::I64;
+        if self.keepdims {
+            fact.shape[self.axis] = 1;
+        } else {
+            fact.shape.remove(self.axis);
+            if fact.axis > self.axis {
+                fact.axis -= 1;
+            }
+        }
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
 }
 
 impl StatelessOp for ArgMaxMin {
@@ -42,6 +42,25 @@ impl Op for AvgPool {
         "AvgPool".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let kernel_len = self.kernel_shape.iter().product::<usize>();
+        Ok(Cost::element_wise(kernel_len as u64, inputs, outputs))
+    }
+
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        if let Some(data_fmt) = self.data_fmt.permuted_from(axes) {
+            let op = AvgPool { data_fmt, ..self.clone() };
+            return Ok(Some((Box::new(op), Some(axes.to_vec()))));
+        }
+        Ok(None)
+    }
+
     fn codegen(&self, model: &TypedModel, node: &TypedNode) -> TractResult<Option<TypedModelPatch>> {
         let inputs = model.node_input_facts(node.id)?;
         if let Some(shape) = inputs[0].shape.as_finite() {
@@ -59,6 +78,26 @@ impl Op for AvgPool {
         }
         Ok(None)
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let (input, fact) = super::pools::pulsify_pooling(
+            self.data_fmt,
+            &*self.kernel_shape,
+            &self.padding,
+            self.strides.as_ref(),
+            node,
+            target,
+            mapping,
+        )?;
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
 }
 
 impl StatelessOp for AvgPool {
@@ -326,6 +365,11 @@ where
     fn name(&self) -> Cow<str> {
         format!("FixedAvgPool<{:?}>", T::datum_type()).into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let kernel_len = self.patch.kernel_spatial_shape.iter().product::<usize>();
+        Ok(Cost::element_wise(kernel_len as u64, inputs, outputs))
+    }
 }
 
 impl<T> StatelessOp for FixedAvgPool<T>
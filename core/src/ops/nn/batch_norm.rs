@@ -133,10 +133,10 @@ where
     T: Datum + ::num_traits::Float + ::num_traits::FromPrimitive + ::ndarray::ScalarOperand,
     f32: AsPrimitive<T>,
 {
-    c_axis: usize,
-    c_dim: usize,
-    slope: Array1<T>,
-    intercept: Array1<T>,
+    pub c_axis: usize,
+    pub c_dim: usize,
+    pub slope: Array1<T>,
+    pub intercept: Array1<T>,
 }
 
 impl<T> FixedBatchNorm<T>
@@ -180,6 +180,17 @@ where
         format!("FixedBatchNorm<{:?}>", T::datum_type()).into()
     }
 
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        let op = FixedBatchNorm { c_axis: axes[self.c_axis], ..self.clone() };
+        Ok(Some((Box::new(op), Some(axes.to_vec()))))
+    }
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
@@ -9,7 +9,7 @@ use ndarray::prelude::*;
 use crate::ops::nn::conv::KernelFormat;
 use crate::ops::nn::{DataFormat, Patch};
 
-use tract_linalg::MatMul;
+use tract_linalg::{FusedSpec, MatMul};
 
 /*
  * group=1, N=1         N>1             g>1
@@ -50,7 +50,9 @@ where
     pub kernel_fmt: KernelFormat,
     #[debug(skip)]
     pub packed_kernels: Vec<Tensor>,
-    pub bias: Option<ArrayD<T>>,
+    /// Bias and activation applied to each group output, one list per group.
+    #[debug(skip)]
+    pub fused_ops: Vec<Vec<FusedSpec<T>>>,
     pub group: usize,
     pub mm: Arc<MatMul<T>>,
 }
@@ -83,7 +85,7 @@ where
                         DataFormat::NHWC => (1, self.m as isize),
                         DataFormat::NCHW => (self.n as isize, 1),
                     };
-                    self.mm.mat_mul_prepacked(
+                    self.mm.mat_mul_prepacked_fused(
                         a.as_ptr()?,
                         packed_input
                             .as_ptr()
@@ -91,15 +93,12 @@ where
                         output_i_g,
                         rsc,
                         csc,
+                        &self.fused_ops[g],
                     );
                 }
             }
         }
 
-        if let Some(ref bias) = self.bias {
-            output += &bias;
-        }
-
         Ok(output)
     }
 }
@@ -112,6 +111,11 @@ where
         "ConvGemm".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let cost = Cost::element_wise(2 * self.k as u64, inputs, outputs);
+        Ok(cost.map(|c| self.packed_kernels.iter().fold(c, |c, k| c.with_weights(k))))
+    }
+
     fn info(&self) -> TractResult<Option<String>> {
         Ok(Some(format!("{:?}", self.mm)))
     }
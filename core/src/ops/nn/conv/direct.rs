@@ -1,5 +1,5 @@
 use ndarray::prelude::*;
-use tract_linalg::Conv;
+use tract_linalg::{Conv, FusedSpec};
 use crate::ops::prelude::*;
 
 #[derive(CustomDebug, Clone, new)]
@@ -9,6 +9,8 @@ pub struct Direct {
     output_shape: TVec<usize>,
     #[debug(skip)]
     packed_filters: Tensor,
+    #[debug(skip)]
+    pub fused_ops: Vec<FusedSpec<f32>>,
 }
 
 impl Op for Direct {
@@ -16,6 +18,11 @@ impl Op for Direct {
         "ConvDirect".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let cost = Cost::element_wise(2 * self.conv.k() as u64, inputs, outputs);
+        Ok(cost.map(|c| c.with_weights(&self.packed_filters)))
+    }
+
     fn info(&self) -> TractResult<Option<String>> {
         Ok(Some(format!("{:?}", self.conv)))
     }
@@ -30,12 +37,13 @@ impl StatelessOp for Direct {
             for n in 0..input.shape()[0] {
                 let input = input.slice_axis(Axis(0), (n..=n).into());
                 let mut output = output.slice_axis_mut(Axis(0), (n..=n).into());
-                self.conv.conv(
+                self.conv.conv_fused(
                     self.packed_filters.as_slice::<f32>()?.as_ptr(),
                     input.as_ptr(),
                     output.as_mut_ptr(),
                     self.conv.n() as isize,
                     1,
+                    &self.fused_ops,
                 );
             }
             Ok(tvec!(output.into()))
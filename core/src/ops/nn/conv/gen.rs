@@ -96,6 +96,40 @@ impl Op for Conv {
         "Conv".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let kernel = match crate::ops::cost::fact_shape(inputs[1]) {
+            Some(shape) => shape,
+            None => return Ok(None),
+        };
+        let channels_out = match self.kernel_fmt {
+            KernelFormat::OIHW => kernel[0],
+            KernelFormat::HWIO => kernel[kernel.len() - 1],
+        };
+        let k = kernel.iter().product::<usize>() / channels_out.max(1);
+        Ok(Cost::element_wise(2 * k as u64, inputs, outputs))
+    }
+
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        if slot == 0 {
+            if let Some(data_fmt) = self.data_fmt.permuted_from(axes) {
+                let op = Conv { data_fmt, ..self.clone() };
+                return Ok(Some((Box::new(op), Some(axes.to_vec()))));
+            }
+        } else if slot == 1 {
+            if let Some(kernel_fmt) = self.kernel_fmt.permuted_from(axes) {
+                let op = Conv { kernel_fmt, ..self.clone() };
+                return Ok(Some((Box::new(op), None)));
+            }
+        }
+        Ok(None)
+    }
+
     fn declutter(&self, model: &TypedModel, node: &TypedNode) -> TractResult<Option<TypedModelPatch>> {
         let inputs = model.node_input_facts(node.id)?;
         if let Some(op) = self.to_unary(inputs)? {
@@ -98,6 +98,12 @@ impl<T: Copy + Datum + Mul + Zero> Op for Im2Col<T> {
         "Im2col".into()
     }
 
+    /// A pure copy: no arithmetic, but it moves the input and a patch-sized
+    /// output.
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(Cost::new(0, inputs, outputs))
+    }
+
     impl_op_same_as!();
 
     fn info(&self) -> TractResult<Option<String>> {
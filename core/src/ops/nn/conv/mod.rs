@@ -4,6 +4,7 @@ mod gen;
 mod im2col;
 mod unary;
 
+pub use self::conv_gemm::ConvGemm;
 pub use self::direct::Direct;
 pub use self::gen::Conv;
 pub use self::unary::ConvUnary;
@@ -27,4 +28,24 @@ impl KernelFormat {
             KernelFormat::HWIO => 0,
         }
     }
+
+    /// If `axes` turns a kernel in the other format into this one, returns
+    /// the other format.
+    pub fn permuted_from(&self, axes: &[usize]) -> Option<KernelFormat> {
+        let rank = axes.len();
+        if rank < 3 {
+            return None;
+        }
+        let (other, expected): (KernelFormat, Vec<usize>) = match self {
+            KernelFormat::OIHW => {
+                (KernelFormat::HWIO, vec![rank - 1, rank - 2].into_iter().chain(0..rank - 2).collect())
+            }
+            KernelFormat::HWIO => (KernelFormat::OIHW, (2..rank).chain(vec![1, 0]).collect()),
+        };
+        if axes == &*expected {
+            Some(other)
+        } else {
+            None
+        }
+    }
 }
@@ -2,7 +2,6 @@ use ndarray::*;
 
 use crate::model::*;
 use crate::ops::prelude::*;
-use insideout::InsideOut;
 
 use super::conv_gemm::ConvGemm;
 use super::im2col::Im2Col;
@@ -12,7 +11,7 @@ use crate::ops::nn::{DataFormat, PaddingSpec, Patch};
 
 use std::sync::Arc;
 
-use tract_linalg::MatMul;
+use tract_linalg::{FusedSpec, MatMul};
 
 #[derive(Debug, Clone)]
 pub struct ConvUnary {
@@ -82,7 +81,7 @@ impl ConvUnary {
         }
     }
 
-    fn output_channels(&self) -> usize {
+    pub(crate) fn output_channels(&self) -> usize {
         match self.kernel_fmt {
             KernelFormat::OIHW => self.kernel.shape()[0],
             KernelFormat::HWIO => *self.kernel.shape().last().unwrap(),
@@ -93,7 +92,6 @@ impl ConvUnary {
         assert!(
             (0..input_full_shape.len() - 2).all(|ax| self.padding.valid_dim(ax))
                 && self.group == 1
-                && self.bias.is_none()
         );
 
         let patch = self.patch(input_full_shape);
@@ -138,11 +136,17 @@ impl ConvUnary {
             kernel.strides()[1],
         );
 
+        let mut fused_ops = vec![];
+        if let Some(ref bias) = self.bias {
+            fused_ops.push(FusedSpec::PerRowAdd(bias.as_slice::<f32>()?.to_vec()));
+        }
+
         Ok(super::Direct::new(
             conv,
             input_full_shape.into(),
             patch.output_full_shape(self.output_channels()),
             packed,
+            fused_ops,
         ))
     }
 
@@ -212,15 +216,18 @@ impl ConvUnary {
             packed_kernels.push(packed);
         }
 
-        let bias: Option<ArrayD<T>> = self
-            .bias
-            .as_ref()
-            .map(|bias| -> TractResult<_> {
-                let mut bias_shape: Vec<usize> = ::std::iter::repeat(1).take(shape.len()).collect();
-                bias_shape[1] = self.output_channels();
-                Ok(bias.to_array_view::<T>()?.into_shape(&*bias_shape)?.to_owned())
+        let fused_ops = (0..self.group)
+            .map(|g| -> TractResult<Vec<FusedSpec<T>>> {
+                let mut ops = vec![];
+                if let Some(ref bias) = self.bias {
+                    let bias = bias.as_slice::<T>()?;
+                    ops.push(FusedSpec::PerRowAdd(
+                        bias[co_per_group * g..co_per_group * (g + 1)].to_vec(),
+                    ));
+                }
+                Ok(ops)
             })
-            .inside_out()?;
+            .collect::<TractResult<Vec<_>>>()?;
 
         let im2col =
             Im2Col::new(patch.clone(), m, k, n, self.group, ci_per_group, packed_b_len, mm.clone());
@@ -234,7 +241,7 @@ impl ConvUnary {
             n,
             self.kernel_fmt,
             packed_kernels,
-            bias,
+            fused_ops,
             self.group,
             mm.clone(),
         );
@@ -312,6 +319,32 @@ impl Op for ConvUnary {
         "ConvUnary".into()
     }
 
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let k = self.kernel.shape().iter().product::<usize>() / self.output_channels().max(1);
+        let cost = Cost::element_wise(2 * k as u64, inputs, outputs);
+        Ok(cost.map(|c| c.with_weights(&self.kernel)))
+    }
+
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        if let Some(data_fmt) = self.data_fmt.permuted_from(axes) {
+            let inverse = crate::ops::array::PermuteAxes::inverse(axes);
+            let op = ConvUnary {
+                data_fmt,
+                full_input_shape: inverse.iter().map(|&ax| self.full_input_shape[ax]).collect(),
+                full_output_shape: inverse.iter().map(|&ax| self.full_output_shape[ax]).collect(),
+                ..self.clone()
+            };
+            return Ok(Some((Box::new(op), Some(axes.to_vec()))));
+        }
+        Ok(None)
+    }
+
     fn declutter(
         &self,
         model: &TypedModel,
@@ -366,7 +399,6 @@ impl Op for ConvUnary {
                 if (0..spatial_rank).all(|ax| self.padding.valid_dim(ax))
                     && dt == f32::datum_type()
                     && self.group == 1
-                    && self.bias.is_none()
                 {
                     let op = self.to_direct(&*shape)?;
                     return Ok(Some(TypedModelPatch::single_unary_op(model, node, op)?));
@@ -429,6 +461,13 @@ impl Op for ConvUnary {
         } else {
             let spatial_rank = self.full_input_shape.len() - 2;
             let geo_axis = fact.axis - shape.h_axis();
+            if fact.pulse() % self.strides[geo_axis] != 0 {
+                bail!(
+                    "Pulse ({}) must be a multiple of convolution stride ({})",
+                    fact.pulse(),
+                    self.strides[geo_axis]
+                );
+            }
             let kernel_spatial_shape =
                 &self.kernel.shape()[self.kernel_fmt.h_axis()..][..spatial_rank];
             let kernel_len = (kernel_spatial_shape[geo_axis] - 1)
@@ -15,6 +15,29 @@ impl Default for DataFormat {
 }
 
 impl DataFormat {
+    /// If `axes` turns a tensor in the other format into this one, returns
+    /// the other format.
+    pub fn permuted_from(&self, axes: &[usize]) -> Option<DataFormat> {
+        let rank = axes.len();
+        if rank < 3 {
+            return None;
+        }
+        let (other, expected): (DataFormat, Vec<usize>) = match self {
+            DataFormat::NHWC => {
+                (DataFormat::NCHW, Some(0).into_iter().chain(2..rank).chain(Some(1)).collect())
+            }
+            DataFormat::NCHW => (
+                DataFormat::NHWC,
+                Some(0).into_iter().chain(Some(rank - 1)).chain(1..rank - 1).collect(),
+            ),
+        };
+        if axes == &*expected {
+            Some(other)
+        } else {
+            None
+        }
+    }
+
     pub fn shape<D, S>(&self, shape: S) -> DataShape<D, S>
     where
         D: DimLike,
@@ -1,6 +1,16 @@
 use crate::ops::prelude::*;
 use ndarray::prelude::*;
 
+/// `per_element` operations for each element of the input.
+fn global_pool_cost(
+    per_element: u64,
+    inputs: &[&TensorFact],
+    outputs: &[&TensorFact],
+) -> Option<Cost> {
+    let len = crate::ops::cost::fact_len(inputs.get(0)?)?;
+    Cost::new(per_element * len, inputs, outputs)
+}
+
 #[derive(Debug, Clone, new, Default)]
 pub struct GlobalAvgPool {
     //    data_is_nhwc: bool, // default is nchw (onnx)
@@ -33,6 +43,10 @@ impl Op for GlobalAvgPool {
     fn name(&self) -> Cow<str> {
         "GlobalAvgPool".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(global_pool_cost(1, inputs, outputs))
+    }
 }
 
 impl StatelessOp for GlobalAvgPool {
@@ -94,6 +108,10 @@ impl Op for GlobalLpPool {
     fn name(&self) -> Cow<str> {
         "GlobalLpPool".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(global_pool_cost(2, inputs, outputs))
+    }
 }
 
 impl StatelessOp for GlobalLpPool {
@@ -145,6 +163,10 @@ impl Op for GlobalMaxPool {
     fn name(&self) -> Cow<str> {
         "GlobalMaxPool".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        Ok(global_pool_cost(1, inputs, outputs))
+    }
 }
 
 impl StatelessOp for GlobalMaxPool {
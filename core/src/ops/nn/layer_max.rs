@@ -41,6 +41,16 @@ impl Op for LayerHardmax {
     fn name(&self) -> Cow<str> {
         "LayerHardmax".into()
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        pulsify(self, self.axis, node, target, mapping)
+    }
 }
 
 impl StatelessOp for LayerHardmax {
@@ -99,6 +109,16 @@ impl Op for LayerLogSoftmax {
     fn name(&self) -> Cow<str> {
         "LayerLogSoftmax".into()
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        pulsify(self, self.axis, node, target, mapping)
+    }
 }
 
 impl StatelessOp for LayerLogSoftmax {
@@ -157,6 +177,16 @@ impl Op for LayerSoftmax {
     fn name(&self) -> Cow<str> {
         "LayerSoftmax".into()
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        pulsify(self, self.axis, node, target, mapping)
+    }
 }
 
 impl StatelessOp for LayerSoftmax {
@@ -177,6 +207,23 @@ impl InferenceRulesOp for LayerSoftmax {
     }
 }
 
+fn pulsify<O: Op + Clone>(
+    op: &O,
+    axis: isize,
+    node: &NormalizedNode,
+    target: &mut PulsedModel,
+    mapping: &HashMap<OutletId, OutletId>,
+) -> TractResult<TVec<OutletId>> {
+    let input = mapping[&node.inputs[0]];
+    let fact = target.fact(input)?.clone();
+    let axis = if axis < 0 { fact.shape.len() as isize + axis } else { axis } as usize;
+    if fact.axis >= axis {
+        bail!("Can not pulsify {} along a normalized axis", op.name());
+    }
+    let id = target.chain_after(input, &*node.name, op.clone(), tvec!(fact))?;
+    Ok(tvec!(OutletId::new(id, 0)))
+}
+
 fn rules<'r, 'p: 'r, 's: 'r>(
     s: &mut Solver<'r>,
     inputs: &'p [TensorProxy],
@@ -39,6 +39,22 @@ impl Op for Lrn {
     fn name(&self) -> Cow<str> {
         "Lrn".into()
     }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let fact = target.fact(input)?.clone();
+        if fact.axis == 1 {
+            bail!("Can not pulsify Lrn along the channel axis");
+        }
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
 }
 
 impl StatelessOp for Lrn {
@@ -30,6 +30,51 @@ impl Op for MaxPool {
     fn name(&self) -> Cow<str> {
         "MaxPool".into()
     }
+
+    fn cost(&self, inputs: &[&TensorFact], outputs: &[&TensorFact]) -> TractResult<Option<Cost>> {
+        let kernel_len = self.kernel_shape.iter().product::<usize>();
+        Ok(Cost::element_wise(kernel_len as u64, inputs, outputs))
+    }
+
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        if self.with_index_outputs.is_some() {
+            return Ok(None);
+        }
+        if let Some(data_fmt) = self.data_fmt.permuted_from(axes) {
+            let op = MaxPool { data_fmt, ..self.clone() };
+            return Ok(Some((Box::new(op), Some(axes.to_vec()))));
+        }
+        Ok(None)
+    }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        if self.with_index_outputs.is_some() {
+            bail!("Can not pulsify MaxPool with index outputs");
+        }
+        let (input, fact) = super::pools::pulsify_pooling(
+            self.data_fmt,
+            &*self.kernel_shape,
+            &self.padding,
+            self.strides.as_ref(),
+            node,
+            target,
+            mapping,
+        )?;
+        let id = target.chain_after(input, &*node.name, self.clone(), tvec!(fact))?;
+        Ok(tvec!(OutletId::new(id, 0)))
+    }
 }
 
 impl StatelessOp for MaxPool {
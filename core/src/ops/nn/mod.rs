@@ -9,14 +9,15 @@ mod lrn;
 mod maxpool;
 mod padding;
 mod patches;
+mod pools;
 mod reduce;
 mod sigmoid;
 mod tanh;
 
 pub use self::arg_max_min::ArgMaxMin;
 pub use self::avgpool::AvgPool;
-pub use self::batch_norm::BatchNorm;
-pub use self::conv::{Conv, ConvUnary, KernelFormat};
+pub use self::batch_norm::{BatchNorm, FixedBatchNorm};
+pub use self::conv::{Conv, ConvGemm, ConvUnary, Direct, KernelFormat};
 pub use self::data_formats::{DataFormat, DataShape};
 pub use self::global_pools::{GlobalAvgPool, GlobalLpPool, GlobalMaxPool};
 pub use self::layer_max::{LayerHardmax, LayerLogSoftmax, LayerSoftmax};
@@ -28,6 +29,9 @@ pub use self::reduce::{Reduce, Reducer};
 pub use self::sigmoid::Sigmoid;
 pub use self::tanh::Tanh;
 
+pub(crate) use self::sigmoid::sigmoid;
+pub(crate) use self::tanh::tanh;
+
 use num_traits::AsPrimitive;
 
 element_map!(Relu, [f32, i32], |x| if x < 0 as _ { 0 as _ } else { x });
@@ -0,0 +1,73 @@
+use crate::ops::prelude::*;
+use crate::pulse::delay::Delay;
+use crate::pulse::PulsedTensorFact;
+
+use super::{DataFormat, PaddingSpec};
+
+/// Common pulsification logic for MaxPool and AvgPool.
+///
+/// Returns the outlet the pooling op must be chained after (a Delay is
+/// inserted when pooling along the streaming axis) and the pulsed fact of
+/// the pooling output.
+pub(super) fn pulsify_pooling(
+    data_fmt: DataFormat,
+    kernel_shape: &[usize],
+    padding: &PaddingSpec,
+    strides: Option<&TVec<usize>>,
+    node: &NormalizedNode,
+    target: &mut PulsedModel,
+    mapping: &HashMap<OutletId, OutletId>,
+) -> TractResult<(OutletId, PulsedTensorFact)> {
+    let mut input = mapping[&node.inputs[0]];
+    let mut fact = target.fact(input)?.clone();
+    let ones = tvec![1; kernel_shape.len()];
+    let strides = strides.unwrap_or(&ones);
+    let (c_axis, h_axis) = {
+        let shape = data_fmt.shape(&*fact.shape);
+        (shape.c_axis(), shape.h_axis())
+    };
+    let mut output_delay = fact.delay;
+    let mut output_dim = fact.dim;
+    if fact.axis == c_axis {
+        bail!("Can not pulsify pooling along the channel axis");
+    } else if fact.axis >= h_axis && fact.axis < h_axis + kernel_shape.len() {
+        let geo_axis = fact.axis - h_axis;
+        if !padding.valid_dim(geo_axis) {
+            bail!("Can not pulsify pooling with padding along the streaming axis");
+        }
+        let stride = strides[geo_axis];
+        let kernel_len = kernel_shape[geo_axis];
+        if fact.pulse() % stride != 0 {
+            bail!("Pulse ({}) must be a multiple of pooling stride ({})", fact.pulse(), stride);
+        }
+        // smallest multiple of stride covering the receptive field overhang
+        let overlap = (kernel_len.saturating_sub(stride) + stride - 1) / stride * stride;
+        // extra delay to align the first pulsed frame on the stride grid
+        let extra = (stride - (fact.delay + overlap) % stride) % stride;
+        if overlap + extra > 0 {
+            let mut augmented_fact = fact.clone();
+            augmented_fact.shape[fact.axis] += overlap;
+            augmented_fact.delay += overlap + extra;
+            let delay = Delay::new(fact.clone(), extra, overlap);
+            let id = target.chain_after(
+                input,
+                format!("{}/Delay", node.name),
+                delay,
+                tvec!(augmented_fact.clone()),
+            )?;
+            input = OutletId::new(id, 0);
+            fact = augmented_fact;
+        }
+        output_delay = fact.delay / stride;
+        output_dim = (output_dim - kernel_len + 1).div_ceil(stride.to_dim());
+    }
+    let computed = {
+        let shape = data_fmt.shape(&*fact.shape);
+        padding.compute(shape.hw_dims(), kernel_shape, &ones, strides)
+    };
+    let mut output_fact = fact;
+    output_fact.shape[h_axis..][..kernel_shape.len()].copy_from_slice(&computed.output);
+    output_fact.delay = output_delay;
+    output_fact.dim = output_dim;
+    Ok((input, output_fact))
+}
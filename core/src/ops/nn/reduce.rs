@@ -251,6 +251,37 @@ impl Op for Reduce {
         format!("Reduce<{:?}>", self.reducer).into()
     }
 
+    fn absorb_permute_axes(
+        &self,
+        _model: &TypedModel,
+        _node: &TypedNode,
+        _slot: usize,
+        axes: &[usize],
+    ) -> TractResult<Option<(Box<Op>, Option<Vec<usize>>)>> {
+        let rank = axes.len();
+        let reduced_axes = match &self.axes {
+            Some(reduced) => Some(
+                reduced
+                    .iter()
+                    .map(|&ax| Ok(axes[Self::resolve_axis(ax, rank as i64)?] as i64))
+                    .collect::<TractResult<Vec<i64>>>()?,
+            ),
+            None => None,
+        };
+        let op = Reduce::new(reduced_axes, self.keep_dims, self.reducer);
+        let output_axes = if self.keep_dims {
+            axes.to_vec()
+        } else {
+            // renumber the surviving axes in the reduced ranks
+            let kept: Vec<usize> = (0..rank).filter(|&ax| !op.must_reduce(ax, rank)).collect();
+            axes.iter()
+                .filter(|&&ax| !op.must_reduce(ax, rank))
+                .map(|ax| kept.iter().position(|k| k == ax).unwrap())
+                .collect()
+        };
+        Ok(Some((Box::new(op), Some(output_axes))))
+    }
+
     fn pulsify(
         &self,
         _source: &NormalizedModel,
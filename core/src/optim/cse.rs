@@ -0,0 +1,134 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::model::*;
+use crate::ops::konst::Const;
+use crate::{DatumType, TractResult};
+
+/// Common subexpression elimination.
+///
+/// Merges nodes computing the same op (as told by `Op::same_as`) on the same
+/// inputs. Candidates are bucketed by a structural hash of the node (op name,
+/// inputs and output arity, plus datum type, shape and content for constants),
+/// so `same_as` is only called on plausible pairs.
+#[derive(Debug)]
+pub struct Cse;
+
+fn structural_hash(node: &TypedNode) -> TractResult<u64> {
+    let mut hasher = DefaultHasher::new();
+    node.op.name().hash(&mut hasher);
+    node.inputs.hash(&mut hasher);
+    node.outputs.len().hash(&mut hasher);
+    if node.op_is::<Const>() {
+        if let Some(value) = node.outputs[0].fact.konst.as_ref() {
+            format!("{:?}", value.datum_type()).hash(&mut hasher);
+            value.shape().hash(&mut hasher);
+            if value.datum_type() == DatumType::String {
+                value.as_slice::<String>()?.hash(&mut hasher);
+            } else {
+                value.as_bytes().hash(&mut hasher);
+            }
+        }
+    }
+    Ok(hasher.finish())
+}
+
+impl super::DeclutterPass for Cse {
+    fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
+        let mut done_something = false;
+        let mut seen = HashMap::<u64, Vec<usize>>::new();
+        for id in model.eval_order()? {
+            let hash = structural_hash(model.node(id))?;
+            let candidates = seen.entry(hash).or_insert_with(Vec::new);
+            let kept = candidates.iter().cloned().find(|&c| model.node(c).same_as(model.node(id)));
+            match kept {
+                Some(kept) if !super::is_output(model, id)? => {
+                    trace!("merging {} into {}", model.node(id), model.node(kept));
+                    for slot in 0..model.node(id).outputs.len() {
+                        super::shunt_outlet(
+                            model,
+                            OutletId::new(id, slot),
+                            OutletId::new(kept, slot),
+                        )?;
+                    }
+                    model.clear_inputs(id)?;
+                    done_something = true;
+                }
+                _ => candidates.push(id),
+            }
+        }
+        Ok(done_something)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DeclutterPass;
+    use super::*;
+    use crate::ops::array::AddDims;
+    use crate::ops::math::Add;
+    use crate::ops::prelude::*;
+
+    #[test]
+    fn merge_duplicated_ops() {
+        let mut model = InferenceModel::default();
+        let x =
+            model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2, 3))).unwrap();
+        let x = OutletId::new(x, 0);
+        let s1 = model.add_node_default("s1", AddDims::new(vec![0])).unwrap();
+        model.add_edge(x, InletId::new(s1, 0)).unwrap();
+        let s2 = model.add_node_default("s2", AddDims::new(vec![0])).unwrap();
+        model.add_edge(x, InletId::new(s2, 0)).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(s1, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(s2, 0), InletId::new(add, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(add, 0)]).unwrap();
+        let mut model = model.into_typed().unwrap();
+        assert!(Cse.pass(&mut model).unwrap());
+        let model = model.into_normalized().unwrap();
+        assert_eq!(model.nodes().len(), 3);
+        let add = model.node_by_name("add").unwrap();
+        assert_eq!(add.inputs[0], add.inputs[1]);
+    }
+
+    #[test]
+    fn keep_different_ops() {
+        let mut model = InferenceModel::default();
+        let x =
+            model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2, 3))).unwrap();
+        let x = OutletId::new(x, 0);
+        let s1 = model.add_node_default("s1", AddDims::new(vec![0])).unwrap();
+        model.add_edge(x, InletId::new(s1, 0)).unwrap();
+        let s2 = model.add_node_default("s2", AddDims::new(vec![1])).unwrap();
+        model.add_edge(x, InletId::new(s2, 0)).unwrap();
+        model.set_output_outlets(&[OutletId::new(s1, 0), OutletId::new(s2, 0)]).unwrap();
+        let mut model = model.into_typed().unwrap();
+        assert!(!Cse.pass(&mut model).unwrap());
+    }
+
+    #[test]
+    fn merge_equal_consts_only() {
+        let mut model = InferenceModel::default();
+        let c1 = model.add_const("c1", ndarray::arr1(&[1f32, 2.0]).into()).unwrap();
+        let c2 = model.add_const("c2", ndarray::arr1(&[1f32, 2.0]).into()).unwrap();
+        let c3 = model.add_const("c3", ndarray::arr1(&[1f32, 3.0]).into()).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(c1, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(c2, 0), InletId::new(add, 1)).unwrap();
+        let add2 = model.add_node_default("add2", Add::default()).unwrap();
+        model.add_edge(OutletId::new(add, 0), InletId::new(add2, 0)).unwrap();
+        model.add_edge(OutletId::new(c3, 0), InletId::new(add2, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(add2, 0)]).unwrap();
+        let mut model = model.into_typed().unwrap();
+        assert_ne!(
+            structural_hash(model.node(c1)).unwrap(),
+            structural_hash(model.node(c3)).unwrap()
+        );
+        assert!(Cse.pass(&mut model).unwrap());
+        let add = model.node_by_name("add").unwrap();
+        assert_eq!(add.inputs[0], add.inputs[1]);
+        let add2 = model.node_by_name("add2").unwrap();
+        assert_ne!(add2.inputs[1], add.inputs[0]);
+    }
+}
@@ -0,0 +1,392 @@
+//! Operator fusion rules.
+//!
+//! At declutter time, a constant-parameter batch normalization or a constant
+//! bias add following a convolution is folded into the convolution kernel
+//! and bias.
+//!
+//! At codegen time, a bias add after a matrix product, and a Relu, Relu6,
+//! Sigmoid or Tanh after a matrix product or a convolution, are pushed into
+//! the linalg output loops as `FusedSpec`s.
+
+use ndarray::*;
+use num_traits::{AsPrimitive, Float, FromPrimitive};
+use tract_linalg::FusedSpec;
+
+use super::pattern::{Pattern, RewriteRule};
+use crate::model::*;
+use crate::ops::math::mat_mul::{MatMulUnaryImplA, MatMulUnaryImplASimpleB};
+use crate::ops::math::{self, Add};
+use crate::ops::nn::{self, ConvGemm, ConvUnary, Direct, FixedBatchNorm, KernelFormat};
+use crate::ops::prelude::*;
+
+pub fn declutter_rules() -> Vec<RewriteRule> {
+    vec![conv_batch_norm::<f32>(), conv_batch_norm::<f64>(), conv_bias(), conv_const_bias()]
+}
+
+pub fn codegen_rules() -> Vec<RewriteRule> {
+    vec![mat_mul_bias(), linear_activation()]
+}
+
+/// Replaces the chain going from `first` to `last` by a single node running
+/// `op`. It computes the value of `last`, so it is named after it.
+fn replace_chain(
+    model: &TypedModel,
+    first: usize,
+    last: usize,
+    op: impl Into<Box<Op>>,
+) -> TractResult<TypedModelPatch> {
+    let first = model.node(first);
+    let mut patch = TypedModelPatch::default();
+    patch.tap_model(model, first.inputs[0])?;
+    let last = model.node(last);
+    let id = patch.chain(&*last.name, op, tvec!(last.outputs[0].fact.clone()))?;
+    patch.shunt_outside(OutletId::new(last.id, 0), OutletId::new(id, 0))?;
+    Ok(patch)
+}
+
+/// Extracts the values of `t` along `axis` if `t`, broadcast to a tensor of
+/// rank `rank`, only varies along `axis` (of dimension `dim`).
+fn along_axis<T: Datum + Copy>(
+    t: &Tensor,
+    rank: usize,
+    axis: usize,
+    dim: usize,
+) -> TractResult<Option<Vec<T>>> {
+    let shape = t.shape();
+    if shape.len() > rank {
+        return Ok(None);
+    }
+    let offset = rank - shape.len();
+    for (ix, &d) in shape.iter().enumerate() {
+        if d != 1 && (ix + offset != axis || d != dim) {
+            return Ok(None);
+        }
+    }
+    let values: Vec<T> = t.to_array_view::<T>()?.iter().cloned().collect();
+    if values.len() == 1 {
+        Ok(Some(vec![values[0]; dim]))
+    } else {
+        Ok(Some(values))
+    }
+}
+
+fn conv_output_channel_axis(conv: &ConvUnary) -> usize {
+    conv.data_fmt.shape(&*conv.full_output_shape).c_axis()
+}
+
+fn conv_batch_norm<T>() -> RewriteRule
+where
+    T: Datum + Float + FromPrimitive + ScalarOperand,
+    f32: AsPrimitive<T>,
+{
+    RewriteRule::new(
+        format!("conv-batch-norm-{:?}", T::datum_type()),
+        Pattern::op::<FixedBatchNorm<T>>("bn")
+            .input(0, Pattern::op::<ConvUnary>("conv").single_use()),
+        |model, m| {
+            let conv = model.node(m.node("conv")?).op_as::<ConvUnary>().unwrap();
+            let bn = model.node(m.node("bn")?).op_as::<FixedBatchNorm<T>>().unwrap();
+            if conv.kernel.datum_type() != T::datum_type()
+                || bn.c_axis != conv_output_channel_axis(conv)
+                || bn.c_dim != conv.output_channels()
+            {
+                return Ok(None);
+            }
+            let mut kernel = conv.kernel.to_array_view::<T>()?.to_owned();
+            let o_axis = match conv.kernel_fmt {
+                KernelFormat::OIHW => 0,
+                KernelFormat::HWIO => kernel.ndim() - 1,
+            };
+            for (o, mut filter) in kernel.axis_iter_mut(Axis(o_axis)).enumerate() {
+                filter.mapv_inplace(|x| x * bn.slope[o]);
+            }
+            let bias: Vec<T> = match conv.bias {
+                Some(ref bias) => bias
+                    .as_slice::<T>()?
+                    .iter()
+                    .enumerate()
+                    .map(|(o, &b)| b * bn.slope[o] + bn.intercept[o])
+                    .collect(),
+                None => bn.intercept.to_vec(),
+            };
+            let mut op = conv.clone();
+            op.kernel = kernel.into();
+            op.bias = Some(Array1::from_vec(bias).into());
+            Ok(Some(replace_chain(model, m.node("conv")?, m.node("bn")?, op)?))
+        },
+    )
+}
+
+fn fold_bias<T: Datum + Float>(conv: &ConvUnary, b: &Tensor) -> TractResult<Option<ConvUnary>> {
+    let rank = conv.full_output_shape.len();
+    let co = conv.output_channels();
+    let bias = match along_axis::<T>(b, rank, conv_output_channel_axis(conv), co)? {
+        Some(bias) => bias,
+        None => return Ok(None),
+    };
+    let bias: Vec<T> = match conv.bias {
+        Some(ref previous) => {
+            previous.as_slice::<T>()?.iter().zip(bias.iter()).map(|(&a, &b)| a + b).collect()
+        }
+        None => bias,
+    };
+    let mut op = conv.clone();
+    op.bias = Some(Array1::from_vec(bias).into());
+    Ok(Some(op))
+}
+
+fn conv_with_bias(
+    model: &TypedModel,
+    conv: usize,
+    add: usize,
+    b: &Tensor,
+) -> TractResult<Option<TypedModelPatch>> {
+    let op = model.node(conv).op_as::<ConvUnary>().unwrap();
+    let dt = op.kernel.datum_type();
+    if b.datum_type() != dt || (dt != DatumType::F32 && dt != DatumType::F64) {
+        return Ok(None);
+    }
+    if let Some(op) = dispatch_floatlike!(self::fold_bias(dt)(op, b))? {
+        Ok(Some(replace_chain(model, conv, add, op)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn conv_bias() -> RewriteRule {
+    RewriteRule::new(
+        "conv-bias",
+        Pattern::op::<Add::UnaryA>("add").input(0, Pattern::op::<ConvUnary>("conv").single_use()),
+        |model, m| {
+            let add = model.node(m.node("add")?).op_as::<Add::UnaryA>().unwrap();
+            conv_with_bias(model, m.node("conv")?, m.node("add")?, &add.b)
+        },
+    )
+}
+
+fn conv_const_bias() -> RewriteRule {
+    RewriteRule::new(
+        "conv-const-bias",
+        Pattern::op::<Add::Bin>("add")
+            .commutative()
+            .input(0, Pattern::op::<ConvUnary>("conv").single_use())
+            .input(1, Pattern::konst("bias")),
+        |model, m| conv_with_bias(model, m.node("conv")?, m.node("add")?, m.konst("bias")?),
+    )
+}
+
+fn relu(x: f32) -> f32 {
+    if x < 0.0 {
+        0.0
+    } else {
+        x
+    }
+}
+
+fn relu6(x: f32) -> f32 {
+    x.max(0.0).min(6.0)
+}
+
+fn activation(op: &Op) -> Option<fn(f32) -> f32> {
+    if op.downcast_ref::<nn::Relu>().is_some() {
+        Some(relu)
+    } else if op.downcast_ref::<math::Clip>().map(|c| c.min == 0.0 && c.max == 6.0).unwrap_or(false)
+    {
+        Some(relu6)
+    } else if op.downcast_ref::<nn::Sigmoid>().is_some() {
+        Some(nn::sigmoid)
+    } else if op.downcast_ref::<nn::Tanh>().is_some() {
+        Some(nn::tanh)
+    } else if op.downcast_ref::<math::Tanh>().is_some() {
+        Some(f32::tanh)
+    } else {
+        None
+    }
+}
+
+fn is_mat_mul(op: &Op) -> bool {
+    op.downcast_ref::<MatMulUnaryImplASimpleB<f32>>().is_some()
+        || op.downcast_ref::<MatMulUnaryImplA<f32>>().is_some()
+}
+
+fn is_linear(op: &Op) -> bool {
+    is_mat_mul(op)
+        || op.downcast_ref::<ConvGemm<f32>>().is_some()
+        || op.downcast_ref::<Direct>().is_some()
+}
+
+/// Pushes `spec` to the fused operations of a linalg-backed operator.
+fn fuse(op: &Op, spec: FusedSpec<f32>) -> Option<Box<Op>> {
+    if let Some(op) = op.downcast_ref::<ConvGemm<f32>>() {
+        let mut op = op.clone();
+        op.fused_ops.iter_mut().for_each(|ops| ops.push(spec.clone()));
+        Some(Box::new(op))
+    } else if let Some(op) = op.downcast_ref::<Direct>() {
+        let mut op = op.clone();
+        op.fused_ops.push(spec);
+        Some(Box::new(op))
+    } else if let Some(op) = op.downcast_ref::<MatMulUnaryImplASimpleB<f32>>() {
+        let mut op = op.clone();
+        op.fused_ops.push(spec);
+        Some(Box::new(op))
+    } else if let Some(op) = op.downcast_ref::<MatMulUnaryImplA<f32>>() {
+        let mut op = op.clone();
+        op.fused_ops.push(spec);
+        Some(Box::new(op))
+    } else {
+        None
+    }
+}
+
+fn mat_mul_bias() -> RewriteRule {
+    RewriteRule::new(
+        "mat-mul-bias",
+        Pattern::op::<Add::UnaryA>("add").input(0, Pattern::op_if("mm", is_mat_mul).single_use()),
+        |model, m| {
+            let add_node = model.node(m.node("add")?);
+            let add = add_node.op_as::<Add::UnaryA>().unwrap();
+            let shape = match add_node.outputs[0].fact.shape.as_finite() {
+                Some(shape) => shape,
+                None => return Ok(None),
+            };
+            if add.b.datum_type() != DatumType::F32
+                || model.fact(add_node.inputs[0])?.shape != add_node.outputs[0].fact.shape
+            {
+                return Ok(None);
+            }
+            let n = shape[shape.len() - 1];
+            let bias = match along_axis::<f32>(&add.b, shape.len(), shape.len() - 1, n)? {
+                Some(bias) => bias,
+                None => return Ok(None),
+            };
+            let mm = model.node(m.node("mm")?);
+            let op = fuse(mm.op(), FusedSpec::PerColAdd(bias)).unwrap();
+            Ok(Some(replace_chain(model, mm.id, add_node.id, op)?))
+        },
+    )
+}
+
+fn linear_activation() -> RewriteRule {
+    RewriteRule::new(
+        "linear-activation",
+        Pattern::op_if("activation", |op| activation(op).is_some())
+            .input(0, Pattern::op_if("linear", is_linear).single_use()),
+        |model, m| {
+            let f = activation(model.node(m.node("activation")?).op()).unwrap();
+            let linear = model.node(m.node("linear")?);
+            let op = fuse(linear.op(), FusedSpec::Map(f)).unwrap();
+            Ok(Some(replace_chain(model, linear.id, m.node("activation")?, op)?))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::nn::{BatchNorm, Conv, DataFormat, PaddingSpec};
+
+    fn conv_bn_bias_relu(padding: PaddingSpec) -> InferenceModel {
+        let mut model = InferenceModel::default();
+        let x = model
+            .add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(1, 2, 6, 5)))
+            .unwrap();
+        let kernel = Array::from_shape_fn((3, 2, 3, 3), |(o, i, h, w)| {
+            (o as f32 - 1.0) * 0.5 + i as f32 * 0.25 - h as f32 * 0.125 + w as f32 * 0.0625
+        });
+        let kernel = model.add_const("kernel", Tensor::from(kernel.into_dyn()).into()).unwrap();
+        let conv = model
+            .add_node_default(
+                "conv",
+                Conv::new(DataFormat::NCHW, KernelFormat::OIHW, None, None, padding, None, 1),
+            )
+            .unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(conv, 0)).unwrap();
+        model.add_edge(OutletId::new(kernel, 0), InletId::new(conv, 1)).unwrap();
+        let bn =
+            model.add_node_default("bn", BatchNorm::new(DataFormat::NCHW, 1e-3, true)).unwrap();
+        model.add_edge(OutletId::new(conv, 0), InletId::new(bn, 0)).unwrap();
+        for (ix, values) in
+            [[1.0f32, 0.5, 2.0], [0.1, -0.2, 0.3], [0.5, -1.0, 0.0], [1.0, 2.0, 0.5]]
+                .iter()
+                .enumerate()
+        {
+            let k = model.add_const(format!("bn-{}", ix), arr1(values).into()).unwrap();
+            model.add_edge(OutletId::new(k, 0), InletId::new(bn, ix + 1)).unwrap();
+        }
+        let bias = model
+            .add_const(
+                "bias",
+                Array::from_shape_vec((3, 1, 1), vec![0.5f32, -0.5, 1.0]).unwrap().into(),
+            )
+            .unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(bn, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(bias, 0), InletId::new(add, 1)).unwrap();
+        model.chain_default("relu", nn::Relu::default()).unwrap();
+        model
+    }
+
+    fn run(model: &TypedModel) -> Tensor {
+        let input = Array::from_shape_fn((1, 2, 6, 5), |(_, c, h, w)| {
+            (c as f32 - 0.5) * (h as f32 - 2.5) + w as f32 * 0.25
+        });
+        let plan = crate::SimplePlan::new(model).unwrap();
+        plan.run(tvec!(input.into())).unwrap().remove(0).as_tensor().clone()
+    }
+
+    #[test]
+    fn fold_batch_norm_and_bias() {
+        let model = conv_bn_bias_relu(PaddingSpec::SameUpper).into_typed().unwrap();
+        let expected = run(&model);
+        let decluttered = model.declutter().unwrap();
+        assert!(decluttered.nodes().iter().all(|n| n.op_as::<FixedBatchNorm<f32>>().is_none()
+            && n.op_as::<Add::UnaryA>().is_none()
+            && n.op_as::<Add::Bin>().is_none()));
+        assert!(decluttered.node_by_name("add").unwrap().op_is::<ConvUnary>());
+        assert!(run(&decluttered).close_enough(&expected, true));
+    }
+
+    fn check_fused_activation(padding: PaddingSpec, linear: &str) {
+        let model = conv_bn_bias_relu(padding).into_typed().unwrap();
+        let expected = run(&model);
+        let optimized = model.declutter().unwrap().into_optimized().unwrap();
+        assert!(optimized.nodes().iter().all(|n| n.op_as::<nn::Relu>().is_none()));
+        assert!(optimized.nodes().iter().any(|n| n.op().name() == linear));
+        assert!(run(&optimized).close_enough(&expected, true));
+    }
+
+    #[test]
+    fn fuse_activation_in_conv_gemm() {
+        check_fused_activation(PaddingSpec::SameUpper, "ConvGemm")
+    }
+
+    #[test]
+    fn fuse_activation_in_direct_conv() {
+        check_fused_activation(PaddingSpec::Valid, "ConvDirect")
+    }
+
+    #[test]
+    fn gemm_bias_sigmoid() {
+        use crate::ops::math::Gemm;
+        let mut model = InferenceModel::default();
+        let a =
+            model.add_source("a", TensorFact::dt_shape(DatumType::F32, shapefact!(3, 4))).unwrap();
+        let b = Array::from_shape_fn((5, 4), |(i, j)| i as f32 * 0.25 - j as f32 * 0.5);
+        let b = model.add_const("b", b.into()).unwrap();
+        let c = model.add_const("c", arr1(&[0.5f32, -1.0, 0.0, 1.0, 2.0]).into()).unwrap();
+        let gemm = model.add_node_default("gemm", Gemm::new(0.5, 2.0, false, true, true)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(gemm, 0)).unwrap();
+        model.add_edge(OutletId::new(b, 0), InletId::new(gemm, 1)).unwrap();
+        model.add_edge(OutletId::new(c, 0), InletId::new(gemm, 2)).unwrap();
+        model.chain_default("sigmoid", nn::Sigmoid::default()).unwrap();
+        let model = model.into_typed().unwrap();
+        let input = Array::from_shape_fn((3, 4), |(i, j)| i as f32 - j as f32 * 0.75);
+        let expected =
+            crate::SimplePlan::new(&model).unwrap().run(tvec!(input.clone().into())).unwrap();
+        let optimized = model.declutter().unwrap().into_optimized().unwrap();
+        assert_eq!(optimized.eval_order().unwrap().len(), 2);
+        assert_eq!(optimized.node(optimized.outputs().unwrap()[0].node).name, "sigmoid");
+        let found = crate::SimplePlan::new(&optimized).unwrap().run(tvec!(input.into())).unwrap();
+        assert!(found[0].close_enough(&expected[0], true));
+    }
+}
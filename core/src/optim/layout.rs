@@ -0,0 +1,269 @@
+use crate::model::*;
+use crate::ops::array::PermuteAxes;
+use crate::ops::prelude::*;
+
+/// Pushes `PermuteAxes` nodes down the graph.
+///
+/// A permutation feeding an op that does not care about the layout (see
+/// `Op::absorb_permute_axes`) is moved after it, possibly switching the
+/// data or kernel format of convolutions and pools on the way. Successive
+/// permutations are merged, and dropped when they cancel each other.
+#[derive(Debug)]
+pub struct PushPermuteAxesDown;
+
+impl super::DeclutterPass for PushPermuteAxesDown {
+    fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
+        let mut done_something = false;
+        loop {
+            let mut done_something_this_time = false;
+            for id in model.eval_order()? {
+                if let Some((patch, dead)) = self.try_node(model, id)? {
+                    debug!("Pushing {} down", model.node(id));
+                    patch.apply(model)?;
+                    for dead in dead {
+                        model.clear_inputs(dead)?;
+                    }
+                    if cfg!(debug_assertions) {
+                        model.check_edges()?;
+                    }
+                    done_something_this_time = true;
+                }
+            }
+            done_something = done_something || done_something_this_time;
+            if !done_something_this_time {
+                break;
+            }
+        }
+        Ok(done_something)
+    }
+}
+
+impl PushPermuteAxesDown {
+    /// Looks for a PermuteAxes at `id` that can go past its successor.
+    /// Returns the patch and the nodes it leaves dead.
+    fn try_node(
+        &self,
+        model: &TypedModel,
+        id: usize,
+    ) -> TractResult<Option<(TypedModelPatch, Vec<usize>)>> {
+        let node = model.node(id);
+        let op = if let Some(op) = node.op_as::<PermuteAxes>() {
+            op
+        } else {
+            return Ok(None);
+        };
+        if node.outputs[0].successors.len() != 1 || super::is_output(model, id)? {
+            return Ok(None);
+        }
+        let input = node.inputs[0];
+        let axes = op.axes(model.fact(input)?.shape.rank());
+        let inlet = node.outputs[0].successors[0];
+        let succ = model.node(inlet.node);
+        if succ.outputs.len() != 1 {
+            return Ok(None);
+        }
+        let succ_is_output = super::is_output(model, succ.id)?;
+        let mut patch = TypedModelPatch::default();
+
+        if let Some(next) = succ.op_as::<PermuteAxes>() {
+            let next_axes = next.axes(axes.len());
+            let composed: Vec<usize> = next_axes.iter().map(|&ax| axes[ax]).collect();
+            let tapped = patch.tap_model(model, input)?;
+            if is_identity(&composed) {
+                if succ_is_output {
+                    return Ok(None);
+                }
+                patch.shunt_outside(OutletId::new(succ.id, 0), tapped)?;
+            } else {
+                let fact = succ.outputs[0].fact.clone();
+                let merged =
+                    patch.add_node(&*succ.name, PermuteAxes::new(Some(composed)), tvec!(fact))?;
+                patch.add_edge(tapped, InletId::new(merged, 0))?;
+                patch.shunt_outside(OutletId::new(succ.id, 0), OutletId::new(merged, 0))?;
+            }
+            return Ok(Some((patch, vec![succ.id, id])));
+        }
+
+        let (new_op, output_axes) =
+            if let Some(it) = succ.op.absorb_permute_axes(model, succ, inlet.slot, &axes)? {
+                it
+            } else {
+                return Ok(None);
+            };
+        let output_axes = output_axes.filter(|axes| !is_identity(axes));
+        if output_axes.is_some() && succ_is_output {
+            // keep the model outputs where they are
+            return Ok(None);
+        }
+        let fact = &succ.outputs[0].fact;
+        let new_fact = match &output_axes {
+            Some(output_axes) => TypedTensorInfo {
+                datum_type: fact.datum_type,
+                shape: fact.shape.permuted(&PermuteAxes::inverse(output_axes)),
+                konst: None,
+            },
+            None => fact.clone(),
+        };
+        let new_node = patch.add_node(&*succ.name, new_op, tvec!(new_fact))?;
+        for (ix, &i) in succ.inputs.iter().enumerate() {
+            let tapped = patch.tap_model(model, if ix == inlet.slot { input } else { i })?;
+            patch.add_edge(tapped, InletId::new(new_node, ix))?;
+        }
+        let mut output = OutletId::new(new_node, 0);
+        if let Some(output_axes) = output_axes {
+            let permute = patch.add_node(
+                &*node.name,
+                PermuteAxes::new(Some(output_axes)),
+                tvec!(fact.clone()),
+            )?;
+            patch.add_edge(output, InletId::new(permute, 0))?;
+            output = OutletId::new(permute, 0);
+        }
+        patch.shunt_outside(OutletId::new(succ.id, 0), output)?;
+        Ok(Some((patch, vec![succ.id, id])))
+    }
+}
+
+fn is_identity(axes: &[usize]) -> bool {
+    axes.iter().enumerate().all(|(ix, &ax)| ix == ax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DeclutterPass;
+    use super::*;
+    use crate::ops::nn::{Conv, DataFormat, KernelFormat, PaddingSpec, Relu};
+    use ndarray::*;
+
+    fn conv(data_fmt: DataFormat) -> Conv {
+        Conv::new(data_fmt, KernelFormat::OIHW, None, None, PaddingSpec::Valid, None, 1)
+    }
+
+    // NHWC input, converted to NCHW around every conv, as tf2onnx does
+    fn sandwiches() -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x = model
+            .add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(1, 5, 5, 2)))
+            .unwrap();
+        let mut wire = OutletId::new(x, 0);
+        for layer in 0..2 {
+            let kernel = Array4::from_shape_fn((2, 2, 1, 1), |(o, i, _, _)| (o + 2 * i) as f32);
+            let kernel = model.add_const(format!("kernel{}", layer), kernel.into()).unwrap();
+            let to_nchw = model
+                .chain_after(
+                    wire,
+                    format!("to_nchw{}", layer),
+                    PermuteAxes::new(Some(vec![0, 3, 1, 2])),
+                    tvec!(TensorFact::default()),
+                )
+                .unwrap();
+            let conv =
+                model.add_node_default(format!("conv{}", layer), conv(DataFormat::NCHW)).unwrap();
+            model.add_edge(OutletId::new(to_nchw, 0), InletId::new(conv, 0)).unwrap();
+            model.add_edge(OutletId::new(kernel, 0), InletId::new(conv, 1)).unwrap();
+            model
+                .chain(
+                    format!("to_nhwc{}", layer),
+                    PermuteAxes::new(Some(vec![0, 2, 3, 1])),
+                    tvec!(TensorFact::default()),
+                )
+                .unwrap();
+            let relu = model
+                .chain(format!("relu{}", layer), Relu::default(), tvec!(TensorFact::default()))
+                .unwrap();
+            wire = OutletId::new(relu, 0);
+        }
+        model.set_output_outlets(&[wire]).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    #[test]
+    fn eliminate_sandwiches() {
+        let original = sandwiches();
+        let input = Tensor::from(Array4::from_shape_fn((1, 5, 5, 2), |(_, h, w, c)| {
+            (h * 10 + w) as f32 - c as f32 * 20.0
+        }));
+        let expected =
+            crate::plan::SimplePlan::new(&original).unwrap().run(tvec!(input.clone())).unwrap();
+
+        let mut model = original.declutter().unwrap();
+        assert!(model.nodes().iter().all(|n| !n.op_is::<PermuteAxes>()));
+        let conv = model.node_by_name("conv0").unwrap();
+        assert_eq!(conv.op_as::<crate::ops::nn::ConvUnary>().unwrap().data_fmt, DataFormat::NHWC);
+        assert!(!PushPermuteAxesDown.pass(&mut model).unwrap());
+
+        let found = crate::plan::SimplePlan::new(&model).unwrap().run(tvec!(input)).unwrap();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn push_through_add_and_reduce() {
+        use crate::ops::math::Add;
+        use crate::ops::nn::{Reduce, Reducer};
+        let mut model = InferenceModel::default();
+        let x = model
+            .add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(1, 3, 4, 2)))
+            .unwrap();
+        let b = model.add_const("b", arr3(&[[[1.0f32]], [[-2.0]]]).into()).unwrap();
+        let to_nchw = model
+            .chain_after(
+                OutletId::new(x, 0),
+                "to_nchw",
+                PermuteAxes::new(Some(vec![0, 3, 1, 2])),
+                tvec!(TensorFact::default()),
+            )
+            .unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(to_nchw, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(b, 0), InletId::new(add, 1)).unwrap();
+        let fact = TensorFact::dt_shape(DatumType::F32, shapefact!(1, 2, 4));
+        let sum = Reduce::new(Some(vec![2]), false, Reducer::Sum);
+        model.chain("sum", sum, tvec!(fact.clone())).unwrap();
+        let relu = model.chain("relu", Relu::default(), tvec!(fact)).unwrap();
+        model.set_output_outlets(&[OutletId::new(relu, 0)]).unwrap();
+        let original = model.into_typed().unwrap();
+        let input = Tensor::from(Array4::from_shape_fn((1, 3, 4, 2), |(_, h, w, c)| {
+            (h * 10 + w) as f32 - c as f32 * 20.0
+        }));
+        let expected =
+            crate::plan::SimplePlan::new(&original).unwrap().run(tvec!(input.clone())).unwrap();
+
+        let model = original.declutter().unwrap();
+        let permutes: Vec<&TypedNode> =
+            model.nodes().iter().filter(|n| n.op_is::<PermuteAxes>()).collect();
+        assert_eq!(permutes.len(), 1);
+        assert_eq!(model.node(permutes[0].outputs[0].successors[0].node).name, "relu");
+
+        let found = crate::plan::SimplePlan::new(&model).unwrap().run(tvec!(input)).unwrap();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn absorb_kernel_format() {
+        let mut model = InferenceModel::default();
+        let x = model
+            .add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(1, 2, 5, 5)))
+            .unwrap();
+        let k = model
+            .add_source("k", TensorFact::dt_shape(DatumType::F32, shapefact!(1, 1, 2, 3)))
+            .unwrap();
+        let to_oihw = model
+            .chain_after(
+                OutletId::new(k, 0),
+                "to_oihw",
+                PermuteAxes::new(Some(vec![3, 2, 0, 1])),
+                tvec!(TensorFact::default()),
+            )
+            .unwrap();
+        let conv = model.add_node_default("conv", conv(DataFormat::NCHW)).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(conv, 0)).unwrap();
+        model.add_edge(OutletId::new(to_oihw, 0), InletId::new(conv, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(conv, 0)]).unwrap();
+        let mut model = model.into_typed().unwrap();
+        assert!(PushPermuteAxesDown.pass(&mut model).unwrap());
+        let model = model.into_normalized().unwrap();
+        assert_eq!(model.nodes().len(), 3);
+        let conv = model.node_by_name("conv").unwrap().op_as::<Conv>().unwrap();
+        assert!(format!("{:?}", conv).contains("kernel_fmt: HWIO"));
+    }
+}
@@ -1,27 +1,75 @@
-use crate::model::TypedModel;
+use crate::model::{OutletId, TypedModel};
 use crate::TractResult;
 use std::fmt::Debug;
 
+mod cse;
+mod fuse;
+mod layout;
+mod no_op;
+mod optimizer;
+pub mod pattern;
 mod prop_const;
 mod push_split_down;
 
+pub use self::cse::Cse;
+pub use self::layout::PushPermuteAxesDown;
+pub use self::no_op::RemoveNoOps;
 use self::prop_const::PropConst;
 use self::push_split_down::PushSplitDown;
 
+pub(crate) use self::optimizer::run_to_fixpoint;
+pub use self::optimizer::{Optimizer, OptimizerReport, Rewrite};
+pub use self::pattern::{Match, Pattern, RewriteRule};
+
 pub trait DeclutterPass: Debug + Send + Sync {
+    /// Name used to select the pass, and in optimisation logs.
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
     fn pass(&self, model: &mut TypedModel) -> TractResult<bool>;
 }
 
 pub trait CodegenPass: Debug + Send + Sync {
+    /// Name used to select the pass, and in optimisation logs.
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
     fn pass(&self, model: &mut TypedModel) -> TractResult<bool>;
 }
 
 pub fn declutter() -> Vec<Box<DeclutterPass>> {
-    vec![Box::new(PropConst) as _, Box::new(NormalizeOps)]
+    let mut passes: Vec<Box<DeclutterPass>> = vec![
+        Box::new(PropConst),
+        Box::new(NormalizeOps),
+        Box::new(PushPermuteAxesDown),
+        Box::new(RemoveNoOps),
+        Box::new(Cse),
+    ];
+    passes.extend(fuse::declutter_rules().into_iter().map(|r| Box::new(r) as _));
+    passes
 }
 
 pub fn codegen() -> Vec<Box<CodegenPass>> {
-    vec![Box::new(CodegenOps), Box::new(PushSplitDown)]
+    let mut passes: Vec<Box<CodegenPass>> = vec![Box::new(CodegenOps), Box::new(PushSplitDown)];
+    passes.extend(fuse::codegen_rules().into_iter().map(|r| Box::new(r) as _));
+    passes
+}
+
+fn is_output(model: &TypedModel, node: usize) -> TractResult<bool> {
+    Ok(model.outputs()?.iter().any(|o| o.node == node))
+}
+
+/// Reconnects the successors of `from` to `to`.
+fn shunt_outlet(model: &mut TypedModel, from: OutletId, to: OutletId) -> TractResult<()> {
+    // add_edge drops `to` from the model outputs, so keep them aside
+    let outputs = model.outputs()?.to_vec();
+    let successors = model.node(from.node).outputs[from.slot].successors.clone();
+    for succ in successors {
+        model.add_edge(to, succ)?;
+    }
+    model.set_output_outlets(&outputs)
 }
 
 #[derive(Debug)]
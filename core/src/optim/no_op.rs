@@ -0,0 +1,89 @@
+use crate::model::*;
+use crate::ops::array::{AddDims, Flatten, Reshape, ReshapeUnary, RmDims, Squeeze, SymbolicReshape};
+use crate::ops::cast::Cast;
+use crate::ops::identity::Identity;
+use crate::TractResult;
+
+/// Removes nodes that leave their first input untouched: `Identity`, and
+/// casts or reshapes whose output type and shape are the ones of the input.
+#[derive(Debug)]
+pub struct RemoveNoOps;
+
+fn is_no_op(model: &TypedModel, node: &TypedNode) -> TractResult<bool> {
+    let op = node.op();
+    if op.downcast_ref::<Identity>().is_some() {
+        return Ok(node.inputs.len() == 1 && node.outputs.len() == 1);
+    }
+    let metadata_only = op.downcast_ref::<Cast>().is_some()
+        || op.downcast_ref::<Reshape>().is_some()
+        || op.downcast_ref::<ReshapeUnary>().is_some()
+        || op.downcast_ref::<SymbolicReshape>().is_some()
+        || op.downcast_ref::<Squeeze>().is_some()
+        || op.downcast_ref::<AddDims>().is_some()
+        || op.downcast_ref::<RmDims>().is_some()
+        || op.downcast_ref::<Flatten>().is_some();
+    if !metadata_only || node.inputs.len() == 0 || node.outputs.len() != 1 {
+        return Ok(false);
+    }
+    let input = model.fact(node.inputs[0])?;
+    let output = &node.outputs[0].fact;
+    Ok(input.datum_type == output.datum_type && input.shape == output.shape)
+}
+
+impl super::DeclutterPass for RemoveNoOps {
+    fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
+        let mut done_something = false;
+        for id in model.eval_order()? {
+            if !is_no_op(model, model.node(id))? || super::is_output(model, id)? {
+                continue;
+            }
+            trace!("removing {}", model.node(id));
+            let input = model.node(id).inputs[0];
+            super::shunt_outlet(model, OutletId::new(id, 0), input)?;
+            model.clear_inputs(id)?;
+            done_something = true;
+        }
+        Ok(done_something)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DeclutterPass;
+    use super::*;
+    use crate::ops::math::Add;
+    use crate::ops::prelude::*;
+
+    fn model(cast_to: DatumType) -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x =
+            model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2, 3))).unwrap();
+        model.chain_default("id", Identity).unwrap();
+        model.chain_default("cast", Cast::new(cast_to)).unwrap();
+        model.chain_default("flatten", Flatten::new(1)).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(add - 1, 0), InletId::new(add, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(add, 0)]).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    #[test]
+    fn remove_no_ops() {
+        let mut model = model(DatumType::F32);
+        assert!(RemoveNoOps.pass(&mut model).unwrap());
+        let model = model.into_normalized().unwrap();
+        assert_eq!(model.nodes().len(), 2);
+        let add = model.node_by_name("add").unwrap();
+        assert_eq!(add.inputs[0], add.inputs[1]);
+    }
+
+    #[test]
+    fn keep_actual_cast() {
+        let mut model = model(DatumType::F64);
+        assert!(RemoveNoOps.pass(&mut model).unwrap());
+        let model = model.into_normalized().unwrap();
+        assert_eq!(model.nodes().len(), 3);
+        assert!(model.node_by_name("cast").is_ok());
+    }
+}
@@ -0,0 +1,292 @@
+//! A configurable optimisation pipeline.
+//!
+//! `Optimizer` holds the declutter and codegen passes, runs each group until
+//! fixpoint (or an iteration cap), and reports which pass rewrote which
+//! nodes.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::model::compact;
+use crate::model::*;
+use crate::TractResult;
+
+use super::{CodegenPass, DeclutterPass};
+
+/// A pass run that changed the model.
+#[derive(Clone, Debug)]
+pub struct Rewrite {
+    /// Name of the pass.
+    pub pass: String,
+    /// Fixpoint iteration the pass ran in (first is 0).
+    pub iteration: usize,
+    /// Names of the nodes the pass got rid of.
+    pub removed: Vec<String>,
+    /// Names of the nodes the pass introduced.
+    pub added: Vec<String>,
+    /// Live nodes before the pass.
+    pub nodes_before: usize,
+    /// Live nodes after the pass.
+    pub nodes_after: usize,
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "#{} {}: {} -> {} nodes, removed [{}], added [{}]",
+            self.iteration,
+            self.pass,
+            self.nodes_before,
+            self.nodes_after,
+            self.removed.join(", "),
+            self.added.join(", ")
+        )
+    }
+}
+
+/// What an optimisation run did.
+#[derive(Clone, Debug, Default)]
+pub struct OptimizerReport {
+    pub rewrites: Vec<Rewrite>,
+    /// Fixpoint iterations run.
+    pub iterations: usize,
+    /// False if the iteration cap stopped the run before fixpoint.
+    pub converged: bool,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+}
+
+impl OptimizerReport {
+    fn append(&mut self, other: OptimizerReport) {
+        if self.rewrites.is_empty() && self.iterations == 0 {
+            self.nodes_before = other.nodes_before;
+        }
+        self.rewrites.extend(other.rewrites);
+        self.iterations += other.iterations;
+        self.converged = other.converged;
+        self.nodes_after = other.nodes_after;
+    }
+}
+
+/// Declutter and codegen passes, and how to run them.
+#[derive(Debug)]
+pub struct Optimizer {
+    declutter: Vec<Box<DeclutterPass>>,
+    codegen: Vec<Box<CodegenPass>>,
+    max_iterations: Option<usize>,
+}
+
+impl Default for Optimizer {
+    /// The standard pipeline, as used by `TypedModel::declutter` and
+    /// `TypedModel::codegen`.
+    fn default() -> Optimizer {
+        Optimizer {
+            declutter: super::declutter(),
+            codegen: super::codegen(),
+            max_iterations: None,
+        }
+    }
+}
+
+impl Optimizer {
+    /// An optimizer with no pass at all.
+    pub fn empty() -> Optimizer {
+        Optimizer { declutter: vec![], codegen: vec![], max_iterations: None }
+    }
+
+    /// Appends a declutter pass.
+    pub fn declutter_pass<P: DeclutterPass + 'static>(mut self, pass: P) -> Optimizer {
+        self.declutter.push(Box::new(pass));
+        self
+    }
+
+    /// Appends a codegen pass.
+    pub fn codegen_pass<P: CodegenPass + 'static>(mut self, pass: P) -> Optimizer {
+        self.codegen.push(Box::new(pass));
+        self
+    }
+
+    /// Caps the number of fixpoint iterations of each of declutter and
+    /// codegen.
+    pub fn max_iterations(mut self, max: usize) -> Optimizer {
+        self.max_iterations = Some(max);
+        self
+    }
+
+    /// Names of the declutter passes, then of the codegen passes.
+    pub fn pass_names(&self) -> Vec<String> {
+        self.declutter.iter().map(|p| p.name()).chain(self.codegen.iter().map(|p| p.name())).collect()
+    }
+
+    /// Names of the declutter passes.
+    pub fn declutter_pass_names(&self) -> Vec<String> {
+        self.declutter.iter().map(|p| p.name()).collect()
+    }
+
+    /// Names of the codegen passes.
+    pub fn codegen_pass_names(&self) -> Vec<String> {
+        self.codegen.iter().map(|p| p.name()).collect()
+    }
+
+    fn check_names<S: AsRef<str>>(&self, names: &[S]) -> TractResult<()> {
+        let known = self.pass_names();
+        for name in names {
+            if !known.iter().any(|k| k == name.as_ref()) {
+                bail!("Unknown pass {}, known passes are: {}", name.as_ref(), known.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the passes called `name`.
+    pub fn without_pass(mut self, name: &str) -> TractResult<Optimizer> {
+        self.check_names(&[name])?;
+        self.declutter.retain(|p| p.name() != name);
+        self.codegen.retain(|p| p.name() != name);
+        Ok(self)
+    }
+
+    /// Only keeps the passes listed in `names`, in both stages: listing only
+    /// declutter passes leaves no codegen pass.
+    pub fn only_passes<S: AsRef<str>>(mut self, names: &[S]) -> TractResult<Optimizer> {
+        self.check_names(names)?;
+        let keep = |name: String| names.iter().any(|n| n.as_ref() == name);
+        self.declutter.retain(|p| keep(p.name()));
+        self.codegen.retain(|p| keep(p.name()));
+        Ok(self)
+    }
+
+    /// Runs the declutter passes until fixpoint.
+    pub fn declutter(&self, model: TypedModel) -> TractResult<(TypedModel, OptimizerReport)> {
+        run_to_fixpoint(model, &self.declutter, |p| p.name(), |p, m| p.pass(m), self.max_iterations)
+    }
+
+    /// Runs the codegen passes until fixpoint.
+    pub fn codegen(&self, model: TypedModel) -> TractResult<(TypedModel, OptimizerReport)> {
+        run_to_fixpoint(model, &self.codegen, |p| p.name(), |p, m| p.pass(m), self.max_iterations)
+    }
+
+    /// Runs declutter, then codegen.
+    pub fn optimize(&self, model: TypedModel) -> TractResult<(TypedModel, OptimizerReport)> {
+        let (model, mut report) = self.declutter(model)?;
+        let (model, codegen) = self.codegen(model)?;
+        report.append(codegen);
+        Ok((model, report))
+    }
+}
+
+pub(crate) fn run_to_fixpoint<P: ?Sized>(
+    model: TypedModel,
+    passes: &[Box<P>],
+    name: impl Fn(&P) -> String,
+    run: impl Fn(&P, &mut TypedModel) -> TractResult<bool>,
+    max_iterations: Option<usize>,
+) -> TractResult<(TypedModel, OptimizerReport)> {
+    let mut model = model;
+    let mut report = OptimizerReport::default();
+    report.nodes_before = model.eval_order()?.len();
+    loop {
+        if max_iterations.map(|max| report.iterations >= max).unwrap_or(false) {
+            break;
+        }
+        let mut done_something = false;
+        for p in passes {
+            let before: HashSet<usize> = model.eval_order()?.into_iter().collect();
+            let first_new_id = model.nodes().len();
+            let changed = run(p, &mut model)?;
+            if cfg!(debug_assertions) {
+                model.check_edges()?;
+            }
+            if changed {
+                let after: HashSet<usize> = model.eval_order()?.into_iter().collect();
+                let mut removed: Vec<usize> = before.difference(&after).cloned().collect();
+                removed.sort();
+                let mut added: Vec<usize> =
+                    after.iter().filter(|&&id| id >= first_new_id).cloned().collect();
+                added.sort();
+                let names = |ids: Vec<usize>| -> Vec<String> {
+                    ids.into_iter().map(|id| model.node(id).name.clone()).collect()
+                };
+                let rewrite = Rewrite {
+                    pass: name(p),
+                    iteration: report.iterations,
+                    removed: names(removed),
+                    added: names(added),
+                    nodes_before: before.len(),
+                    nodes_after: after.len(),
+                };
+                debug!("{}", rewrite);
+                report.rewrites.push(rewrite);
+                done_something = true;
+            }
+        }
+        report.iterations += 1;
+        if !done_something {
+            report.converged = true;
+            break;
+        }
+        model = compact::compact(&model)?;
+    }
+    report.nodes_after = model.eval_order()?.len();
+    Ok((model, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::math::{Add, Mul};
+    use crate::ops::prelude::*;
+
+    fn model() -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        let a = model.add_const("a", 2.0f32.into()).unwrap();
+        let b = model.add_const("b", 1.0f32.into()).unwrap();
+        let mul = model.add_node_default("mul", Mul::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(mul, 0)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(mul, 1)).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(mul, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(b, 0), InletId::new(add, 1)).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    #[test]
+    fn report_rewrites() {
+        let (model, report) = Optimizer::default().declutter(model()).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.nodes_before, 5);
+        assert_eq!(report.nodes_after, 3);
+        assert_eq!(model.nodes().len(), 3);
+        let normalize: Vec<&Rewrite> =
+            report.rewrites.iter().filter(|r| r.pass == "NormalizeOps").collect();
+        assert_eq!(normalize.len(), 1);
+        assert_eq!(normalize[0].removed, vec!["a", "mul", "b", "add"]);
+        assert_eq!(normalize[0].added, vec!["mul", "add"]);
+    }
+
+    #[test]
+    fn remove_pass() {
+        let optimizer = Optimizer::default().without_pass("NormalizeOps").unwrap();
+        assert!(!optimizer.pass_names().contains(&"NormalizeOps".to_string()));
+        let (model, report) = optimizer.declutter(model()).unwrap();
+        assert!(report.rewrites.is_empty());
+        assert_eq!(model.nodes().len(), 5);
+    }
+
+    #[test]
+    fn only_passes() {
+        let optimizer = Optimizer::default().only_passes(&["NormalizeOps"]).unwrap();
+        assert_eq!(optimizer.pass_names(), vec!["NormalizeOps"]);
+        assert!(optimizer.codegen_pass_names().is_empty());
+        assert!(Optimizer::default().only_passes(&["NoSuchPass"]).is_err());
+    }
+
+    #[test]
+    fn iteration_cap() {
+        let (_, report) = Optimizer::default().max_iterations(1).declutter(model()).unwrap();
+        assert_eq!(report.iterations, 1);
+        assert!(!report.converged);
+    }
+}
@@ -0,0 +1,466 @@
+//! Declarative subgraph matching for rewrite rules.
+//!
+//! A `Pattern` describes a subgraph by its root (the node whose output is
+//! rewritten) and, recursively, its inputs. Matching captures nodes, outlets
+//! and constants by name, and a `RewriteRule` turns a `Match` into a
+//! `TypedModelPatch`.
+//!
+//! ```ignore
+//! let pattern = Pattern::op::<Add::Bin>("add")
+//!     .commutative()
+//!     .input(0, Pattern::op::<Mul::Bin>("mul").single_use().input(1, Pattern::konst("a")))
+//!     .input(1, Pattern::konst("b"));
+//! ```
+
+use std::fmt;
+
+use crate::model::*;
+use crate::ops::prelude::*;
+
+type OpPredicate = Box<Fn(&Op) -> bool + Send + Sync>;
+type ConstPredicate = Box<Fn(&Tensor) -> bool + Send + Sync>;
+type Rewrite = Box<Fn(&TypedModel, &Match) -> TractResult<Option<TypedModelPatch>> + Send + Sync>;
+
+pub enum Pattern {
+    /// Any outlet, captured by name.
+    Any(&'static str),
+    /// A constant outlet, captured by name.
+    Const(&'static str, Option<ConstPredicate>),
+    /// A node output, captured by name.
+    Op {
+        name: &'static str,
+        predicate: OpPredicate,
+        inputs: Vec<(usize, Pattern)>,
+        single_use: bool,
+        commutative: bool,
+    },
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Any(name) => write!(fmt, "{}", name),
+            Pattern::Const(name, _) => write!(fmt, "const {}", name),
+            Pattern::Op { name, inputs, .. } => {
+                write!(fmt, "{}(", name)?;
+                for (ix, (slot, p)) in inputs.iter().enumerate() {
+                    if ix > 0 {
+                        write!(fmt, ", ")?;
+                    }
+                    write!(fmt, "#{}: {:?}", slot, p)?;
+                }
+                write!(fmt, ")")
+            }
+        }
+    }
+}
+
+impl Pattern {
+    /// Matches any outlet.
+    pub fn any(name: &'static str) -> Pattern {
+        Pattern::Any(name)
+    }
+
+    /// Matches a constant outlet.
+    pub fn konst(name: &'static str) -> Pattern {
+        Pattern::Const(name, None)
+    }
+
+    /// Matches a constant outlet satisfying `predicate`.
+    pub fn konst_with<F>(name: &'static str, predicate: F) -> Pattern
+    where
+        F: Fn(&Tensor) -> bool + Send + Sync + 'static,
+    {
+        Pattern::Const(name, Some(Box::new(predicate)))
+    }
+
+    /// Matches a node with an operator of type `O`.
+    pub fn op<O: Op>(name: &'static str) -> Pattern {
+        Pattern::op_with::<O, _>(name, |_| true)
+    }
+
+    /// Matches a node with an operator of type `O` satisfying `predicate`.
+    pub fn op_with<O: Op, F>(name: &'static str, predicate: F) -> Pattern
+    where
+        F: Fn(&O) -> bool + Send + Sync + 'static,
+    {
+        Pattern::op_if(name, move |op: &Op| {
+            op.downcast_ref::<O>().map(|op| predicate(op)).unwrap_or(false)
+        })
+    }
+
+    /// Matches a node whose operator satisfies `predicate`, whatever its
+    /// type.
+    pub fn op_if<F>(name: &'static str, predicate: F) -> Pattern
+    where
+        F: Fn(&Op) -> bool + Send + Sync + 'static,
+    {
+        Pattern::Op {
+            name,
+            predicate: Box::new(predicate),
+            inputs: vec![],
+            single_use: false,
+            commutative: false,
+        }
+    }
+
+    /// Constrains the `slot`-th input of an operator pattern.
+    pub fn input(mut self, slot: usize, pattern: Pattern) -> Pattern {
+        if let Pattern::Op { ref mut inputs, .. } = self {
+            inputs.push((slot, pattern))
+        }
+        self
+    }
+
+    /// Only matches if the node output is used once, and is not a model
+    /// output.
+    pub fn single_use(mut self) -> Pattern {
+        if let Pattern::Op { ref mut single_use, .. } = self {
+            *single_use = true
+        }
+        self
+    }
+
+    /// Also tries the two first inputs swapped.
+    pub fn commutative(mut self) -> Pattern {
+        if let Pattern::Op { ref mut commutative, .. } = self {
+            *commutative = true
+        }
+        self
+    }
+
+    /// Tries to match the pattern rooted at node `root`.
+    pub fn matches(&self, model: &TypedModel, root: usize) -> TractResult<Option<Match>> {
+        let mut m = Match::default();
+        if self.match_outlet(model, OutletId::new(root, 0), true, &mut m)? {
+            Ok(Some(m))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn match_outlet(
+        &self,
+        model: &TypedModel,
+        outlet: OutletId,
+        root: bool,
+        m: &mut Match,
+    ) -> TractResult<bool> {
+        match self {
+            Pattern::Any(name) => Ok(m.capture_outlet(name, outlet)),
+            Pattern::Const(name, predicate) => {
+                let konst = match model.fact(outlet)?.konst {
+                    Some(ref k) => k.clone(),
+                    None => return Ok(false),
+                };
+                if predicate.as_ref().map(|p| p(&konst)).unwrap_or(true) {
+                    m.konsts.insert(name, konst);
+                    Ok(m.capture_outlet(name, outlet))
+                } else {
+                    Ok(false)
+                }
+            }
+            Pattern::Op { name, predicate, inputs, single_use, commutative } => {
+                let node = model.node(outlet.node);
+                if !predicate(node.op()) {
+                    return Ok(false);
+                }
+                if *single_use && !root {
+                    if node.outputs[outlet.slot].successors.len() != 1
+                        || model.outputs()?.contains(&outlet)
+                    {
+                        return Ok(false);
+                    }
+                }
+                if m.nodes.get(name).map(|&n| n != node.id).unwrap_or(false) {
+                    return Ok(false);
+                }
+                m.nodes.insert(name, node.id);
+                if !m.capture_outlet(name, outlet) {
+                    return Ok(false);
+                }
+                let saved = m.clone();
+                if Self::match_inputs(model, &node.inputs, inputs, false, m)? {
+                    return Ok(true);
+                }
+                if *commutative && node.inputs.len() == 2 {
+                    *m = saved;
+                    return Self::match_inputs(model, &node.inputs, inputs, true, m);
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    fn match_inputs(
+        model: &TypedModel,
+        node_inputs: &[OutletId],
+        patterns: &[(usize, Pattern)],
+        swap: bool,
+        m: &mut Match,
+    ) -> TractResult<bool> {
+        for (slot, pattern) in patterns {
+            let slot = if swap && *slot < 2 { 1 - *slot } else { *slot };
+            if slot >= node_inputs.len() {
+                return Ok(false);
+            }
+            if !pattern.match_outlet(model, node_inputs[slot], false, m)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Nodes, outlets and constants captured by a successful match.
+#[derive(Clone, Debug, Default)]
+pub struct Match {
+    pub nodes: HashMap<&'static str, usize>,
+    pub outlets: HashMap<&'static str, OutletId>,
+    pub konsts: HashMap<&'static str, SharedTensor>,
+}
+
+impl Match {
+    fn capture_outlet(&mut self, name: &'static str, outlet: OutletId) -> bool {
+        match self.outlets.get(name) {
+            Some(&o) => o == outlet,
+            None => {
+                self.outlets.insert(name, outlet);
+                true
+            }
+        }
+    }
+
+    pub fn node(&self, name: &str) -> TractResult<usize> {
+        self.nodes.get(name).cloned().ok_or_else(|| format!("No node captured as {}", name).into())
+    }
+
+    pub fn outlet(&self, name: &str) -> TractResult<OutletId> {
+        self.outlets
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No outlet captured as {}", name).into())
+    }
+
+    pub fn konst(&self, name: &str) -> TractResult<&SharedTensor> {
+        self.konsts.get(name).ok_or_else(|| format!("No constant captured as {}", name).into())
+    }
+}
+
+/// A pattern and the rewrite to apply where it matches.
+pub struct RewriteRule {
+    name: String,
+    pattern: Pattern,
+    rewrite: Rewrite,
+}
+
+impl fmt::Debug for RewriteRule {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} {:?}", self.name, self.pattern)
+    }
+}
+
+impl RewriteRule {
+    pub fn new<F>(name: impl Into<String>, pattern: Pattern, rewrite: F) -> RewriteRule
+    where
+        F: Fn(&TypedModel, &Match) -> TractResult<Option<TypedModelPatch>> + Send + Sync + 'static,
+    {
+        RewriteRule { name: name.into(), pattern, rewrite: Box::new(rewrite) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Looks for a match at `node` and computes the rewrite.
+    pub fn try_node(
+        &self,
+        model: &TypedModel,
+        node: usize,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if let Some(m) = self.pattern.matches(model, node)? {
+            (self.rewrite)(model, &m)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Applies the rule until it matches nowhere. A patch rewires the model,
+    /// so the evaluation order is recomputed after each one.
+    fn run(&self, model: &mut TypedModel) -> TractResult<bool> {
+        let mut done_something = false;
+        'walk: loop {
+            for id in model.eval_order()? {
+                let patch = self
+                    .try_node(model, id)
+                    .map_err(|e| format!("{} node {}, {:?}", self.name, model.node(id), e))?;
+                if let Some(patch) = patch {
+                    debug!("Apply {} on {}", self.name, model.node(id));
+                    patch.apply(model)?;
+                    if cfg!(debug_assertions) {
+                        model.check_edges()?;
+                    }
+                    done_something = true;
+                    continue 'walk;
+                }
+            }
+            return Ok(done_something);
+        }
+    }
+}
+
+impl super::DeclutterPass for RewriteRule {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
+        self.run(model)
+    }
+}
+
+impl super::CodegenPass for RewriteRule {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
+        self.run(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DeclutterPass;
+    use super::*;
+    use crate::ops::identity::Identity;
+    use crate::ops::math::{Add, Mul};
+
+    fn model() -> TypedModel {
+        let mut model = InferenceModel::default();
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        let a = model.add_const("a", 2.0f32.into()).unwrap();
+        let b = model.add_const("b", 0.0f32.into()).unwrap();
+        let mul = model.add_node_default("mul", Mul::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(mul, 0)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(mul, 1)).unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(b, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(mul, 0), InletId::new(add, 1)).unwrap();
+        model.into_typed().unwrap()
+    }
+
+    fn affine() -> Pattern {
+        Pattern::op::<Add::Bin>("add")
+            .commutative()
+            .input(
+                0,
+                Pattern::op::<Mul::Bin>("mul")
+                    .single_use()
+                    .input(0, Pattern::any("x"))
+                    .input(1, Pattern::konst("a")),
+            )
+            .input(1, Pattern::konst("b"))
+    }
+
+    #[test]
+    fn match_commutative() {
+        let model = model();
+        let add = model.node_by_name("add").unwrap().id;
+        let m = affine().matches(&model, add).unwrap().unwrap();
+        assert_eq!(m.node("mul").unwrap(), model.node_by_name("mul").unwrap().id);
+        assert_eq!(m.outlet("x").unwrap(), OutletId::new(model.node_by_name("x").unwrap().id, 0));
+        assert_eq!(m.konst("a").unwrap().as_tensor(), &Tensor::from(2.0f32));
+        assert_eq!(m.konst("b").unwrap().as_tensor(), &Tensor::from(0.0f32));
+    }
+
+    #[test]
+    fn no_match_on_wrong_op() {
+        let model = model();
+        let mul = model.node_by_name("mul").unwrap().id;
+        assert!(affine().matches(&model, mul).unwrap().is_none());
+    }
+
+    #[test]
+    fn single_use() {
+        let mut model = model();
+        let mul = OutletId::new(model.node_by_name("mul").unwrap().id, 0);
+        let add = OutletId::new(model.node_by_name("add").unwrap().id, 0);
+        model.set_output_outlets(&[add, mul]).unwrap();
+        assert!(affine().matches(&model, add.node).unwrap().is_none());
+    }
+
+    #[test]
+    fn const_predicate() {
+        let model = model();
+        let add = model.node_by_name("add").unwrap().id;
+        let is_one =
+            |t: &Tensor| t.as_slice::<f32>().map(|t| t.iter().all(|&x| x == 1.0)).unwrap_or(false);
+        let pattern =
+            Pattern::op::<Add::Bin>("add").commutative().input(1, Pattern::konst_with("b", is_one));
+        assert!(pattern.matches(&model, add).unwrap().is_none());
+    }
+
+    #[test]
+    fn rewrite_add_zero() {
+        let is_zero =
+            |t: &Tensor| t.as_slice::<f32>().map(|t| t.iter().all(|&x| x == 0.0)).unwrap_or(false);
+        let rule = RewriteRule::new(
+            "add-zero",
+            Pattern::op::<Add::Bin>("add")
+                .commutative()
+                .input(0, Pattern::any("x"))
+                .input(1, Pattern::konst_with("zero", is_zero)),
+            |model, m| {
+                let add = model.node(m.node("add")?);
+                if model.fact(m.outlet("x")?)?.shape != add.outputs[0].fact.shape {
+                    return Ok(None);
+                }
+                Ok(Some(TypedModelPatch::replace_single_op(
+                    model,
+                    add,
+                    tvec!(m.outlet("x")?),
+                    Identity::default(),
+                )?))
+            },
+        );
+        let mut model = model();
+        assert!(rule.pass(&mut model).unwrap());
+        let model: TypedModel = crate::model::compact::compact(&model).unwrap();
+        assert!(model.nodes().iter().all(|n| !n.op_is::<Add::Bin>()));
+        let input = ndarray::arr1(&[1.0f32, 2.0]);
+        let result = crate::SimplePlan::new(&model).unwrap().run(tvec!(input.into())).unwrap();
+        assert_eq!(result[0].as_tensor(), &Tensor::from(ndarray::arr1(&[2.0f32, 4.0])));
+    }
+
+    #[test]
+    fn rewrite_overlapping_matches() {
+        use crate::ops::nn::Relu;
+        let rule = RewriteRule::new(
+            "relu-relu",
+            Pattern::op::<Relu>("outer")
+                .input(0, Pattern::op::<Relu>("inner").single_use().input(0, Pattern::any("x"))),
+            |model, m| {
+                let outer = model.node(m.node("outer")?);
+                let inputs = tvec!(m.outlet("x")?);
+                Ok(Some(TypedModelPatch::replace_single_op(model, outer, inputs, Relu::default())?))
+            },
+        );
+        let mut model = InferenceModel::default();
+        model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(2))).unwrap();
+        for ix in 0..4 {
+            model.chain_default(format!("relu-{}", ix), Relu::default()).unwrap();
+        }
+        let mut model = model.into_typed().unwrap();
+        assert!(rule.pass(&mut model).unwrap());
+        let model: TypedModel = crate::model::compact::compact(&model).unwrap();
+        assert_eq!(model.nodes().iter().filter(|n| n.op_is::<Relu>()).count(), 1);
+        let input = ndarray::arr1(&[-1.0f32, 2.0]);
+        let result = crate::SimplePlan::new(&model).unwrap().run(tvec!(input.into())).unwrap();
+        assert_eq!(result[0].as_tensor(), &Tensor::from(ndarray::arr1(&[0.0f32, 2.0])));
+    }
+}
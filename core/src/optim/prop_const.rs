@@ -1,53 +1,218 @@
 use crate::model::*;
+use crate::ops::array::{MultiBroadcastTo, MultiBroadcastToUnary, Reshape, SymbolicReshape};
 use crate::TractResult;
 use bit_set;
 
+/// Constant propagation.
+///
+/// Replaces the inputs the analyser found to be constant by Const nodes, then
+/// folds the Reshape and MultiBroadcastTo nodes which target shape is now a
+/// constant, possibly symbolic, into unary ops carrying that shape. This is
+/// what gets rid of the Shape / Gather / Concat plumbing ONNX exports put in
+/// front of them.
 #[derive(Debug)]
 pub struct PropConst;
 
 impl super::DeclutterPass for PropConst {
     fn pass(&self, model: &mut TypedModel) -> TractResult<bool> {
-        let mut replaced = 0;
-        let mut done = bit_set::BitSet::with_capacity(model.nodes().len());
-        let mut needed: Vec<usize> = vec![];
-        for t in model.outputs()?.iter().map(|n| n.node) {
-            needed.push(t);
+        let replaced = replace_by_consts(model)?;
+        let folded = fold_shape_consumers(model)?;
+        Ok(replaced || folded)
+    }
+}
+
+fn replace_by_consts(model: &mut TypedModel) -> TractResult<bool> {
+    let mut replaced = 0;
+    let mut done = bit_set::BitSet::with_capacity(model.nodes().len());
+    let mut needed: Vec<usize> = vec![];
+    for t in model.outputs()?.iter().map(|n| n.node) {
+        needed.push(t);
+    }
+    while let Some(&node) = needed.last() {
+        if done.contains(node) {
+            needed.pop();
+            continue;
         }
-        while let Some(&node) = needed.last() {
-            if done.contains(node) {
-                needed.pop();
-                continue;
+        if model.nodes()[node].inputs.iter().all(|i| done.contains(i.node)) {
+            needed.pop();
+            done.insert(node);
+        } else {
+            trace!("Looking at node {} inputs", node);
+            for ix in 0..model.nodes()[node].inputs.len() {
+                let source = model.nodes()[node].inputs[ix];
+                if model.nodes()[source.node].op().name() != "Const"
+                    && model.fact(source)?.konst.is_some()
+                    && (!model.fact(source)?.is_symbolic_konst()?
+                        || (ix == 1 && foldable(model, node)?))
+                {
+                    let konst = model.fact(source)?.konst.clone().unwrap();
+                    let id = model.nodes().len();
+                    trace!(
+                        "   Replacing node {} input {} by a constant instead of {:?}",
+                        node,
+                        ix,
+                        source
+                    );
+                    let id = model.add_const(format!("Const-{}", id), konst.clone())?;
+                    model.add_edge(OutletId::new(id, 0), InletId::new(node, ix))?;
+                    model.check_edges()?;
+                    model.set_fact(OutletId::new(id, 0), konst.into())?;
+                    replaced += 1;
+                } else {
+                    needed.push(source.node);
+                }
             }
-            if model.nodes()[node].inputs.iter().all(|i| done.contains(i.node)) {
-                needed.pop();
-                done.insert(node);
-            } else {
-                trace!("Looking at node {} inputs", node);
-                for ix in 0..model.nodes()[node].inputs.len() {
-                    let source = model.nodes()[node].inputs[ix];
-                    if model.nodes()[source.node].op().name() != "Const"
-                        && model.fact(source)?.konst.is_some()
-                    {
-                        let konst = model.fact(source)?.konst.clone().unwrap();
-                        let id = model.nodes().len();
-                        trace!(
-                            "   Replacing node {} input {} by a constant instead of {:?}",
-                            node,
-                            ix,
-                            source
-                        );
-                        let id = model.add_const(format!("Const-{}", id), konst.clone())?;
-                        model.add_edge(OutletId::new(id, 0), InletId::new(node, ix))?;
-                        model.check_edges()?;
-                        model.set_fact(OutletId::new(id, 0), konst.into())?;
-                        replaced += 1;
-                    } else {
-                        needed.push(source.node);
-                    }
+        }
+    }
+    debug!("Replaced {} inputs by constants", replaced);
+    Ok(replaced > 0)
+}
+
+/// Whether the node is a Reshape or MultiBroadcastTo that can be folded once
+/// its target shape is constant.
+///
+/// The unary ops recover S from their input shape at runtime, so this is
+/// not the case if the input streaming dimension can not be inverted (as
+/// `S/2`), or if the output depends on S and the input does not.
+fn foldable(model: &TypedModel, node: usize) -> TractResult<bool> {
+    let node = model.node(node);
+    if !node.op_is::<Reshape>() && !node.op_is::<MultiBroadcastTo>() {
+        return Ok(false);
+    }
+    let input_shape = &model.fact(node.inputs[0])?.shape;
+    let solvable = match input_shape.stream_info {
+        Some(info) => info.len.is_affine(),
+        None => node.outputs[0].fact.shape.stream_info.is_none(),
+    };
+    if !solvable {
+        debug!("Can not fold {}: S can not be recovered from {:?}", node, input_shape);
+    }
+    Ok(solvable)
+}
+
+/// Replaces Reshape and MultiBroadcastTo nodes with a constant target shape
+/// by unary ops carrying the shape.
+fn fold_shape_consumers(model: &mut TypedModel) -> TractResult<bool> {
+    let mut done_something = false;
+    for id in model.eval_order()? {
+        if !foldable(model, id)? || model.fact(model.node(id).inputs[1])?.konst.is_none() {
+            continue;
+        }
+        let patch = {
+            let node = model.node(id);
+            let input_shape = &model.fact(node.inputs[0])?.shape;
+            let shape = &node.outputs[0].fact.shape;
+            if node.op_is::<Reshape>() {
+                // concrete specifications are handled by Reshape itself
+                if !model.fact(node.inputs[1])?.is_symbolic_konst()? {
+                    continue;
                 }
+                let op = SymbolicReshape::new(input_shape.clone(), shape.clone());
+                TypedModelPatch::single_unary_op(model, node, op)?
+            } else {
+                let op = MultiBroadcastToUnary::new(input_shape.clone(), shape.clone());
+                TypedModelPatch::single_unary_op(model, node, op)?
             }
+        };
+        patch.apply(model)?;
+        done_something = true;
+    }
+    Ok(done_something)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ops::array::*;
+    use crate::ops::prelude::*;
+    use ndarray::{arr1, arr2, Array3};
+
+    // Shape -> Gather -> Concat, as found in front of ONNX Reshape and Expand
+    fn shape_of(model: &mut InferenceModel, x: usize, last: i64) -> OutletId {
+        let shape = model
+            .chain_after(
+                OutletId::new(x, 0),
+                "shape",
+                Shape::new(DatumType::I64),
+                tvec!(TensorFact::default()),
+            )
+            .unwrap();
+        let indices = model.add_const("indices", arr1(&[0i64]).into()).unwrap();
+        let gather = model.add_node_default("gather", Gather::new(0)).unwrap();
+        model.add_edge(OutletId::new(shape, 0), InletId::new(gather, 0)).unwrap();
+        model.add_edge(OutletId::new(indices, 0), InletId::new(gather, 1)).unwrap();
+        let last = model.add_const("last", arr1(&[last]).into()).unwrap();
+        let concat = model.add_node_default("concat", Concat::new(0)).unwrap();
+        model.add_edge(OutletId::new(gather, 0), InletId::new(concat, 0)).unwrap();
+        model.add_edge(OutletId::new(last, 0), InletId::new(concat, 1)).unwrap();
+        OutletId::new(concat, 0)
+    }
+
+    fn assert_no_shape_plumbing(model: &TypedModel) {
+        for node in model.nodes() {
+            assert!(["Shape", "Gather", "Concat", "Reshape", "MultiBroadcastTo"]
+                .iter()
+                .all(|op| node.op().name() != *op));
         }
-        debug!("Replaced {} inputs by constants", replaced);
-        Ok(replaced > 0)
+    }
+
+    #[test]
+    fn symbolic_reshape() {
+        let mut model = InferenceModel::default();
+        let x = model
+            .add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(S, 2, 3)))
+            .unwrap();
+        let shape = shape_of(&mut model, x, 6);
+        let reshape = model.add_node_default("reshape", Reshape::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(reshape, 0)).unwrap();
+        model.add_edge(shape, InletId::new(reshape, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(reshape, 0)]).unwrap();
+        let model = model.into_typed().unwrap().declutter().unwrap();
+        assert_no_shape_plumbing(&model);
+        assert_eq!(model.nodes().len(), 2);
+        assert!(model.node_by_name("reshape").unwrap().op_is::<SymbolicReshape>());
+        assert_eq!(model.outputs_fact(0).unwrap().shape.dim(0), TDim::s());
+
+        let input = Array3::from_shape_fn((4, 2, 3), |(a, b, c)| (a * 6 + b * 3 + c) as f32);
+        let found =
+            crate::plan::SimplePlan::new(&model).unwrap().run(tvec!(input.clone().into())).unwrap();
+        assert_eq!(found[0], input.into_shape((4, 6)).unwrap().into());
+    }
+
+    #[test]
+    fn symbolic_broadcast() {
+        let mut model = InferenceModel::default();
+        let x =
+            model.add_source("x", TensorFact::dt_shape(DatumType::F32, shapefact!(S, 1))).unwrap();
+        let shape = shape_of(&mut model, x, 3);
+        let broadcast = model.add_node_default("broadcast", MultiBroadcastTo::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(broadcast, 0)).unwrap();
+        model.add_edge(shape, InletId::new(broadcast, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(broadcast, 0)]).unwrap();
+        let model = model.into_typed().unwrap().declutter().unwrap();
+        assert_no_shape_plumbing(&model);
+        assert!(model.node_by_name("broadcast").unwrap().op_is::<MultiBroadcastToUnary>());
+
+        let input = arr2(&[[1.0f32], [2.0]]);
+        let found = crate::plan::SimplePlan::new(&model).unwrap().run(tvec!(input.into())).unwrap();
+        assert_eq!(found[0], arr2(&[[1.0f32, 1.0, 1.0], [2.0, 2.0, 2.0]]).into());
+    }
+
+    #[test]
+    fn non_affine_streaming_dim_is_left_alone() {
+        let mut model = InferenceModel::default();
+        let shape = ShapeFact::from(vec![TDim::s() / 2, 2.to_dim(), 3.to_dim()]);
+        let x = model.add_source("x", TensorFact::dt_shape(DatumType::F32, shape)).unwrap();
+        let shape = shape_of(&mut model, x, 6);
+        let reshape = model.add_node_default("reshape", Reshape::default()).unwrap();
+        model.add_edge(OutletId::new(x, 0), InletId::new(reshape, 0)).unwrap();
+        model.add_edge(shape, InletId::new(reshape, 1)).unwrap();
+        model.set_output_outlets(&[OutletId::new(reshape, 0)]).unwrap();
+        let model = model.into_typed().unwrap().declutter().unwrap();
+        assert!(model.node_by_name("reshape").unwrap().op_is::<Reshape>());
+
+        let input = Array3::from_shape_fn((4, 2, 3), |(a, b, c)| (a * 6 + b * 3 + c) as f32);
+        let found =
+            crate::plan::SimplePlan::new(&model).unwrap().run(tvec!(input.clone().into())).unwrap();
+        assert_eq!(found[0], input.into_shape((4, 6)).unwrap().into());
     }
 }
@@ -1,12 +1,74 @@
 use std::borrow::Borrow;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
 use crate::model::{eval_order, Model, TensorInfo};
 use crate::ops::prelude::*;
+use crate::TractErrorKind;
 
 #[derive(Debug, Default)]
 pub struct SessionState {
     pub known_stream_len: Option<usize>,
+    /// Checked before and after each node: a cancelled run stops with
+    /// `Cancelled`.
+    pub cancellation: Option<CancellationToken>,
+    /// Checked before and after each node: a late run stops with
+    /// `DeadlineExceeded`.
+    pub deadline: Option<Instant>,
+    /// Cap on the total size of the tensors kept alive by the run, checked
+    /// before and after each node. Exceeding it stops with
+    /// `LiveBytesExceeded`. A tensor shared by several wires counts once.
+    pub max_live_bytes: Option<usize>,
+}
+
+impl SessionState {
+    /// Checks cancellation, deadline and memory cap. On failure, wires are
+    /// cleared so the state can be reused.
+    fn check_limits(&self, values: &mut [Option<TVec<SharedTensor>>]) -> TractResult<()> {
+        let result = self.limits_error(values);
+        if result.is_err() {
+            values.iter_mut().for_each(|v| *v = None);
+        }
+        result
+    }
+
+    fn limits_error(&self, values: &[Option<TVec<SharedTensor>>]) -> TractResult<()> {
+        if self.cancellation.as_ref().map(|c| c.is_cancelled()).unwrap_or(false) {
+            bail!(TractErrorKind::Cancelled)
+        }
+        if self.deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+            bail!(TractErrorKind::DeadlineExceeded)
+        }
+        if let Some(limit) = self.max_live_bytes {
+            let mut seen = std::collections::HashSet::new();
+            let live = values
+                .iter()
+                .flat_map(|v| v.iter().flat_map(|v| v.iter()))
+                .filter(|t| seen.insert(t.as_tensor() as *const Tensor))
+                .map(|t| t.shape().iter().product::<usize>() * t.datum_type().size_of())
+                .sum::<usize>();
+            if live > limit {
+                bail!(TractErrorKind::LiveBytesExceeded(live, limit))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shared flag to cancel a run from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -136,6 +198,7 @@ impl<TI: TensorInfo, M: Borrow<Model<TI>>, P: Borrow<SimplePlan<TI, M>>> SimpleS
             }
             let plan = plan.borrow();
             for (step, n) in plan.order.iter().enumerate() {
+                session_state.check_limits(values)?;
                 let node = model.node(*n);
                 trace!("Running step {}, node {}", step, node);
                 if node.op_as::<Source>().is_none() {
@@ -158,12 +221,14 @@ impl<TI: TensorInfo, M: Borrow<Model<TI>>, P: Borrow<SimplePlan<TI, M>>> SimpleS
                     .map_err(|e| format!("Evaluating {}: {}", node, e))?;
 
                     values[node.id] = Some(vs);
+                    session_state.check_limits(values)?;
                 }
                 for flush in &plan.flush_lists[step] {
                     trace!("  flushing node {} {}", flush, node);
                     values[*flush] = None;
                 }
             }
+            session_state.check_limits(values)?;
             for output in model.outputs()? {
                 result.push(values[output.node].as_ref().unwrap()[output.slot].clone())
             }
@@ -312,3 +377,74 @@ impl<TI: TensorInfo, M: Borrow<Model<TI>>, P: Borrow<SimplePlan<TI, M>>> SimpleS
         self.plan().model()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::math::Add;
+    use crate::TractError;
+
+    fn plan() -> SimplePlan<TensorFact, InferenceModel> {
+        let mut model = InferenceModel::default();
+        let a = model.add_source_default("a").unwrap();
+        let add = model.add_node_default("add", Add::default()).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(add, 0)).unwrap();
+        model.add_edge(OutletId::new(a, 0), InletId::new(add, 1)).unwrap();
+        SimplePlan::new(model).unwrap()
+    }
+
+    fn input() -> TVec<Tensor> {
+        tvec!(::ndarray::arr1(&[1.0f32, 2.0]).into())
+    }
+
+    #[test]
+    fn cancelled() {
+        let plan = plan();
+        let mut state = SimpleState::new(&plan).unwrap();
+        let token = CancellationToken::default();
+        state.session_state.cancellation = Some(token.clone());
+        assert!(state.run(input()).is_ok());
+        token.cancel();
+        match state.run(input()) {
+            Err(TractError(TractErrorKind::Cancelled, _)) => (),
+            r => panic!("expected cancellation, got {:?}", r),
+        }
+        assert!(state.values.iter().all(|v| v.is_none()));
+    }
+
+    #[test]
+    fn deadline() {
+        let plan = plan();
+        let mut state = SimpleState::new(&plan).unwrap();
+        state.session_state.deadline = Some(Instant::now());
+        match state.run(input()) {
+            Err(TractError(TractErrorKind::DeadlineExceeded, _)) => (),
+            r => panic!("expected deadline exceeded, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn live_bytes() {
+        let plan = plan();
+        let mut state = SimpleState::new(&plan).unwrap();
+        state.session_state.max_live_bytes = Some(16);
+        assert!(state.run(input()).is_ok());
+        // the output of the last node, alive with its input, is checked too
+        state.session_state.max_live_bytes = Some(12);
+        match state.run(input()) {
+            Err(TractError(TractErrorKind::LiveBytesExceeded(16, 12), _)) => (),
+            r => panic!("expected live bytes exceeded, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn live_bytes_count_shared_tensors_once() {
+        let mut model = InferenceModel::default();
+        model.add_source_default("a").unwrap();
+        model.chain_default("id", crate::ops::identity::Identity).unwrap();
+        let plan = SimplePlan::new(model).unwrap();
+        let mut state = SimpleState::new(&plan).unwrap();
+        state.session_state.max_live_bytes = Some(8);
+        assert!(state.run(input()).is_ok());
+    }
+}
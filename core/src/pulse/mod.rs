@@ -76,6 +76,80 @@ impl PulsedTensorFact {
     }
 }
 
+/// Lists, for each model input, the axes that could be streamed upon: the
+/// axes whose dimension is either unknown or already the streaming dimension.
+pub fn stream_axis_candidates(model: &InferenceModel) -> TractResult<TVec<TVec<usize>>> {
+    model
+        .inputs()?
+        .iter()
+        .map(|&input| {
+            let fact = model.fact(input)?;
+            if fact.shape.is_open() {
+                return Ok(tvec!());
+            }
+            Ok(fact
+                .shape
+                .dims()
+                .enumerate()
+                .filter(|(_, d)| match d {
+                    GenericFact::Any => true,
+                    GenericFact::Only(d) => d.is_stream(),
+                })
+                .map(|(ix, _)| ix)
+                .collect())
+        })
+        .collect()
+}
+
+/// Makes `axis` the streaming dimension of a closed shape tensor fact.
+pub fn streaming_fact(fact: &TensorFact, axis: usize) -> TractResult<TensorFact> {
+    if fact.shape.is_open() {
+        bail!("Can not pick a streaming axis in an open shape: {:?}", fact)
+    }
+    let dims: TVec<DimFact> = fact
+        .shape
+        .dims()
+        .enumerate()
+        .map(|(ix, d)| if ix == axis { GenericFact::Only(TDim::s()) } else { d })
+        .collect();
+    if axis >= dims.len() {
+        bail!("Streaming axis {} is out of rank for {:?}", axis, fact)
+    }
+    Ok(TensorFact { shape: ShapeFact::closed(dims), ..fact.clone() })
+}
+
+/// A pulse size a model can be pulsified with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PulseCandidate {
+    pub pulse: usize,
+    /// Largest delay of the pulsed model outputs.
+    pub delay: usize,
+}
+
+/// Latency and length summary of a pulsed model output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PulsedOutputInfo {
+    /// Streaming axis of the output.
+    pub axis: usize,
+    /// Number of output frames produced by each pulse.
+    pub pulse: usize,
+    /// Number of output frames produced before the first valid one.
+    pub delay: usize,
+    /// The same delay, expressed in input samples.
+    pub input_delay: usize,
+    /// Number of valid output frames, in terms of the input stream length.
+    pub len: TDim,
+}
+
+impl PulsedOutputInfo {
+    /// Number of pulses to run for the output to be complete, for a given
+    /// input stream length.
+    pub fn pulses_for(&self, input_len: usize) -> TractResult<usize> {
+        let len = self.len.eval(input_len as i32).ok_or("Can not evaluate output length")?;
+        Ok((self.delay + len as usize).div_ceil(self.pulse))
+    }
+}
+
 pub type PulsedModel = Model<PulsedTensorFact>;
 
 impl PulsedModel {
@@ -117,6 +191,93 @@ impl PulsedModel {
         Ok((target, mapping))
     }
 
+    /// Searches valid pulse sizes: the smallest one, and its power of two
+    /// multiples up to `max_pulse`.
+    ///
+    /// Every valid pulse is a multiple of the granularity of the model (see
+    /// `pulse_granularity`), so only those multiples are pulsified. The
+    /// smallest one is returned even if it is larger than `max_pulse`.
+    pub fn pulse_candidates(
+        source: &NormalizedModel,
+        max_pulse: usize,
+    ) -> TractResult<Vec<PulseCandidate>> {
+        let granularity = PulsedModel::pulse_granularity(source)?;
+        let mut candidates = vec![];
+        let mut pulse = granularity;
+        loop {
+            match PulsedModel::new(source, pulse) {
+                Ok(pulsed) => {
+                    let delay = pulsed
+                        .outputs()?
+                        .iter()
+                        .map(|&o| Ok(pulsed.fact(o)?.delay))
+                        .collect::<TractResult<Vec<usize>>>()?
+                        .into_iter()
+                        .max()
+                        .unwrap_or(0);
+                    candidates.push(PulseCandidate { pulse, delay })
+                }
+                Err(e) if candidates.len() == 0 => {
+                    bail!("No valid pulse, the smallest candidate ({}) fails: {}", pulse, e)
+                }
+                Err(e) => debug!("Pulse {} is not valid: {}", pulse, e),
+            }
+            pulse *= 2;
+            if pulse > max_pulse {
+                return Ok(candidates);
+            }
+        }
+    }
+
+    /// The combined stride of the model along the streaming axis: valid
+    /// pulses are multiples of it.
+    ///
+    /// The stride accumulated between the input and a node is the ratio of
+    /// their stream lengths, read from the streaming dimension of the node
+    /// output. The granularity is the least common multiple of these.
+    pub fn pulse_granularity(source: &NormalizedModel) -> TractResult<usize> {
+        use num_integer::Integer;
+        // large enough for kernel overhangs to be rounded away
+        const SPAN: i32 = 720_720;
+        let mut granularity = 1;
+        for node in source.nodes() {
+            for output in &node.outputs {
+                let len = match output.fact.shape.stream_info {
+                    Some(info) => info.len,
+                    None => continue,
+                };
+                let grown = match (len.eval(SPAN), len.eval(2 * SPAN)) {
+                    (Some(a), Some(b)) if b > a => b - a,
+                    _ => continue,
+                };
+                let stride = (SPAN as f64 / grown as f64).round() as usize;
+                if stride > 1 {
+                    granularity = granularity.lcm(&stride);
+                }
+            }
+        }
+        Ok(granularity)
+    }
+
+    /// Summarises delay, pulse and length of each model output.
+    pub fn output_info(&self) -> TractResult<TVec<PulsedOutputInfo>> {
+        let input_pulse = self.input_fact()?.pulse();
+        self.outputs()?
+            .iter()
+            .map(|&o| {
+                let fact = self.fact(o)?;
+                let pulse = fact.pulse();
+                Ok(PulsedOutputInfo {
+                    axis: fact.axis,
+                    pulse,
+                    delay: fact.delay,
+                    input_delay: (fact.delay * input_pulse).div_ceil(pulse),
+                    len: fact.dim,
+                })
+            })
+            .collect()
+    }
+
     pub fn into_typed(self) -> TractResult<TypedModel> {
         crate::model::compact::compact(&self)
     }
@@ -257,6 +418,32 @@ mod tests {
             proptest_regular_against_pulse(model, pulse as _, input.into_dyn(), 0)?;
         }
 
+        #[test]
+        fn proptest_max_pool(pulse in 1i32..3, input_len in 0i32..10, kernel in 1i32..4, stride in 1i32..3) {
+            use crate::ops::nn::{MaxPool, PaddingSpec};
+            let pulse = pulse * stride;
+            let input_len = input_len + kernel;
+            let mut model = Model::default();
+            let _ = model
+                .add_source("a", TensorFact::dt_shape(f32::datum_type(), shapefact!(1, 1, S)))
+                .unwrap();
+            model.chain_default(
+                "pool",
+                MaxPool::new(
+                    Default::default(),
+                    tvec!(kernel as usize),
+                    PaddingSpec::Valid,
+                    Some(tvec!(stride as usize)),
+                    None,
+                ),
+            ).unwrap();
+
+            let input = Array1::range(1.0f32, input_len as f32 + 1.0, 1.0)
+                .into_shape((1, 1, input_len as usize))
+                .unwrap();
+            proptest_regular_against_pulse(model, pulse as _, input.into_dyn(), 2)?;
+        }
+
         #[test]
         fn proptest_pad(pulse in 1i32..3, input_len in 0i32..10, begin in 0i32..3, end in 0i32..3) {
             use crate::ops::array::{ Pad, PadMode };
@@ -288,6 +475,104 @@ mod tests {
         proptest_regular_against_pulse(model, 4, input.into_dyn(), 2).unwrap();
     }
 
+    #[test]
+    fn test_stream_axis_candidates() {
+        let mut model = Model::default();
+        let _a = model
+            .add_source("a", TensorFact::dt_shape(DatumType::F32, shapefact!(1, _, 3)))
+            .unwrap();
+        assert_eq!(stream_axis_candidates(&model).unwrap(), tvec!(tvec!(1)));
+        let fact = streaming_fact(model.input_fact().unwrap(), 1).unwrap();
+        assert_eq!(fact, TensorFact::dt_shape(DatumType::F32, shapefact!(1, S, 3)));
+    }
+
+    #[test]
+    fn test_pulse_candidates() {
+        use crate::ops::nn::{MaxPool, PaddingSpec};
+        let mut model = Model::default();
+        let _ = model
+            .add_source("a", TensorFact::dt_shape(f32::datum_type(), shapefact!(1, 1, S)))
+            .unwrap();
+        for (ix, stride) in [2, 3].iter().enumerate() {
+            let pool = MaxPool::new(
+                Default::default(),
+                tvec!(3),
+                PaddingSpec::Valid,
+                Some(tvec!(*stride)),
+                None,
+            );
+            model.chain_default(format!("pool-{}", ix), pool).unwrap();
+        }
+        let model = model.into_normalized().unwrap();
+        assert_eq!(PulsedModel::pulse_granularity(&model).unwrap(), 6);
+        let candidates = PulsedModel::pulse_candidates(&model, 24).unwrap();
+        assert_eq!(candidates.iter().map(|c| c.pulse).collect::<Vec<_>>(), vec!(6, 12, 24));
+        let candidates = PulsedModel::pulse_candidates(&model, 4).unwrap();
+        assert_eq!(candidates.iter().map(|c| c.pulse).collect::<Vec<_>>(), vec!(6));
+    }
+
+    #[test]
+    fn test_avg_pool_delayed() {
+        use crate::ops::array::{Slice};
+        use crate::ops::nn::{AvgPool, PaddingSpec};
+        let mut model = Model::default();
+        let _ = model
+            .add_source("a", TensorFact::dt_shape(f32::datum_type(), shapefact!(1, 1, S)))
+            .unwrap();
+        model.chain_default("slice", Slice::new(vec![(0, 0), (0, 0), (1, 0)])).unwrap();
+        model
+            .chain_default(
+                "pool",
+                AvgPool::new(
+                    Default::default(),
+                    tvec!(3),
+                    PaddingSpec::Valid,
+                    Some(tvec!(2)),
+                    false,
+                ),
+            )
+            .unwrap();
+
+        let input = arr3(&[[[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]]]);
+        proptest_regular_against_pulse(model, 4, input.into_dyn(), 2).unwrap();
+    }
+
+    #[test]
+    fn test_output_info() {
+        use crate::ops::array::Slice;
+        use crate::ops::nn::{AvgPool, PaddingSpec};
+        let mut model = Model::default();
+        let _ = model
+            .add_source("a", TensorFact::dt_shape(f32::datum_type(), shapefact!(1, 1, S)))
+            .unwrap();
+        model.chain_default("slice", Slice::new(vec![(0, 0), (0, 0), (1, 0)])).unwrap();
+        let pool =
+            AvgPool::new(Default::default(), tvec!(3), PaddingSpec::Valid, Some(tvec!(2)), false);
+        model.chain_default("pool", pool).unwrap();
+        let pulsed = PulsedModel::new(&model.into_normalized().unwrap(), 4).unwrap();
+        let info = pulsed.output_info().unwrap();
+        assert_eq!(info.len(), 1);
+        assert_eq!((info[0].axis, info[0].pulse, info[0].delay, info[0].input_delay), (2, 2, 2, 4));
+        assert_eq!(info[0].len.eval(9), Some(3));
+        assert_eq!(info[0].pulses_for(9).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_reshape_non_streaming_axes() {
+        use crate::ops::array::Reshape;
+        let mut model = Model::default();
+        let _ = model
+            .add_source("a", TensorFact::dt_shape(f32::datum_type(), shapefact!(S, 2, 3)))
+            .unwrap();
+        let shape = model.add_const("shape", arr1(&[0i64, -1]).into()).unwrap();
+        let reshape = model.chain_default("reshape", Reshape::default()).unwrap();
+        model.add_edge(OutletId::new(0, 0), InletId::new(reshape, 0)).unwrap();
+        model.add_edge(OutletId::new(shape, 0), InletId::new(reshape, 1)).unwrap();
+
+        let input = Array1::range(0.0f32, 30.0, 1.0).into_shape((5, 2, 3)).unwrap();
+        proptest_regular_against_pulse(model, 2, input.into_dyn(), 0).unwrap();
+    }
+
     #[test]
     fn test_pad_after_1() {
         use crate::ops::array::{Pad, PadMode};
@@ -126,6 +126,11 @@ impl Tensor {
         })
     }
 
+    /// The raw content of the tensor. Meaningless for String tensors.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
     pub fn into_aligned(self, alignment: usize) -> TractResult<Tensor> {
         Ok(Tensor {
             null: self.null,
@@ -173,6 +178,14 @@ impl Tensor {
         self.dt
     }
 
+    /// Permutes the axes, with the same convention as `PermuteAxes`.
+    pub fn permute_axes(self, axes: &[usize]) -> TractResult<Tensor> {
+        fn permute<T: Datum>(axes: &[usize], input: Tensor) -> TractResult<Tensor> {
+            Ok(input.into_array::<T>()?.permuted_axes(axes).into())
+        }
+        dispatch_datum!(permute(self.datum_type())(axes, self))
+    }
+
     pub fn dump_t<D: Datum>(&self, force_full: bool) -> TractResult<String> {
         use itertools::Itertools;
         let s = if self.shape.len() == 0 {
@@ -243,6 +256,49 @@ impl Tensor {
                 .all(|t| t.2)
     }
 
+    /// Compares the tensor to an `expected` one of the same shape. A value
+    /// passes if it is within `atol + rtol * |expected|` of the expected one,
+    /// or if both are NaN.
+    pub fn compare(&self, expected: &Tensor, atol: f64, rtol: f64) -> TractResult<Discrepancy> {
+        if self.shape() != expected.shape() {
+            bail!("Shape mismatch: got {:?}, expected {:?}", self.shape(), expected.shape())
+        }
+        let ulps = match (self.datum_type(), expected.datum_type()) {
+            (DatumType::F16, DatumType::F16) => Some(ulp_distances::<f16>(self, expected)?),
+            (DatumType::F32, DatumType::F32) => Some(ulp_distances::<f32>(self, expected)?),
+            (DatumType::F64, DatumType::F64) => Some(ulp_distances::<f64>(self, expected)?),
+            _ => None,
+        };
+        let mut discrepancy = Discrepancy::default();
+        if let (Some(got), Some(exp)) = (integer_values(self)?, integer_values(expected)?) {
+            // not through f64, which can not represent all i64 values
+            for (ix, (&g, &e)) in got.iter().zip(exp.iter()).enumerate() {
+                if g != e {
+                    let abs = (g as i128 - e as i128).abs() as f64;
+                    let e = (e as f64).abs();
+                    let rel = if e == 0.0 { std::f64::INFINITY } else { abs / e };
+                    discrepancy.record(ix, abs, rel, abs <= atol + rtol * e);
+                }
+            }
+            return Ok(discrepancy);
+        }
+        let got = self.cast_to::<f64>()?;
+        let exp = expected.cast_to::<f64>()?;
+        for (ix, (&g, &e)) in got.as_slice::<f64>()?.iter().zip(exp.as_slice::<f64>()?).enumerate()
+        {
+            if g.is_nan() && e.is_nan() || g == e {
+                continue;
+            }
+            let abs = (g - e).abs();
+            let rel = if e == 0.0 { std::f64::INFINITY } else { abs / e.abs() };
+            discrepancy.record(ix, abs, rel, abs <= atol + rtol * e.abs());
+            if let Some(ulps) = &ulps {
+                discrepancy.max_ulp = discrepancy.max_ulp.max(ulps[ix]);
+            }
+        }
+        Ok(discrepancy)
+    }
+
     pub fn into_array<D: Datum>(self) -> TractResult<ArrayD<D>> {
         if self.datum_type() != D::datum_type() {
             bail!(
@@ -448,6 +504,12 @@ impl Tensor {
             (I32, F32) => self.cast::<i32, f32>()?,
             (I64, F32) => self.cast::<i64, f32>()?,
 
+            (Bool, F64) => self.cast::<bool, f64>()?,
+            (I8, F64) => self.cast::<i8, f64>()?,
+            (I16, F64) => self.cast::<i16, f64>()?,
+            (I32, F64) => self.cast::<i32, f64>()?,
+            (I64, F64) => self.cast::<i64, f64>()?,
+
             (F32, String) => self.cast::<f32, std::string::String>()?,
             (String, F32) => self.cast::<std::string::String, f32>()?,
 
@@ -465,6 +527,96 @@ impl Tensor {
     }
 }
 
+/// How far a tensor is from an expected one, as measured by
+/// `Tensor::compare`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Discrepancy {
+    pub max_abs: f64,
+    pub max_rel: f64,
+    /// Maximum distance in units in the last place, for floating point
+    /// tensors of the same type.
+    pub max_ulp: u64,
+    /// Number of values out of tolerance.
+    pub failures: usize,
+    /// Index, in logical order, of the first value out of tolerance.
+    pub first_failure: Option<usize>,
+}
+
+impl Discrepancy {
+    pub fn is_ok(&self) -> bool {
+        self.failures == 0
+    }
+
+    fn record(&mut self, ix: usize, abs: f64, rel: f64, ok: bool) {
+        self.max_abs = self.max_abs.max(if abs.is_nan() { std::f64::INFINITY } else { abs });
+        self.max_rel = self.max_rel.max(if rel.is_nan() { std::f64::INFINITY } else { rel });
+        if !ok {
+            self.failures += 1;
+            self.first_failure = self.first_failure.or(Some(ix));
+        }
+    }
+}
+
+/// The values of a boolean, integer or dimension tensor, in logical order.
+fn integer_values(t: &Tensor) -> TractResult<Option<Vec<i64>>> {
+    fn values<T: Datum + Copy + Into<i64>>(t: &Tensor) -> TractResult<Option<Vec<i64>>> {
+        Ok(Some(t.to_array_view::<T>()?.iter().map(|&v| v.into()).collect()))
+    }
+    match t.datum_type() {
+        DatumType::Bool => values::<bool>(t),
+        DatumType::U8 => values::<u8>(t),
+        DatumType::U16 => values::<u16>(t),
+        DatumType::I8 => values::<i8>(t),
+        DatumType::I16 => values::<i16>(t),
+        DatumType::I32 => values::<i32>(t),
+        DatumType::I64 => values::<i64>(t),
+        DatumType::TDim => Ok(Some(
+            t.to_array_view::<crate::dim::TDim>()?
+                .iter()
+                .map(|d| d.to_integer().map(|i| i as i64))
+                .collect::<TractResult<_>>()?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// Floating point types, mapped to integers in the same order so that
+/// consecutive floats map to consecutive integers.
+trait OrderedBits: Datum + Copy {
+    fn ordered_bits(self) -> i128;
+}
+
+impl OrderedBits for f16 {
+    fn ordered_bits(self) -> i128 {
+        let bits = self.0.to_bits() as i16 as i128;
+        if bits < 0 { std::i16::MIN as i128 - bits } else { bits }
+    }
+}
+
+impl OrderedBits for f32 {
+    fn ordered_bits(self) -> i128 {
+        let bits = self.to_bits() as i32 as i128;
+        if bits < 0 { std::i32::MIN as i128 - bits } else { bits }
+    }
+}
+
+impl OrderedBits for f64 {
+    fn ordered_bits(self) -> i128 {
+        let bits = self.to_bits() as i64 as i128;
+        if bits < 0 { std::i64::MIN as i128 - bits } else { bits }
+    }
+}
+
+fn ulp_distances<T: OrderedBits>(a: &Tensor, b: &Tensor) -> TractResult<Vec<u64>> {
+    Ok(a.as_slice::<T>()?
+        .iter()
+        .zip(b.as_slice::<T>()?)
+        .map(|(&a, &b)| {
+            (a.ordered_bits() - b.ordered_bits()).abs().min(std::u64::MAX as i128) as u64
+        })
+        .collect())
+}
+
 impl PartialEq for Tensor {
     fn eq(&self, other: &Tensor) -> bool {
         if self.dt != other.dt || self.shape != other.shape {
@@ -587,3 +739,51 @@ where
         ArrayBase::from_shape_vec_unchecked(dim, v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dim::ToDim;
+
+    #[test]
+    fn compare_tolerances() {
+        let expected: Tensor = arr1(&[1.0f32, 100.0, 0.0, std::f32::NAN]).into();
+        let got: Tensor = arr1(&[1.0f32, 100.01, 1e-7, std::f32::NAN]).into();
+        let d = got.compare(&expected, 1e-6, 1e-3).unwrap();
+        assert!(d.is_ok());
+        assert!((d.max_abs - 0.01).abs() < 1e-5);
+        assert!(d.max_rel.is_infinite());
+        let d = got.compare(&expected, 1e-6, 1e-5).unwrap();
+        assert_eq!((d.failures, d.first_failure), (1, Some(1)));
+    }
+
+    #[test]
+    fn compare_ulps() {
+        let one = 1.0f32;
+        let next = f32::from_bits(one.to_bits() + 3);
+        let d = Tensor::from(arr1(&[next, -0.0]))
+            .compare(&arr1(&[one, f32::from_bits(1)]).into(), 0.0, 0.0)
+            .unwrap();
+        assert_eq!(d.max_ulp, 3);
+        assert_eq!(d.failures, 2);
+        assert!(Tensor::from(arr1(&[1i32])).compare(&arr1(&[1i32, 2]).into(), 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn compare_integers() {
+        let d = Tensor::from(arr1(&[1i32, 5])).compare(&arr1(&[1i32, 2]).into(), 0.0, 0.0).unwrap();
+        assert_eq!((d.max_abs, d.failures, d.first_failure), (3.0, 1, Some(1)));
+        let bytes = Tensor::from(arr1(&[1u8, 250]));
+        let d = bytes.compare(&arr1(&[3u8, 250]).into(), 0.0, 0.0).unwrap();
+        assert_eq!((d.max_abs, d.failures, d.first_failure), (2.0, 1, Some(0)));
+        let d = Tensor::from(arr1(&[7u16])).compare(&arr1(&[7u16]).into(), 0.0, 0.0).unwrap();
+        assert!(d.is_ok());
+        let dims = arr1(&[2.to_dim(), 3.to_dim()]);
+        let d = Tensor::from(dims).compare(&arr1(&[2.to_dim(), 4.to_dim()]).into(), 0.0, 0.0);
+        assert_eq!(d.unwrap().first_failure, Some(1));
+        // equal once rounded to f64
+        let big = 1i64 << 62;
+        let d = Tensor::from(arr1(&[big + 1])).compare(&arr1(&[big]).into(), 0.0, 0.0).unwrap();
+        assert_eq!((d.max_abs, d.failures), (1.0, 1));
+    }
+}
@@ -1,7 +1,9 @@
 pub mod conv;
+pub mod fused;
 pub mod matmul;
 
 pub use self::conv::Conv;
 pub use self::conv::PackedConv;
+pub use self::fused::FusedSpec;
 pub use self::matmul::MatMul;
 pub use self::matmul::PackedMatMul;
@@ -4,14 +4,29 @@ use std::ops::{Add, Mul};
 
 use std::marker::PhantomData;
 
+use super::fused::{apply_fused, FusedSpec};
+
 pub trait Conv<T: Copy + Add + Mul + Zero + Debug>: Send + Sync + Debug + objekt::Clone {
     fn packed_a_len(&self) -> usize;
     fn packed_a_alignment(&self) -> usize;
     fn pack_a(&self, pa: *mut T, a: *const T, rsa: isize, csa: isize);
 
     fn co(&self) -> usize;
+    fn k(&self) -> usize;
     fn n(&self) -> usize;
-    fn conv(&self, pa: *const T, b: *const T, c: *mut T, rsc: isize, csc: isize);
+    fn conv(&self, pa: *const T, b: *const T, c: *mut T, rsc: isize, csc: isize) {
+        self.conv_fused(pa, b, c, rsc, csc, &[])
+    }
+
+    fn conv_fused(
+        &self,
+        pa: *const T,
+        b: *const T,
+        c: *mut T,
+        rsc: isize,
+        csc: isize,
+        fused: &[FusedSpec<T>],
+    );
 }
 
 clone_trait_object!(<T> Conv<T> where T: Copy + Add + Mul + Zero);
@@ -162,7 +177,15 @@ where
         }
     }
 
-    fn conv(&self, pa: *const T, b: *const T, c: *mut T, rsc: isize, csc: isize) {
+    fn conv_fused(
+        &self,
+        pa: *const T,
+        b: *const T,
+        c: *mut T,
+        rsc: isize,
+        csc: isize,
+        fused: &[FusedSpec<T>],
+    ) {
         assert!(pa as usize % K::alignment_bytes_a() == 0);
         let mr = K::mr();
         let nr = K::nr();
@@ -183,6 +206,14 @@ where
                         rsc as usize,
                         csc as usize,
                     );
+                    apply_fused(
+                        fused,
+                        c.offset((mr * ia) as isize * rsc + (nr * ib) as isize * csc),
+                        rsc,
+                        csc,
+                        (mr * ia, nr * ib),
+                        (mr, nr),
+                    );
                 }
                 if n % nr != 0 {
                     K::kernel(
@@ -194,6 +225,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (mr * ia, n / nr * nr),
+                        (mr, n % nr),
+                    );
                     for y in 0..mr {
                         for x in 0..(n % nr) {
                             *c.offset(
@@ -214,6 +253,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (co / mr * mr, ib * nr),
+                        (co % mr, nr),
+                    );
                     for y in 0..(co % mr) {
                         for x in 0..nr {
                             *c.offset(
@@ -232,6 +279,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (co / mr * mr, n / nr * nr),
+                        (co % mr, n % nr),
+                    );
                     for y in 0..(co % mr) {
                         for x in 0..(n % nr) {
                             *c.offset(
@@ -249,6 +304,10 @@ where
         self.co
     }
 
+    fn k(&self) -> usize {
+        self.k
+    }
+
     fn n(&self) -> usize {
         self.n
     }
@@ -0,0 +1,143 @@
+use num_traits::Zero;
+use std::fmt::Debug;
+use std::ops::{Add, Mul};
+
+/// Element-wise operations applied to the output of a matrix product or a
+/// convolution, tile by tile, while the tile is still hot.
+#[derive(Clone, Debug)]
+pub enum FusedSpec<T: Copy + Add + Mul + Zero + Debug> {
+    /// Adds `v[row]` to every element of each row (per output channel bias
+    /// for a convolution).
+    PerRowAdd(Vec<T>),
+    /// Adds `v[col]` to every element of each column (bias of a dense layer).
+    PerColAdd(Vec<T>),
+    /// Applies a function to every element (activation).
+    Map(fn(T) -> T),
+}
+
+/// Applies `specs` to the `rows` x `cols` tile at `c`, whose top left corner
+/// is at (`row`, `col`) in the full output. Each element is read and written
+/// once.
+///
+/// # Safety
+///
+/// `c`, `rsc` and `csc` must address a valid `rows` x `cols` tile.
+pub unsafe fn apply_fused<T>(
+    specs: &[FusedSpec<T>],
+    c: *mut T,
+    rsc: isize,
+    csc: isize,
+    (row, col): (usize, usize),
+    (rows, cols): (usize, usize),
+) where
+    T: Copy + Add + Mul + Zero + Debug,
+{
+    if specs.is_empty() {
+        return;
+    }
+    for y in 0..rows {
+        for x in 0..cols {
+            let p = c.offset(y as isize * rsc + x as isize * csc);
+            let mut v = *p;
+            for spec in specs {
+                v = match spec {
+                    FusedSpec::PerRowAdd(bias) => v + bias[row + y],
+                    FusedSpec::PerColAdd(bias) => v + bias[col + x],
+                    FusedSpec::Map(f) => f(v),
+                };
+            }
+            *p = v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::align;
+    use crate::frame::{Conv, MatMul, PackedConv, PackedMatMul};
+    use crate::generic::{SConv4x4, SMatMul4x4};
+
+    fn relu(x: f32) -> f32 {
+        x.max(0.0)
+    }
+
+    #[test]
+    fn mat_mul_fused() {
+        let (m, k, n) = (5, 3, 7);
+        let a: Vec<f32> = (0..m * k).map(|x| x as f32 - 7.0).collect();
+        let b: Vec<f32> = (0..k * n).map(|x| 3.0 - x as f32).collect();
+        let rows: Vec<f32> = (0..m).map(|x| x as f32).collect();
+        let cols: Vec<f32> = (0..n).map(|x| -(x as f32)).collect();
+        let mm = PackedMatMul::<SMatMul4x4, f32>::new(m, k, n);
+        let found = unsafe {
+            let mut pa: Vec<f32> = align::uninitialized(mm.packed_a_len(), mm.packed_a_alignment());
+            mm.pack_a(pa.as_mut_ptr(), a.as_ptr(), k as isize, 1);
+            let mut pb: Vec<f32> = align::uninitialized(mm.packed_b_len(), mm.packed_b_alignment());
+            mm.pack_b(pb.as_mut_ptr(), b.as_ptr(), n as isize, 1);
+            let mut c = vec![9999.0f32; m * n];
+            mm.mat_mul_prepacked_fused(
+                pa.as_ptr(),
+                pb.as_ptr(),
+                c.as_mut_ptr(),
+                n as isize,
+                1,
+                &[
+                    FusedSpec::PerRowAdd(rows.clone()),
+                    FusedSpec::PerColAdd(cols.clone()),
+                    FusedSpec::Map(relu),
+                ],
+            );
+            c
+        };
+        let mut expect = vec![0.0f32; m * n];
+        for y in 0..m {
+            for x in 0..n {
+                let dot = (0..k).map(|i| a[i + k * y] * b[x + i * n]).sum::<f32>();
+                expect[x + y * n] = relu(dot + rows[y] + cols[x]);
+            }
+        }
+        assert_eq!(found, expect);
+    }
+
+    #[test]
+    fn conv_fused() {
+        let (ci, co, kt, t) = (2, 5, 3, 10);
+        let filters: Vec<f32> = (0..ci * co * kt).map(|x| (x % 7) as f32 - 3.0).collect();
+        let data: Vec<f32> = (0..ci * t).map(|x| 2.0 - (x % 5) as f32).collect();
+        let n = t - kt + 1;
+        let data_offsets: Vec<isize> = (0..n as isize).collect();
+        let kernel_offsets: Vec<isize> =
+            (0..ci).flat_map(|i| (0..kt).map(move |k| (k + i * t) as isize)).collect();
+        let bias: Vec<f32> = (0..co).map(|x| x as f32 * 10.0 - 20.0).collect();
+        let conv = PackedConv::<SConv4x4, f32>::new(co, kernel_offsets, data_offsets);
+        let found = unsafe {
+            let mut pa: Vec<f32> =
+                align::uninitialized(conv.packed_a_len(), conv.packed_a_alignment());
+            conv.pack_a(pa.as_mut_ptr(), filters.as_ptr(), (ci * kt) as isize, 1);
+            let mut c = vec![9999.0f32; co * n];
+            conv.conv_fused(
+                pa.as_ptr(),
+                data.as_ptr(),
+                c.as_mut_ptr(),
+                n as isize,
+                1,
+                &[FusedSpec::PerRowAdd(bias.clone()), FusedSpec::Map(relu)],
+            );
+            c
+        };
+        let mut expect = vec![0.0f32; co * n];
+        for o in 0..co {
+            for x in 0..n {
+                let mut sum = bias[o];
+                for i in 0..ci {
+                    for k in 0..kt {
+                        sum += filters[k + i * kt + o * ci * kt] * data[x + k + i * t];
+                    }
+                }
+                expect[x + o * n] = relu(sum);
+            }
+        }
+        assert_eq!(found, expect);
+    }
+}
@@ -4,6 +4,8 @@ use std::ops::{Add, Mul};
 
 use std::marker::PhantomData;
 
+use super::fused::{apply_fused, FusedSpec};
+
 pub trait MatMul<T: Copy + Add + Mul + Zero + Debug>: Send + Sync + Debug + objekt::Clone {
     fn packed_a_len(&self) -> usize;
     fn packed_a_alignment(&self) -> usize;
@@ -13,7 +15,19 @@ pub trait MatMul<T: Copy + Add + Mul + Zero + Debug>: Send + Sync + Debug + obje
     fn pack_b(&self, pb: *mut T, b: *const T, rsb: isize, csb: isize);
     fn write_b_packed_by_rows<'p>(&self, pb: &'p mut [T]) -> PackedWriter<'p, T>;
 
-    fn mat_mul_prepacked(&self, pa: *const T, pb: *const T, c: *mut T, rsc: isize, csc: isize);
+    fn mat_mul_prepacked(&self, pa: *const T, pb: *const T, c: *mut T, rsc: isize, csc: isize) {
+        self.mat_mul_prepacked_fused(pa, pb, c, rsc, csc, &[])
+    }
+
+    fn mat_mul_prepacked_fused(
+        &self,
+        pa: *const T,
+        pb: *const T,
+        c: *mut T,
+        rsc: isize,
+        csc: isize,
+        fused: &[FusedSpec<T>],
+    );
 }
 
 clone_trait_object!(<T> MatMul<T> where T: Copy + Add + Mul + Zero);
@@ -178,7 +192,15 @@ where
         PackedWriter::new(pb, K::nr(), self.n, self.k)
     }
 
-    fn mat_mul_prepacked(&self, pa: *const T, pb: *const T, c: *mut T, rsc: isize, csc: isize) {
+    fn mat_mul_prepacked_fused(
+        &self,
+        pa: *const T,
+        pb: *const T,
+        c: *mut T,
+        rsc: isize,
+        csc: isize,
+        fused: &[FusedSpec<T>],
+    ) {
         assert!(pa as usize % K::alignment_bytes_a() == 0);
         assert!(pb as usize % K::alignment_bytes_b() == 0);
         let mr = K::mr();
@@ -198,6 +220,14 @@ where
                         rsc as usize,
                         csc as usize,
                     );
+                    apply_fused(
+                        fused,
+                        c.offset((mr * ia) as isize * rsc + (nr * ib) as isize * csc),
+                        rsc,
+                        csc,
+                        (mr * ia, nr * ib),
+                        (mr, nr),
+                    );
                 }
                 if n % nr != 0 {
                     K::kernel(
@@ -208,6 +238,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (mr * ia, n / nr * nr),
+                        (mr, n % nr),
+                    );
                     for y in 0..mr {
                         for x in 0..(n % nr) {
                             *c.offset(
@@ -227,6 +265,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (m / mr * mr, ib * nr),
+                        (m % mr, nr),
+                    );
                     for y in 0..(m % mr) {
                         for x in 0..nr {
                             *c.offset(
@@ -244,6 +290,14 @@ where
                         nr,
                         1,
                     );
+                    apply_fused(
+                        fused,
+                        tmpc.as_mut_ptr(),
+                        nr as isize,
+                        1,
+                        (m / mr * mr, n / nr * nr),
+                        (m % mr, n % nr),
+                    );
                     for y in 0..(m % mr) {
                         for x in 0..(n % nr) {
                             *c.offset(
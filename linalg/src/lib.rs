@@ -22,7 +22,7 @@ pub mod arm64;
 pub mod arm32;
 
 pub use self::frame::{Conv, PackedConv};
-pub use self::frame::{MatMul, PackedMatMul};
+pub use self::frame::{FusedSpec, MatMul, PackedMatMul};
 
 pub struct Ops {
     pub smm: Box<Fn(usize, usize, usize) -> Box<MatMul<f32>> + Send + Sync>,
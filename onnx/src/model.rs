@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use tract_core::framework::{Framework, OpBuilder, OpRegister};
 use tract_core::model::*;
@@ -41,13 +41,9 @@ impl Framework<pb::NodeProto, pb::ModelProto> for Onnx {
             }
         }
         for pbnode in graph.get_node().iter() {
-            let name = if pbnode.get_name() != "" {
-                pbnode.get_name().to_string()
-            } else if pbnode.get_output().len() > 0 && pbnode.get_output()[0] != "" {
-                pbnode.get_output()[0].to_owned()
-            } else {
-                format!("{}-{}", model.nodes().len(), pbnode.get_op_type())
-            };
+            let name = node_name(pbnode)
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("{}-{}", model.nodes().len(), pbnode.get_op_type()));
             trace!("Creating node {}", name);
             let facts = (0..pbnode.get_output().len()).map(|_| TensorFact::default()).collect();
             trace!("  outputs {:?}", pbnode.get_output());
@@ -69,3 +65,133 @@ impl Framework<pb::NodeProto, pb::ModelProto> for Onnx {
         Ok(model)
     }
 }
+
+/// The name of the node made from `pbnode`, if it does not have to be
+/// generated.
+fn node_name(pbnode: &pb::NodeProto) -> Option<&str> {
+    if pbnode.get_name() != "" {
+        Some(pbnode.get_name())
+    } else if pbnode.get_output().len() > 0 && pbnode.get_output()[0] != "" {
+        Some(&pbnode.get_output()[0])
+    } else {
+        None
+    }
+}
+
+impl Onnx {
+    /// The name of the ONNX tensor for output `slot` of the node called
+    /// `node` in models built from `proto`.
+    pub fn tensor_name(
+        &self,
+        proto: &pb::ModelProto,
+        node: &str,
+        slot: usize,
+    ) -> TractResult<String> {
+        let graph = proto.get_graph();
+        if slot == 0 && graph.get_input().iter().any(|i| i.get_name() == node) {
+            return Ok(node.to_string());
+        }
+        graph
+            .get_node()
+            .iter()
+            .find(|n| node_name(n) == Some(node))
+            .and_then(|n| n.get_output().get(slot))
+            .map(|s| s.to_string())
+            .ok_or_else(|| format!("No tensor for output {} of node {}", slot, node).into())
+    }
+
+    /// Extracts the part of `proto` computing the `outputs` tensors from the
+    /// `inputs` tensors, as a standalone model. Tensors are given by name,
+    /// with the facts to declare them with.
+    pub fn extract_proto_model(
+        &self,
+        proto: &pb::ModelProto,
+        inputs: &[(String, TensorFact)],
+        outputs: &[(String, TensorFact)],
+    ) -> TractResult<pb::ModelProto> {
+        let graph = proto.get_graph();
+        let producers: HashMap<&str, usize> = graph
+            .get_node()
+            .iter()
+            .enumerate()
+            .flat_map(|(ix, n)| n.get_output().iter().map(move |o| (&**o, ix)))
+            .collect();
+        let cut: HashSet<&str> = inputs.iter().map(|i| &*i.0).collect();
+        let mut tensors = HashSet::new();
+        let mut kept = HashSet::new();
+        let mut todo: Vec<&str> = outputs.iter().map(|o| &*o.0).collect();
+        while let Some(tensor) = todo.pop() {
+            if tensor == "" || cut.contains(tensor) || !tensors.insert(tensor) {
+                continue;
+            }
+            if let Some(&ix) = producers.get(tensor) {
+                if kept.insert(ix) {
+                    todo.extend(graph.get_node()[ix].get_input().iter().map(|s| &**s));
+                }
+            }
+        }
+
+        let value_info = |name: &str, fact: &TensorFact| -> TractResult<pb::ValueInfoProto> {
+            let mut info = pb::ValueInfoProto::new();
+            info.set_name(name.to_string());
+            info.set_field_type(crate::tensor::onnx_type_for_fact(fact)?);
+            Ok(info)
+        };
+        let mut extracted = proto.clone();
+        {
+            let new_graph = extracted.mut_graph();
+            let nodes = graph
+                .get_node()
+                .iter()
+                .enumerate()
+                .filter(|(ix, _)| kept.contains(ix))
+                .map(|(_, n)| n.clone())
+                .collect();
+            new_graph.set_node(nodes);
+            let initializers = graph
+                .get_initializer()
+                .iter()
+                .filter(|i| tensors.contains(i.get_name()))
+                .cloned()
+                .collect();
+            new_graph.set_initializer(initializers);
+            let mut new_inputs = inputs
+                .iter()
+                .map(|(name, fact)| value_info(name, fact))
+                .collect::<TractResult<Vec<_>>>()?;
+            new_inputs.extend(
+                graph.get_input().iter().filter(|i| tensors.contains(i.get_name())).cloned(),
+            );
+            new_graph.set_input(new_inputs.into());
+            let new_outputs = outputs
+                .iter()
+                .map(|(name, fact)| {
+                    match graph.get_output().iter().find(|o| o.get_name() == name) {
+                        Some(o) => Ok(o.clone()),
+                        None => value_info(name, fact),
+                    }
+                })
+                .collect::<TractResult<Vec<_>>>()?;
+            new_graph.set_output(new_outputs.into());
+            let infos = graph
+                .get_value_info()
+                .iter()
+                .filter(|i| tensors.contains(i.get_name()))
+                .cloned()
+                .collect();
+            new_graph.set_value_info(infos);
+        }
+        Ok(extracted)
+    }
+
+    /// Writes `proto` in the ONNX protobuf format.
+    pub fn write_proto_model(
+        &self,
+        proto: &pb::ModelProto,
+        w: &mut std::io::Write,
+    ) -> TractResult<()> {
+        use protobuf::Message;
+        proto.write_to_writer(w).map_err(|e| format!("{:?}", e))?;
+        Ok(())
+    }
+}
@@ -37,6 +37,51 @@ impl Tractify<TensorProto_DataType> for DatumType {
     */
 }
 
+/// The ONNX element type for a datum type.
+pub fn onnx_datum_type(dt: DatumType) -> TractResult<TensorProto_DataType> {
+    use self::TensorProto_DataType::*;
+    match dt {
+        DatumType::Bool => Ok(BOOL),
+        DatumType::U8 => Ok(UINT8),
+        DatumType::U16 => Ok(UINT16),
+        DatumType::I8 => Ok(INT8),
+        DatumType::I16 => Ok(INT16),
+        DatumType::I32 => Ok(INT32),
+        DatumType::I64 => Ok(INT64),
+        DatumType::F16 => Ok(FLOAT16),
+        DatumType::F32 => Ok(FLOAT),
+        DatumType::F64 => Ok(DOUBLE),
+        DatumType::String => Ok(STRING),
+        DatumType::TDim => bail!("Dimension is not translatable in protobuf"),
+    }
+}
+
+/// The ONNX type for a tensor fact. Unknown dimensions are left empty, and
+/// the streaming dimension becomes the "S" parameter.
+pub fn onnx_type_for_fact(fact: &TensorFact) -> TractResult<TypeProto> {
+    use tract_core::analyser::types::Fact;
+    let mut tensor = TypeProto_Tensor::new();
+    if let Some(dt) = fact.datum_type.concretize() {
+        tensor.set_elem_type(onnx_datum_type(dt)?);
+    }
+    if !fact.shape.is_open() {
+        let mut shape = TensorShapeProto::new();
+        for d in fact.shape.dims() {
+            let mut dim = TensorShapeProto_Dimension::new();
+            match d.concretize().map(|d| d.to_integer()) {
+                Some(Ok(d)) => dim.set_dim_value(d as i64),
+                Some(Err(_)) => dim.set_dim_param("S".to_string()),
+                None => (),
+            }
+            shape.mut_dim().push(dim);
+        }
+        tensor.set_shape(shape);
+    }
+    let mut typ = TypeProto::new();
+    typ.set_tensor_type(tensor);
+    Ok(typ)
+}
+
 impl Tractify<TypeProto_Tensor> for TensorFact {
     fn tractify(t: &TypeProto_Tensor) -> TractResult<TensorFact> {
         let mut fact = TensorFact::default();
@@ -4,7 +4,7 @@ pub fn build(_pb: &crate::tfpb::node_def::NodeDef) -> TractResult<Box<Op>> {
     Ok(Box::new(ExpandDims))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExpandDims;
 
 impl Op for ExpandDims {
@@ -12,6 +12,8 @@ impl Op for ExpandDims {
         "tf.ExpandDims".into()
     }
 
+    impl_op_same_as!();
+
     fn declutter(&self, model: &TypedModel, node: &TypedNode) -> TractResult<Option<TypedModelPatch>> {
         let mut inputs = model.node_input_facts(node.id)?;
         let (_, dims) = args_2!(inputs);
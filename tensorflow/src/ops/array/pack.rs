@@ -9,7 +9,7 @@ pub fn pack(pb: &crate::tfpb::node_def::NodeDef) -> TractResult<Box<Op>> {
     Ok(Box::new(Pack::new(dtype, n, axis)))
 }
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Pack {
     t: DatumType,
     n: usize, // The number of inputs
@@ -37,6 +37,8 @@ impl Op for Pack {
     fn name(&self) -> Cow<str> {
         "tf.Pack".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl StatelessOp for Pack {
@@ -1,7 +1,7 @@
 use ndarray::prelude::*;
 use tract_core::ops::prelude::*;
 
-#[derive(Debug, Clone, new)]
+#[derive(Debug, Clone, PartialEq, new)]
 pub struct Reshape<T: Datum>(PhantomData<T>);
 
 pub fn reshape(pb: &crate::tfpb::node_def::NodeDef) -> TractResult<Box<Op>> {
@@ -35,6 +35,8 @@ impl<T: Datum> Op for Reshape<T> {
     fn name(&self) -> Cow<str> {
         "tf.Reshape".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl<T: Datum> StatelessOp for Reshape<T> {
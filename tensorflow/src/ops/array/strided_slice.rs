@@ -16,7 +16,7 @@ pub fn build(pb: &crate::tfpb::node_def::NodeDef) -> TractResult<Box<Op>> {
     }
 }
 
-#[derive(Debug, Default, Clone, new)]
+#[derive(Debug, Default, Clone, PartialEq, new)]
 pub struct BaseStridedSlice {
     begin_mask: i64,
     end_mask: i64,
@@ -237,7 +237,7 @@ impl BaseStridedSlice {
     }
 }
 
-#[derive(Debug, Default, Clone, new)]
+#[derive(Debug, Default, Clone, PartialEq, new)]
 pub struct StridedSlice<T: Copy + Datum> {
     base: BaseStridedSlice,
     _phantom: PhantomData<T>,
@@ -254,6 +254,8 @@ impl<T: Copy + Datum> Op for StridedSlice<T> {
         "tf.StridedSlice".into()
     }
 
+    impl_op_same_as!();
+
     fn declutter(&self, model: &TypedModel, node: &TypedNode) -> TractResult<Option<TypedModelPatch>> {
         let mut inputs = model.node_input_facts(node.id)?;
         let (input, begin, end, strides) = args_4!(inputs);
@@ -304,7 +306,7 @@ impl<T: Copy + Datum> InferenceRulesOp for StridedSlice<T> {
     }
 }
 
-#[derive(Debug, Default, Clone, new)]
+#[derive(Debug, Default, Clone, PartialEq, new)]
 pub struct StridedSliceD {
     base: BaseStridedSlice,
 }
@@ -324,6 +326,8 @@ impl Op for StridedSliceD {
     fn name(&self) -> Cow<str> {
         "tf.StridedSliceD".into()
     }
+
+    impl_op_same_as!();
 }
 
 impl InferenceRulesOp for StridedSliceD {
@@ -1,4 +1,5 @@
 use crate::tfpb::node_def::NodeDef;
+use tract_core::ops::nn::{BatchNorm, DataFormat};
 use tract_core::ops::prelude::*;
 
 pub fn fused_batch_norm(node: &NodeDef) -> TractResult<Box<Op>> {
@@ -19,6 +20,20 @@ impl Op for FusedBatchNorm {
     fn rounding_errors(&self) -> bool {
         true
     }
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        let op = BatchNorm::new(DataFormat::NHWC, self.epsilon, true);
+        Ok(Some(TypedModelPatch::replace_single_op(
+            model,
+            node,
+            node.inputs.iter().cloned().collect(),
+            op,
+        )?))
+    }
 }
 
 impl StatelessOp for FusedBatchNorm {